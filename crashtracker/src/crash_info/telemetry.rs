@@ -121,6 +121,11 @@ impl TelemetryCrashUploader {
             |ts| ts.timestamp() as u64,
         );
 
+        let mut log = data::Log::new(message, LogLevel::Error);
+        log.stack_trace = Some(stack_trace);
+        log.tags = tags;
+        log.is_sensitive = true;
+        log.is_crash = true;
         let payload = data::Telemetry {
             tracer_time,
             api_version: ddtelemetry::data::ApiVersion::V2,
@@ -128,14 +133,7 @@ impl TelemetryCrashUploader {
             seq_id: 1,
             application: &metadata.application,
             host: &metadata.host,
-            payload: &data::Payload::Logs(vec![data::Log {
-                message,
-                level: LogLevel::Error,
-                stack_trace: Some(stack_trace),
-                tags,
-                is_sensitive: true,
-                count: 1,
-            }]),
+            payload: &data::Payload::Logs(vec![log]),
             origin: Some("Crashtracker"),
         };
         let client = ddtelemetry::worker::http_client::from_config(&self.cfg);
@@ -10,9 +10,10 @@ use ddcommon::{connector::Connector, Endpoint};
 use hyper::body::HttpBody;
 use hyper::{self, body::Buf, header::HeaderName};
 use log::{error, info};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::sleep;
 
 #[allow(clippy::declare_interior_mutable_const)]
 const DATADOG_AGENT_STATE: HeaderName = HeaderName::from_static("datadog-agent-state");
@@ -39,7 +40,7 @@ pub async fn fetch_info_with_state(
         .into_request_builder(concat!("Libdatadog/", env!("CARGO_PKG_VERSION")))?
         .method(hyper::Method::GET)
         .body(hyper::Body::empty());
-    let client = hyper::Client::builder().build(Connector::default());
+    let client = hyper::Client::builder().build(Connector::for_endpoint(info_endpoint));
     let res = client.request(req?).await?;
     let new_state_hash = res
         .headers()
@@ -86,6 +87,23 @@ pub async fn fetch_info(info_endpoint: &Endpoint) -> Result<Box<AgentInfo>> {
     }
 }
 
+/// Abstraction over waiting between two fetches of the info endpoint, allowing tests to control
+/// the passing of time.
+pub trait Sleeper: Send + Sync {
+    /// Returns a future completing once `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
+}
+
+/// The default [`Sleeper`], relying on the tokio timer.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioSleeper;
+
+impl Sleeper for TokioSleeper {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
 /// Fetch the info endpoint and update an ArcSwap keeping it up-to-date.
 ///
 /// Once the fetcher has been created you can get an Arc of the config by calling `get_info`.
@@ -125,6 +143,7 @@ pub struct AgentInfoFetcher {
     info_endpoint: Endpoint,
     info: AgentInfoArc,
     refresh_interval: Duration,
+    sleeper: Box<dyn Sleeper>,
 }
 
 impl AgentInfoFetcher {
@@ -135,9 +154,17 @@ impl AgentInfoFetcher {
             info_endpoint,
             info: Arc::new(ArcSwapOption::new(None)),
             refresh_interval,
+            sleeper: Box::new(TokioSleeper),
         }
     }
 
+    /// Use the given sleeper to wait between fetches instead of the tokio timer, e.g. to control
+    /// time in tests.
+    pub fn with_sleeper(mut self, sleeper: impl Sleeper + 'static) -> Self {
+        self.sleeper = Box::new(sleeper);
+        self
+    }
+
     /// Start fetching the info endpoint with the given interval.
     ///
     /// # Warning
@@ -159,7 +186,7 @@ impl AgentInfoFetcher {
                     error!("Error while fetching /info: {}", err);
                 }
             }
-            sleep(self.refresh_interval).await;
+            self.sleeper.sleep(self.refresh_interval).await;
         }
     }
 
@@ -174,7 +201,25 @@ impl AgentInfoFetcher {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agent_info::schema::AgentInfoDiff;
     use httpmock::prelude::*;
+    use tokio::sync::{mpsc, Mutex};
+
+    /// A sleeper only completing when the test advances time by sending a tick, and reporting
+    /// every requested sleep.
+    struct ManualSleeper {
+        sleeps: mpsc::UnboundedSender<Duration>,
+        ticks: Mutex<mpsc::UnboundedReceiver<()>>,
+    }
+
+    impl Sleeper for ManualSleeper {
+        fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+            Box::pin(async move {
+                let _ = self.sleeps.send(duration);
+                self.ticks.lock().await.recv().await;
+            })
+        }
+    }
 
     const TEST_INFO: &str = r#"{
         "version": "0.0.0",
@@ -311,6 +356,138 @@ mod tests {
                 info: serde_json::from_str(TEST_INFO).unwrap(),
             }
         );
+        assert_eq!(agent_info.max_request_bytes(), Some(26214400));
+    }
+
+    #[test]
+    fn test_agent_info_endpoints() {
+        let agent_info = AgentInfo {
+            state_hash: TEST_INFO_HASH.to_string(),
+            info: serde_json::from_str(TEST_INFO).unwrap(),
+        };
+        assert_eq!(agent_info.endpoint_set().len(), 2);
+        assert!(agent_info.has_endpoint("/v0.4/traces"));
+        assert!(agent_info.has_endpoint("/v0.6/stats"));
+        assert!(!agent_info.has_endpoint("/v0.5/traces"));
+        assert!(!agent_info.has_endpoint("/evp_proxy/v4"));
+        // Computed once, and not taken into account when comparing infos
+        assert!(std::ptr::eq(
+            agent_info.endpoint_set(),
+            agent_info.endpoint_set()
+        ));
+        assert_eq!(
+            agent_info,
+            AgentInfo {
+                state_hash: TEST_INFO_HASH.to_string(),
+                info: serde_json::from_str(TEST_INFO).unwrap(),
+            }
+        );
+
+        let empty_info = AgentInfo::default();
+        assert!(empty_info.endpoint_set().is_empty());
+        assert!(!empty_info.has_endpoint("/v0.4/traces"));
+    }
+
+    #[test]
+    fn test_dogstatsd_endpoint() {
+        let info = AgentInfo {
+            state_hash: TEST_INFO_HASH.to_string(),
+            info: serde_json::from_str(TEST_INFO).unwrap(),
+        };
+        let endpoint = info.dogstatsd_endpoint("localhost").unwrap();
+        assert_eq!(endpoint.url.scheme_str(), Some("udp"));
+        assert_eq!(endpoint.url.host(), Some("localhost"));
+        assert_eq!(endpoint.url.port_u16(), Some(8125));
+
+        let endpoint = info.dogstatsd_endpoint("::1").unwrap();
+        assert_eq!(endpoint.url.host(), Some("[::1]"));
+        assert_eq!(endpoint.url.port_u16(), Some(8125));
+
+        let mut no_port: serde_json::Value = serde_json::from_str(TEST_INFO).unwrap();
+        no_port["config"]["statsd_port"] = serde_json::json!(0);
+        let no_port = AgentInfo {
+            state_hash: "no-port".to_string(),
+            info: serde_json::from_value(no_port).unwrap(),
+        };
+        assert!(no_port.dogstatsd_endpoint("localhost").is_none());
+        assert!(AgentInfo::default()
+            .dogstatsd_endpoint("localhost")
+            .is_none());
+    }
+
+    #[test]
+    fn test_agent_info_diff() {
+        let old_info = AgentInfo {
+            state_hash: TEST_INFO_HASH.to_string(),
+            info: serde_json::from_str(TEST_INFO).unwrap(),
+        };
+        assert!(old_info.diff(&old_info).is_empty());
+
+        let mut new_info: serde_json::Value = serde_json::from_str(TEST_INFO).unwrap();
+        new_info["endpoints"] = serde_json::json!(["/v0.4/traces"]);
+        new_info["feature_flags"] = serde_json::json!(["discovery"]);
+        new_info["config"]["statsd_port"] = serde_json::json!(8135);
+        let new_info = AgentInfo {
+            state_hash: "new-hash".to_string(),
+            info: serde_json::from_value(new_info).unwrap(),
+        };
+
+        let diff = old_info.diff(&new_info);
+        assert_eq!(
+            diff,
+            AgentInfoDiff {
+                added_endpoints: vec![],
+                removed_endpoints: vec!["/v0.6/stats".to_string()],
+                added_feature_flags: vec!["discovery".to_string()],
+                removed_feature_flags: vec![],
+                changed_config_fields: vec!["statsd_port"],
+            }
+        );
+
+        let reverse_diff = new_info.diff(&old_info);
+        assert_eq!(
+            reverse_diff.added_endpoints,
+            vec!["/v0.6/stats".to_string()]
+        );
+        assert_eq!(
+            reverse_diff.removed_feature_flags,
+            vec!["discovery".to_string()]
+        );
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_agent_info_fetcher_interval() {
+        let server = MockServer::start();
+        let mock = server
+            .mock_async(|when, then| {
+                when.path("/info");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .header("datadog-agent-state", "1")
+                    .body(r#"{"version":"1"}"#);
+            })
+            .await;
+        let endpoint = Endpoint::from_url(server.url("/info").parse().unwrap());
+        let (sleeps_tx, mut sleeps_rx) = mpsc::unbounded_channel();
+        let (ticks_tx, ticks_rx) = mpsc::unbounded_channel();
+        let fetcher =
+            AgentInfoFetcher::new(endpoint, Duration::from_secs(5)).with_sleeper(ManualSleeper {
+                sleeps: sleeps_tx,
+                ticks: Mutex::new(ticks_rx),
+            });
+        tokio::spawn(async move {
+            fetcher.run().await;
+        });
+
+        // The fetcher fetches once immediately, then once per elapsed interval
+        assert_eq!(sleeps_rx.recv().await, Some(Duration::from_secs(5)));
+        assert_eq!(mock.hits_async().await, 1);
+        for elapsed_intervals in 1..=3 {
+            ticks_tx.send(()).unwrap();
+            assert_eq!(sleeps_rx.recv().await, Some(Duration::from_secs(5)));
+            assert_eq!(mock.hits_async().await, elapsed_intervals + 1);
+        }
     }
 
     #[cfg_attr(miri, ignore)]
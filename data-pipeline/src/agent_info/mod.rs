@@ -14,4 +14,6 @@ mod fetcher;
 /// Stores an AgentInfo in an ArcSwap to be updated by an AgentInfoFetcher
 pub type AgentInfoArc = Arc<ArcSwapOption<schema::AgentInfo>>;
 
-pub use fetcher::{fetch_info, fetch_info_with_state, AgentInfoFetcher, FetchInfoStatus};
+pub use fetcher::{
+    fetch_info, fetch_info_with_state, AgentInfoFetcher, FetchInfoStatus, Sleeper, TokioSleeper,
+};
@@ -1,11 +1,13 @@
 // Copyright 2024-Present Datadog, Inc. https://www.datadoghq.com/
 // SPDX-License-Identifier: Apache-2.0
 //! This module provides struct representing the info endpoint response
+use ddcommon::{parse_uri, Endpoint};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
 
 /// Wrapper for an agent info response storing the state hash from the agent
-#[derive(Clone, Deserialize, Default, Debug, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, Default, Debug, PartialEq)]
 pub struct AgentInfo {
     /// Hash of the info
     pub state_hash: String,
@@ -13,6 +15,97 @@ pub struct AgentInfo {
     pub info: AgentInfoStruct,
 }
 
+impl AgentInfo {
+    /// Set of the endpoints exposed by the agent, e.g. to check for several endpoints at once.
+    pub fn endpoint_set(&self) -> &HashSet<String> {
+        self.info.endpoint_set()
+    }
+
+    /// Returns true if the agent exposes the given endpoint, e.g. `/v0.6/stats`.
+    pub fn has_endpoint(&self, endpoint: &str) -> bool {
+        self.endpoint_set().contains(endpoint)
+    }
+
+    /// The maximum size of a request accepted by the agent, if advertised.
+    pub fn max_request_bytes(&self) -> Option<i64> {
+        self.info.max_request_bytes()
+    }
+
+    /// The UDP dogstatsd endpoint of the agent, built from the statsd port it advertises and
+    /// `agent_host`, the host the agent is reached at. Returns `None` if the agent advertises no
+    /// valid port.
+    pub fn dogstatsd_endpoint(&self, agent_host: &str) -> Option<Endpoint> {
+        let port = self.info.config.as_ref()?.statsd_port?;
+        let port = u16::try_from(port).ok().filter(|port| *port != 0)?;
+        let url = if agent_host.contains(':') && !agent_host.starts_with('[') {
+            format!("udp://[{agent_host}]:{port}")
+        } else {
+            format!("udp://{agent_host}:{port}")
+        };
+        parse_uri(&url).ok().map(Endpoint::from_url)
+    }
+
+    /// Computes what changed between this info and a newer `other` one, e.g. to only react to a
+    /// newly enabled feature flag when the agent info is updated.
+    pub fn diff(&self, other: &AgentInfo) -> AgentInfoDiff {
+        let (added_endpoints, removed_endpoints) =
+            list_diff(&self.info.endpoints, &other.info.endpoints);
+        let (added_feature_flags, removed_feature_flags) =
+            list_diff(&self.info.feature_flags, &other.info.feature_flags);
+        let default_config = Config::default();
+        let changed_config_fields = self
+            .info
+            .config
+            .as_ref()
+            .unwrap_or(&default_config)
+            .changed_fields(other.info.config.as_ref().unwrap_or(&default_config));
+        AgentInfoDiff {
+            added_endpoints,
+            removed_endpoints,
+            added_feature_flags,
+            removed_feature_flags,
+            changed_config_fields,
+        }
+    }
+}
+
+/// Changes between two agent infos, computed by [`AgentInfo::diff`]. Lists are sorted.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct AgentInfoDiff {
+    /// Endpoints only advertised by the newer info
+    pub added_endpoints: Vec<String>,
+    /// Endpoints only advertised by the older info
+    pub removed_endpoints: Vec<String>,
+    /// Feature flags only enabled in the newer info
+    pub added_feature_flags: Vec<String>,
+    /// Feature flags only enabled in the older info
+    pub removed_feature_flags: Vec<String>,
+    /// Names of the fields of [`Config`] with a different value, e.g. `statsd_port`
+    pub changed_config_fields: Vec<&'static str>,
+}
+
+impl AgentInfoDiff {
+    /// Returns true if neither the endpoints, the feature flags nor the config changed.
+    pub fn is_empty(&self) -> bool {
+        self.added_endpoints.is_empty()
+            && self.removed_endpoints.is_empty()
+            && self.added_feature_flags.is_empty()
+            && self.removed_feature_flags.is_empty()
+            && self.changed_config_fields.is_empty()
+    }
+}
+
+/// Returns the sorted items only present in `new` and the ones only present in `old`.
+fn list_diff(old: &Option<Vec<String>>, new: &Option<Vec<String>>) -> (Vec<String>, Vec<String>) {
+    let old: HashSet<&String> = old.iter().flatten().collect();
+    let new: HashSet<&String> = new.iter().flatten().collect();
+    let mut added: Vec<String> = new.difference(&old).map(|s| s.to_string()).collect();
+    let mut removed: Vec<String> = old.difference(&new).map(|s| s.to_string()).collect();
+    added.sort();
+    removed.sort();
+    (added, removed)
+}
+
 /// Schema of an agent info response
 #[allow(missing_docs)]
 #[derive(Clone, Serialize, Deserialize, Default, Debug, PartialEq)]
@@ -35,6 +128,32 @@ pub struct AgentInfoStruct {
     pub peer_tags: Option<Vec<String>>,
     /// List of span kinds eligible for stats computation
     pub span_kinds_stats_computed: Option<Vec<String>>,
+    #[serde(skip)]
+    endpoint_set: EndpointSetCache,
+}
+
+/// The set of `endpoints`, computed on first use. Not part of the info when comparing.
+#[derive(Clone, Default, Debug)]
+struct EndpointSetCache(OnceLock<HashSet<String>>);
+
+impl PartialEq for EndpointSetCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl AgentInfoStruct {
+    /// Set of the endpoints exposed by the agent, computed once for constant time lookups.
+    pub fn endpoint_set(&self) -> &HashSet<String> {
+        self.endpoint_set
+            .0
+            .get_or_init(|| self.endpoints.iter().flatten().cloned().collect())
+    }
+
+    /// The maximum size of a request accepted by the agent, if advertised.
+    pub fn max_request_bytes(&self) -> Option<i64> {
+        self.config.as_ref()?.max_request_bytes
+    }
 }
 
 #[allow(missing_docs)]
@@ -54,6 +173,34 @@ pub struct Config {
     pub analyzed_spans_by_service: Option<HashMap<String, HashMap<String, f64>>>,
 }
 
+impl Config {
+    /// Names of the fields with a different value in `other`.
+    fn changed_fields(&self, other: &Config) -> Vec<&'static str> {
+        macro_rules! changed_fields {
+            ($($field:ident),*) => {
+                [$((stringify!($field), self.$field != other.$field)),*]
+            };
+        }
+        changed_fields!(
+            default_env,
+            target_tps,
+            max_eps,
+            receiver_port,
+            receiver_socket,
+            connection_limit,
+            receiver_timeout,
+            max_request_bytes,
+            statsd_port,
+            max_memory,
+            max_cpu,
+            analyzed_spans_by_service
+        )
+        .into_iter()
+        .filter_map(|(field, changed)| changed.then_some(field))
+        .collect()
+    }
+}
+
 #[allow(missing_docs)]
 #[derive(Clone, Deserialize, Default, Debug, PartialEq)]
 pub struct ObfuscationConfig {
@@ -49,13 +49,14 @@ impl StatsExporter {
         endpoint: Endpoint,
         cancellation_token: CancellationToken,
     ) -> Self {
+        let client = hyper::Client::builder().build(connector::Connector::for_endpoint(&endpoint));
         Self {
             flush_interval,
             concentrator,
             endpoint,
             meta,
             sequence_id: AtomicU64::new(0),
-            client: hyper::Client::builder().build(connector::Connector::default()),
+            client,
             cancellation_token,
         }
     }
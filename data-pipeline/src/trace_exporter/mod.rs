@@ -486,7 +486,7 @@ impl TraceExporter {
                 .unwrap();
 
             match hyper::Client::builder()
-                .build(connector::Connector::default())
+                .build(connector::Connector::for_endpoint(&self.endpoint))
                 .request(req)
                 .await
             {
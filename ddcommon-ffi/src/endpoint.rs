@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::slice::AsBytes;
-use crate::Error;
+use crate::{Error, MaybeError, StringWrapper};
 use ddcommon::{parse_uri, Endpoint};
 use hyper::http::uri::{Authority, Parts};
 use std::borrow::Cow;
@@ -73,6 +73,30 @@ extern "C" fn ddog_endpoint_set_test_token(endpoint: &mut Endpoint, token: crate
     };
 }
 
+/// Serializes the whole endpoint configuration (url, api key, timeout and test token) to JSON,
+/// so that it can be restored with `ddog_endpoint_deserialize`.
+#[no_mangle]
+#[must_use]
+pub extern "C" fn ddog_endpoint_serialize(endpoint: &Endpoint) -> StringWrapper {
+    // Endpoint only consists of strings and integers, this serialization cannot fail
+    serde_json::to_string(endpoint).unwrap_or_default().into()
+}
+
+/// Restores an endpoint previously serialized with `ddog_endpoint_serialize`.
+#[no_mangle]
+pub extern "C" fn ddog_endpoint_deserialize(
+    json: crate::CharSlice,
+    endpoint: &mut *mut Endpoint,
+) -> MaybeError {
+    match serde_json::from_slice::<Endpoint>(json.as_bytes()) {
+        Ok(e) => {
+            *endpoint = Box::into_raw(Box::new(e));
+            MaybeError::None
+        }
+        Err(e) => MaybeError::Some(Error::from(format!("invalid serialized endpoint: {e}"))),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn ddog_endpoint_drop(_: Box<Endpoint>) {}
 
@@ -124,4 +148,25 @@ mod tests {
         ddog_endpoint_set_timeout(&mut endpoint_api_key, 2000);
         assert_eq!(endpoint_api_key.timeout_ms, 2000);
     }
+
+    #[test]
+    fn serialize_round_trip() {
+        let mut endpoint =
+            ddog_endpoint_from_url(CharSlice::from("http://127.0.0.1:8126/")).unwrap();
+        endpoint.api_key = Some("test-key".into());
+        ddog_endpoint_set_timeout(&mut endpoint, 1234);
+        ddog_endpoint_set_test_token(&mut endpoint, CharSlice::from("session-token"));
+
+        let serialized = ddog_endpoint_serialize(&endpoint);
+        let mut restored = std::ptr::null_mut();
+        let result = ddog_endpoint_deserialize(CharSlice::from(serialized.as_ref()), &mut restored);
+        assert!(matches!(result, MaybeError::None));
+        let restored = unsafe { Box::from_raw(restored) };
+        assert_eq!(*restored, *endpoint);
+
+        let mut invalid = std::ptr::null_mut();
+        let result = ddog_endpoint_deserialize(CharSlice::from("{"), &mut invalid);
+        assert!(matches!(result, MaybeError::Some(_)));
+        assert!(invalid.is_null());
+    }
 }
@@ -21,7 +21,9 @@ pub use error::*;
 pub use handle::*;
 pub use option::*;
 pub use result::*;
-pub use slice::{CharSlice, Slice};
+pub use slice::{
+    char_slice_to_string_checked, char_slice_to_string_logged, CharSlice, Slice, Utf8LossyWarning,
+};
 pub use string::*;
 pub use timespec::*;
 pub use vec::Vec;
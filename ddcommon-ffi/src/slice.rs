@@ -211,6 +211,49 @@ impl<'a> From<&'a str> for Slice<'a, c_char> {
     }
 }
 
+/// Returned by [`char_slice_to_string_checked`] when the slice is not valid UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Utf8LossyWarning {
+    /// The string with the invalid sequences replaced by U+FFFD, still usable by callers which
+    /// only want to report the corruption.
+    pub lossy: String,
+    /// The length of the valid UTF-8 prefix of the slice.
+    pub valid_up_to: usize,
+}
+
+impl Display for Utf8LossyWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid UTF-8 after byte {} was replaced: {:?}",
+            self.valid_up_to, self.lossy
+        )
+    }
+}
+
+impl std::error::Error for Utf8LossyWarning {}
+
+/// Converts the slice to an owned string, like `to_utf8_lossy`, but reports whether invalid
+/// UTF-8 sequences had to be replaced, which would otherwise silently corrupt the string.
+pub fn char_slice_to_string_checked(slice: CharSlice) -> Result<String, Utf8LossyWarning> {
+    match slice.try_to_utf8() {
+        Ok(str) => Ok(str.to_string()),
+        Err(e) => Err(Utf8LossyWarning {
+            lossy: slice.to_utf8_lossy().into_owned(),
+            valid_up_to: e.valid_up_to(),
+        }),
+    }
+}
+
+/// Converts the slice to an owned string, logging a warning mentioning `what` the string is if
+/// invalid UTF-8 sequences had to be replaced.
+pub fn char_slice_to_string_logged(slice: CharSlice, what: &str) -> String {
+    char_slice_to_string_checked(slice).unwrap_or_else(|warning| {
+        log::warn!("Invalid {what}: {warning}");
+        warning.lossy
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -284,7 +327,7 @@ mod tests {
             len: 0,
             _marker: PhantomData,
         };
-        assert_eq!(null_len0.as_slice(), &[]);
+        assert_eq!(null_len0.as_slice(), &[] as &[u8]);
     }
 
     #[should_panic]
@@ -308,4 +351,20 @@ mod tests {
         };
         _ = dangerous.as_slice();
     }
+
+    #[test]
+    fn test_char_slice_to_string_checked() {
+        let valid = CharSlice::from("service-é");
+        assert_eq!(char_slice_to_string_checked(valid).unwrap(), "service-é");
+
+        let bytes: &[u8] = b"web\xffapp";
+        let invalid = unsafe { CharSlice::from_raw_parts(bytes.as_ptr().cast(), bytes.len()) };
+        let warning = char_slice_to_string_checked(invalid).unwrap_err();
+        assert_eq!(warning.lossy, "web\u{FFFD}app");
+        assert_eq!(warning.valid_up_to, 3);
+        assert_eq!(
+            char_slice_to_string_logged(invalid, "service"),
+            "web\u{FFFD}app"
+        );
+    }
 }
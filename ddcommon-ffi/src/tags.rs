@@ -1,7 +1,7 @@
 // Copyright 2022-Present Datadog, Inc. https://www.datadoghq.com/
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::slice::{AsBytes, CharSlice};
+use crate::slice::{char_slice_to_string_logged, AsBytes, CharSlice};
 use crate::Error;
 use ddcommon::tag::{parse_tags, Tag};
 
@@ -22,7 +22,8 @@ pub enum PushTagResult {
 
 /// Creates a new Tag from the provided `key` and `value` by doing a utf8
 /// lossy conversion, and pushes into the `vec`. The strings `key` and `value`
-/// are cloned to avoid FFI lifetime issues.
+/// are cloned to avoid FFI lifetime issues. A warning is logged if invalid
+/// utf8 had to be replaced.
 ///
 /// # Safety
 /// The `vec` must be a valid reference.
@@ -35,8 +36,8 @@ pub unsafe extern "C" fn ddog_Vec_Tag_push(
     key: CharSlice,
     value: CharSlice,
 ) -> PushTagResult {
-    let key = key.to_utf8_lossy().into_owned();
-    let value = value.to_utf8_lossy().into_owned();
+    let key = char_slice_to_string_logged(key, "tag key");
+    let value = char_slice_to_string_logged(value, "tag value");
     match Tag::new(key, value) {
         Ok(tag) => {
             vec.push(tag);
@@ -1,6 +1,7 @@
 // Copyright 2021-Present Datadog, Inc. https://www.datadoghq.com/
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::tag::Tag;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::env;
@@ -248,6 +249,29 @@ impl AzureMetadata {
     pub fn get_function_runtime_version(&self) -> &str {
         get_value_or_unknown!(self.function_runtime_version)
     }
+
+    /// Returns the standard `aas.*` tags describing this app service. Tags with a value which
+    /// cannot be used as a tag are skipped.
+    pub fn tags(&self) -> Vec<Tag> {
+        [
+            ("aas.resource.id", self.get_resource_id()),
+            (
+                "aas.environment.extension_version",
+                self.get_extension_version(),
+            ),
+            ("aas.environment.instance_id", self.get_instance_id()),
+            ("aas.environment.instance_name", self.get_instance_name()),
+            ("aas.environment.os", self.get_operating_system()),
+            ("aas.resource.group", self.get_resource_group()),
+            ("aas.site.name", self.get_site_name()),
+            ("aas.site.kind", self.get_site_kind()),
+            ("aas.site.type", self.get_site_type()),
+            ("aas.subscription.id", self.get_subscription_id()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Tag::new(name, value).ok())
+        .collect()
+    }
 }
 
 pub fn get_metadata() -> &'static Option<AzureMetadata> {
@@ -257,6 +281,15 @@ pub fn get_metadata() -> &'static Option<AzureMetadata> {
     &AAS_METATDATA
 }
 
+/// Returns the standard `aas.*` tags of the app service the process is running in, see
+/// [`AzureMetadata::tags`]. Empty when not running in Azure App Services.
+pub fn aas_tags() -> Vec<Tag> {
+    get_metadata()
+        .as_ref()
+        .map(AzureMetadata::tags)
+        .unwrap_or_default()
+}
+
 pub fn get_function_metadata() -> &'static Option<AzureMetadata> {
     lazy_static! {
         static ref AAS_METATDATA: Option<AzureMetadata> = AzureMetadata::new_function(RealEnv {});
@@ -648,6 +681,41 @@ mod tests {
         assert_eq!(expected_runtime_version, metadata.get_runtime_version());
     }
 
+    #[test]
+    fn test_tags() {
+        let mocked_env = MockEnv::new(&[
+            (SERVICE_CONTEXT, "1"),
+            (WEBSITE_ONWER_NAME, "sub-id+my-rg-EastUSwebspace"),
+            (WEBSITE_SITE_NAME, "my-site"),
+            (WEBSITE_OS, "linux"),
+            (INSTANCE_ID, "my-instance-id"),
+        ]);
+        let metadata = AzureMetadata::new(mocked_env).unwrap();
+
+        let tags: Vec<String> = metadata.tags().iter().map(Tag::to_string).collect();
+        assert_eq!(
+            tags,
+            [
+                "aas.resource.id:/subscriptions/sub-id/resourcegroups/my-rg/providers/microsoft.web/sites/my-site",
+                "aas.environment.extension_version:unknown",
+                "aas.environment.instance_id:my-instance-id",
+                "aas.environment.instance_name:unknown",
+                "aas.environment.os:linux",
+                "aas.resource.group:my-rg",
+                "aas.site.name:my-site",
+                "aas.site.kind:app",
+                "aas.site.type:app",
+                "aas.subscription.id:sub-id",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aas_tags_off_aas() {
+        // The tests are not running in Azure App Services
+        assert!(aas_tags().is_empty());
+    }
+
     #[test]
     fn test_get_trimmed_env_var_empty_string() {
         env::remove_var("TEST_VAR_NONE");
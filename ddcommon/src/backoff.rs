@@ -0,0 +1,131 @@
+// Copyright 2024-Present Datadog, Inc. https://www.datadoghq.com/
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Exponential backoff with jitter, for spacing out retries.
+///
+/// Each call to [`Backoff::next`] returns the current delay and multiplies it by `factor` for the
+/// next call, never exceeding `max`. The returned delay is then randomly shortened by up to
+/// `jitter` (a fraction between 0 and 1) of itself, so that clients retrying at the same time
+/// spread out.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    factor: f64,
+    jitter: f64,
+    current: Duration,
+    rng_state: u64,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration, factor: f64, jitter: f64) -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        Self::with_seed(base, max, factor, jitter, seed)
+    }
+
+    /// Like [`Backoff::new`], with a fixed seed for the jitter, making the sequence of delays
+    /// deterministic.
+    pub fn with_seed(base: Duration, max: Duration, factor: f64, jitter: f64, seed: u64) -> Self {
+        Backoff {
+            base,
+            max,
+            factor: factor.max(1.0),
+            jitter: jitter.clamp(0.0, 1.0),
+            current: base.min(max),
+            rng_state: seed,
+        }
+    }
+
+    /// Returns the delay to wait before the next attempt and advances the backoff.
+    // Not an iterator: the sequence never ends.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = Duration::try_from_secs_f64(delay.as_secs_f64() * self.factor)
+            .map_or(self.max, |next| next.min(self.max));
+        if self.jitter == 0.0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 - self.jitter * self.next_random())
+    }
+
+    /// Starts over from the base delay, e.g. after a successful attempt.
+    pub fn reset(&mut self) {
+        self.current = self.base.min(self.max);
+    }
+
+    /// Uniformly distributed in [0, 1), via splitmix64.
+    fn next_random(&mut self) -> f64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geometric_growth_and_cap() {
+        let mut backoff = Backoff::with_seed(
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+            2.0,
+            0.0,
+            42,
+        );
+        let delays: Vec<_> = (0..6).map(|_| backoff.next().as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+
+        backoff.reset();
+        assert_eq!(backoff.next(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_jitter_bounds() {
+        let mut backoff = Backoff::with_seed(
+            Duration::from_millis(100),
+            Duration::from_secs(10),
+            2.0,
+            0.5,
+            42,
+        );
+        let mut expected = Duration::from_millis(100);
+        let mut jittered = 0;
+        for _ in 0..10 {
+            let delay = backoff.next();
+            assert!(delay <= expected, "{delay:?} > {expected:?}");
+            assert!(delay >= expected / 2, "{delay:?} < {expected:?} / 2");
+            if delay != expected {
+                jittered += 1;
+            }
+            expected = (expected * 2).min(Duration::from_secs(10));
+        }
+        assert!(jittered > 0);
+    }
+
+    #[test]
+    fn test_seeded_is_deterministic() {
+        let new = || {
+            Backoff::with_seed(
+                Duration::from_millis(10),
+                Duration::from_secs(1),
+                1.5,
+                1.0,
+                1234,
+            )
+        };
+        let (mut a, mut b) = (new(), new());
+        for _ in 0..20 {
+            assert_eq!(a.next(), b.next());
+        }
+    }
+}
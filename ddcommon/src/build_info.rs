@@ -0,0 +1,46 @@
+// Copyright 2025-Present Datadog, Inc. https://www.datadoghq.com/
+// SPDX-License-Identifier: Apache-2.0
+
+//! Information about how this library was built.
+
+/// Returns the cargo features this crate was compiled with.
+///
+/// `cfg!(feature = ...)` only sees the features of the crate it is evaluated in, so crates which
+/// aggregate others (e.g. the FFI library) need to extend this list with their own features.
+pub fn enabled_features() -> &'static [&'static str] {
+    const FEATURES: &[(&str, bool)] = &[
+        ("use_webpki_roots", cfg!(feature = "use_webpki_roots")),
+        ("cgroup_testing", cfg!(feature = "cgroup_testing")),
+    ];
+    static ENABLED: std::sync::OnceLock<Vec<&'static str>> = std::sync::OnceLock::new();
+    ENABLED.get_or_init(|| filter_enabled(FEATURES))
+}
+
+/// Returns the names of the `(name, enabled)` pairs which are enabled, in order.
+pub fn filter_enabled(features: &[(&'static str, bool)]) -> Vec<&'static str> {
+    features
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_enabled() {
+        assert_eq!(
+            filter_enabled(&[("a", true), ("b", false), ("c", true)]),
+            vec!["a", "c"]
+        );
+        assert!(filter_enabled(&[("a", false)]).is_empty());
+        assert!(filter_enabled(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_enabled_features_is_computed_once() {
+        assert!(std::ptr::eq(enabled_features(), enabled_features()));
+    }
+}
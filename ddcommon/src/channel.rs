@@ -0,0 +1,102 @@
+// Copyright 2024-Present Datadog, Inc. https://www.datadoghq.com/
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+/// A bounded mpsc sender which keeps count of the messages it failed to enqueue.
+///
+/// Every `try_send` which fails, either because the channel is full or because the receiver
+/// was dropped, increments a counter shared by all clones of the sender. This makes silently
+/// dropped messages observable without having to handle the error at every call site.
+#[derive(Debug)]
+pub struct DropCountingSender<T> {
+    sender: mpsc::Sender<T>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<T> Clone for DropCountingSender<T> {
+    fn clone(&self) -> Self {
+        DropCountingSender {
+            sender: self.sender.clone(),
+            dropped: self.dropped.clone(),
+        }
+    }
+}
+
+impl<T> DropCountingSender<T> {
+    pub fn new(sender: mpsc::Sender<T>) -> Self {
+        DropCountingSender {
+            sender,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Creates a bounded channel and wraps its sending half.
+    pub fn channel(capacity: usize) -> (Self, mpsc::Receiver<T>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        (Self::new(sender), receiver)
+    }
+
+    /// Attempts to immediately send a message, counting it as dropped on failure.
+    pub fn try_send(&self, message: T) -> Result<(), TrySendError<T>> {
+        self.sender.try_send(message).inspect_err(|_| {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        })
+    }
+
+    /// The wrapped sender, for sends which wait for capacity. These are not counted.
+    pub fn inner(&self) -> &mpsc::Sender<T> {
+        &self.sender
+    }
+
+    /// Number of messages which failed to be sent via `try_send`, across all clones.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_success() {
+        let (sender, mut receiver) = DropCountingSender::channel(2);
+        sender.try_send(1).unwrap();
+        sender.try_send(2).unwrap();
+        assert_eq!(receiver.try_recv().unwrap(), 1);
+        assert_eq!(receiver.try_recv().unwrap(), 2);
+        assert_eq!(sender.dropped(), 0);
+    }
+
+    #[test]
+    fn test_drop_on_full() {
+        let (sender, mut receiver) = DropCountingSender::channel(1);
+        sender.try_send(1).unwrap();
+        assert!(matches!(sender.try_send(2), Err(TrySendError::Full(2))));
+        assert_eq!(sender.dropped(), 1);
+
+        assert_eq!(receiver.try_recv().unwrap(), 1);
+        sender.try_send(3).unwrap();
+        assert_eq!(receiver.try_recv().unwrap(), 3);
+        assert_eq!(sender.dropped(), 1);
+    }
+
+    #[test]
+    fn test_dropped_count_shared_across_clones() {
+        let (sender, receiver) = DropCountingSender::channel(1);
+        let clone = sender.clone();
+        sender.try_send(1).unwrap();
+        for i in 0..5 {
+            assert!(clone.try_send(i).is_err());
+        }
+        assert_eq!(sender.dropped(), 5);
+
+        drop(receiver);
+        assert!(matches!(sender.try_send(6), Err(TrySendError::Closed(6))));
+        assert_eq!(clone.dropped(), 6);
+    }
+}
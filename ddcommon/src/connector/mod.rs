@@ -10,9 +10,12 @@ use hyper_rustls::ConfigBuilderExt;
 
 use lazy_static::lazy_static;
 
+use rustls::pki_types::ServerName;
 use rustls::ClientConfig;
 use std::future::Future;
+use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 #[cfg(unix)]
@@ -29,10 +32,16 @@ use conn_stream::{ConnStream, ConnStreamError};
 pub enum Connector {
     Http(hyper::client::HttpConnector),
     Https(hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>),
+    /// Dials a fixed address instead of the host of the uri, see [`Connector::for_endpoint`]
+    Pinned {
+        inner: Box<Connector>,
+        addr: SocketAddr,
+    },
 }
 
 lazy_static! {
     static ref DEFAULT_CONNECTOR: Connector = Connector::new();
+    static ref TLS_CONFIG: Option<Arc<ClientConfig>> = tls_config().ok().map(Arc::new);
 }
 
 // When using aws-lc-rs, rustls needs to be initialized with the default CryptoProvider; sometimes
@@ -56,17 +65,39 @@ impl Default for Connector {
 
 impl Connector {
     pub fn new() -> Self {
-        #[cfg(feature = "use_webpki_roots")]
-        let https_connector_fn = build_https_connector_with_webpki_roots;
-        #[cfg(not(feature = "use_webpki_roots"))]
-        let https_connector_fn = build_https_connector;
-
-        match https_connector_fn() {
-            Ok(connector) => Connector::Https(connector),
+        match tls_config() {
+            Ok(client_config) => {
+                Connector::Https(build_https_connector(Arc::new(client_config), None))
+            }
             Err(_) => Connector::Http(HttpConnector::new()),
         }
     }
 
+    /// Returns the connector to use for requests to the given endpoint: the default one, unless
+    /// the endpoint has a [`crate::Endpoint::resolved_addr`], which is then dialed instead of
+    /// resolving the host of the url. TLS connections still verify the certificate against the
+    /// host of the url. The TLS config is loaded once and shared by all these connectors.
+    pub fn for_endpoint(endpoint: &crate::Endpoint) -> Self {
+        let Some(addr) = endpoint.resolved_addr else {
+            return Self::default();
+        };
+        let server_name = endpoint.url.host().and_then(|host| {
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            ServerName::try_from(host.to_string()).ok()
+        });
+        let inner = match (TLS_CONFIG.as_ref(), server_name) {
+            (Some(client_config), Some(server_name)) => Connector::Https(build_https_connector(
+                client_config.clone(),
+                Some(server_name),
+            )),
+            _ => Connector::Http(HttpConnector::new()),
+        };
+        Connector::Pinned {
+            inner: Box::new(inner),
+            addr,
+        }
+    }
+
     fn build_conn_stream<'a>(
         &mut self,
         uri: hyper::Uri,
@@ -86,39 +117,57 @@ impl Connector {
             Self::Https(c) => {
                 ConnStream::from_https_connector_with_uri(c, uri, require_tls).boxed()
             }
+            Self::Pinned { inner, addr } => {
+                // Only the authority is replaced: the scheme still selects plain tcp or tls
+                let mut parts = uri.into_parts();
+                parts.authority = match addr.to_string().parse() {
+                    Ok(authority) => Some(authority),
+                    Err(e) => return future::err(e.into()).boxed(),
+                };
+                match hyper::Uri::from_parts(parts) {
+                    Ok(uri) => inner.build_conn_stream(uri, require_tls),
+                    Err(e) => future::err(e.into()).boxed(),
+                }
+            }
         }
     }
 }
 
 #[cfg(not(feature = "use_webpki_roots"))]
-fn build_https_connector(
-) -> anyhow::Result<hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>>
-{
+fn tls_config() -> anyhow::Result<ClientConfig> {
     let certs = load_root_certs()?;
-    let client_config = ClientConfig::builder()
+    Ok(ClientConfig::builder()
         .with_root_certificates(certs)
-        .with_no_client_auth();
-    Ok(hyper_rustls::HttpsConnectorBuilder::new()
-        .with_tls_config(client_config)
-        .https_or_http()
-        .enable_http1()
-        .build())
+        .with_no_client_auth())
 }
 
 #[cfg(feature = "use_webpki_roots")]
-fn build_https_connector_with_webpki_roots(
-) -> anyhow::Result<hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>>
-{
+fn tls_config() -> anyhow::Result<ClientConfig> {
     *INIT_CRYPTO_PROVIDER; // One-time initialization of a crypto provider if needed
 
-    let client_config = ClientConfig::builder()
+    Ok(ClientConfig::builder()
         .with_webpki_roots()
-        .with_no_client_auth();
-    Ok(hyper_rustls::HttpsConnectorBuilder::new()
-        .with_tls_config(client_config)
-        .https_or_http()
-        .enable_http1()
-        .build())
+        .with_no_client_auth())
+}
+
+/// Builds an https connector, verifying certificates against `server_name` if set, or else
+/// against the host of the uri.
+///
+/// This is what `HttpsConnectorBuilder` builds with `https_or_http()` and `enable_http1()`, but
+/// without taking ownership of the client config, so that it can be shared between connectors.
+fn build_https_connector(
+    client_config: Arc<ClientConfig>,
+    server_name: Option<ServerName<'static>>,
+) -> hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector> {
+    let mut http = hyper_util::client::legacy::connect::HttpConnector::new();
+    // HttpConnector won't enforce the scheme, the https connector does
+    http.enforce_http(false);
+    let server_name_resolver: Arc<dyn hyper_rustls::ResolveServerName + Send + Sync> =
+        match server_name {
+            Some(server_name) => Arc::new(hyper_rustls::FixedServerNameResolver::new(server_name)),
+            None => Arc::new(hyper_rustls::DefaultServerNameResolver::default()),
+        };
+    hyper_rustls::HttpsConnector::new(http, client_config, false, server_name_resolver)
 }
 
 #[cfg(not(feature = "use_webpki_roots"))]
@@ -157,6 +206,7 @@ impl hyper::service::Service<hyper::Uri> for Connector {
         match self {
             Connector::Http(c) => c.poll_ready(cx).map_err(|e| e.into()),
             Connector::Https(c) => c.poll_ready(cx),
+            Connector::Pinned { inner, .. } => inner.poll_ready(cx),
         }
     }
 }
@@ -52,9 +52,11 @@
 //! ```
 
 use crate::config::parse_env;
+use crate::tag::Tag;
 use lazy_static::lazy_static;
 
 const EXTERNAL_ENV_ENVIRONMENT_VARIABLE: &str = "DD_EXTERNAL_ENV";
+const ENTITY_ID_ENVIRONMENT_VARIABLE: &str = "DD_ENTITY_ID";
 
 /// Unix specific module allowing the use of unix specific functions
 #[cfg(unix)]
@@ -93,6 +95,31 @@ pub fn get_entity_id() -> Option<&'static str> {
     }
 }
 
+/// The cgroup version in use, as relevant for container id and entity id extraction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgroupVersion {
+    /// Only cgroupV1 controller hierarchies are mounted
+    V1,
+    /// Only the cgroupV2 unified hierarchy is mounted
+    V2,
+    /// Both cgroupV1 controller hierarchies and the cgroupV2 unified hierarchy are mounted
+    Hybrid,
+    /// The cgroup version could not be detected, e.g. when not running on linux
+    Unknown,
+}
+
+/// Returns the cgroup version in use. The result is cached after the first detection.
+pub fn detect_cgroup_version() -> CgroupVersion {
+    #[cfg(unix)]
+    {
+        unix::detect_cgroup_version()
+    }
+    #[cfg(not(unix))]
+    {
+        CgroupVersion::Unknown
+    }
+}
+
 /// Returns the `DD_EXTERNAL_ENV` if available as an env variable
 pub fn get_external_env() -> Option<&'static str> {
     lazy_static! {
@@ -101,3 +128,67 @@ pub fn get_external_env() -> Option<&'static str> {
     }
     DD_EXTERNAL_ENV.as_deref()
 }
+
+/// Tag key of the dogstatsd origin detection tag, carrying the `DD_ENTITY_ID` (the pod uid set by
+/// the admission controller)
+pub const ENTITY_ID_TAG_KEY: &str = "dd.internal.entity_id";
+
+/// Returns the tags the dogstatsd clients add for origin detection: `dd.internal.entity_id` with
+/// the value of `DD_ENTITY_ID`, if set. The container id and the external env are not tags but
+/// the `|c:` and `|e:` fields of the datagram, see [`origin_detection_value`] and
+/// [`get_external_env`].
+pub fn get_entity_tags() -> Vec<Tag> {
+    lazy_static! {
+        static ref DD_ENTITY_ID: Option<String> =
+            parse_env::str_not_empty(ENTITY_ID_ENVIRONMENT_VARIABLE);
+    }
+    entity_tags(DD_ENTITY_ID.as_deref())
+}
+
+/// Returns the value of the `|c:` field of dogstatsd datagrams, for origin detection when no
+/// entity headers can be sent, e.g. over UDS: the container id, else `in-<cgroup inode>`.
+pub fn origin_detection_value() -> Option<String> {
+    origin_detection(get_container_id(), get_entity_id())
+}
+
+fn origin_detection(container_id: Option<&str>, entity_id: Option<&str>) -> Option<String> {
+    container_id
+        .or_else(|| entity_id.filter(|entity_id| entity_id.starts_with("in-")))
+        .map(str::to_string)
+}
+
+fn entity_tags(dd_entity_id: Option<&str>) -> Vec<Tag> {
+    dd_entity_id
+        .and_then(|entity_id| Tag::new(ENTITY_ID_TAG_KEY, entity_id).ok())
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entity_tags() {
+        let tags = entity_tags(Some("3d274242-8ee0-11e9-a8a6-1e68d864ef1a"));
+        assert_eq!(
+            tags.iter().map(|t| t.to_string()).collect::<Vec<_>>(),
+            vec!["dd.internal.entity_id:3d274242-8ee0-11e9-a8a6-1e68d864ef1a"]
+        );
+        assert!(entity_tags(None).is_empty());
+    }
+
+    #[test]
+    fn test_origin_detection() {
+        let container_id = "3726184226f5d3147c25fdeab5b60097e378e8a720503a5e19ecfdf29f869860";
+        assert_eq!(
+            origin_detection(Some(container_id), Some(&format!("ci-{container_id}"))),
+            Some(container_id.to_string())
+        );
+        assert_eq!(
+            origin_detection(None, Some("in-12345")),
+            Some("in-12345".to_string())
+        );
+        assert_eq!(origin_detection(None, None), None);
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright 2021-Present Datadog, Inc. https://www.datadoghq.com/
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module provides functions to detect which cgroup version is in use
+use super::super::CgroupVersion;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// File only present at the root of a cgroupV2 (unified) hierarchy
+const CGROUP_V2_CONTROLLERS_FILE: &str = "cgroup.controllers";
+
+/// Detects the cgroup version from the cgroup file located at `cgroup_path`.
+///
+/// cgroupV1 entries have the form `<id>:<controllers>:<path>` with a non-empty controller list,
+/// whereas the cgroupV2 entry is always `0::<path>`. Hybrid setups list both kinds of entries.
+/// If the cgroup file cannot be read, we fall back to looking for cgroupV2 markers at
+/// `cgroup_mount_path`.
+pub fn detect_cgroup_version(cgroup_path: &Path, cgroup_mount_path: &Path) -> CgroupVersion {
+    let Ok(file) = File::open(cgroup_path) else {
+        return if cgroup_mount_path.join(CGROUP_V2_CONTROLLERS_FILE).exists() {
+            CgroupVersion::V2
+        } else {
+            CgroupVersion::Unknown
+        };
+    };
+
+    let mut has_v1 = false;
+    let mut has_v2 = false;
+    for line in BufReader::new(file).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let cgroup_entry: Vec<&str> = line.split(':').collect();
+        if cgroup_entry.len() != 3 {
+            continue;
+        }
+        if cgroup_entry[1].is_empty() {
+            has_v2 |= cgroup_entry[0] == "0";
+        } else {
+            has_v1 = true;
+        }
+    }
+
+    match (has_v1, has_v2) {
+        (true, true) => CgroupVersion::Hybrid,
+        (true, false) => CgroupVersion::V1,
+        (false, true) => CgroupVersion::V2,
+        (false, false) => CgroupVersion::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn test_cgroup_version_detection() {
+        let test_root_dir: &Path = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests"));
+
+        let test_files = hashmap! {
+            // a cgroupV2 only file has a single 0:: entry
+            "cgroup.v2" => CgroupVersion::V2,
+            "cgroup.v2_custom_path" => CgroupVersion::V2,
+            // cgroupV1 only files list controllers for every entry
+            "cgroup.linux" => CgroupVersion::V1,
+            "cgroup.docker" => CgroupVersion::V1,
+            // hybrid setups list both cgroupV1 controllers and the cgroupV2 0:: entry
+            "cgroup.hybrid" => CgroupVersion::Hybrid,
+            // missing cgroup file and no cgroupV2 mount
+            "path/to/cgroup.missing" => CgroupVersion::Unknown,
+        };
+
+        for (&filename, expected_result) in test_files.iter() {
+            assert_eq!(
+                detect_cgroup_version(&test_root_dir.join(filename), &test_root_dir.join("cgroup")),
+                *expected_result,
+                "testing cgroup version detection with file: {filename}"
+            );
+        }
+    }
+}
@@ -1,12 +1,14 @@
 // Copyright 2021-Present Datadog, Inc. https://www.datadoghq.com/
 // SPDX-License-Identifier: Apache-2.0
 
+use super::CgroupVersion;
 use lazy_static::lazy_static;
 use std::error;
 use std::fmt;
 use std::path::Path;
 
 mod cgroup_inode;
+mod cgroup_version;
 mod container_id;
 
 const DEFAULT_CGROUP_PATH: &str = "/proc/self/cgroup";
@@ -99,6 +101,18 @@ pub fn get_entity_id() -> Option<&'static str> {
     ENTITY_ID.as_deref()
 }
 
+/// Returns the cgroup version in use, detected from the cgroup file
+pub fn detect_cgroup_version() -> CgroupVersion {
+    // cache the cgroup version in a static to avoid recomputing it at each call
+    lazy_static! {
+        static ref CGROUP_VERSION: CgroupVersion = cgroup_version::detect_cgroup_version(
+            Path::new(get_cgroup_path()),
+            Path::new(get_cgroup_mount_path()),
+        );
+    }
+    *CGROUP_VERSION
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
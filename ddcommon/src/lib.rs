@@ -1,7 +1,13 @@
 // Copyright 2021-Present Datadog, Inc. https://www.datadoghq.com/
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{borrow::Cow, ops::Deref, path::PathBuf, str::FromStr};
+use std::{
+    borrow::Cow,
+    net::SocketAddr,
+    ops::Deref,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use hyper::{
     header::HeaderValue,
@@ -11,6 +17,9 @@ use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 pub mod azure_app_services;
+pub mod backoff;
+pub mod build_info;
+pub mod channel;
 pub mod connector;
 pub mod entity_id;
 #[macro_use]
@@ -45,6 +54,10 @@ pub struct Endpoint {
     pub timeout_ms: u64,
     /// Sets X-Datadog-Test-Session-Token header on any request
     pub test_token: Option<Cow<'static, str>>,
+    /// Dial this address instead of resolving the host of the url, see
+    /// [`Endpoint::with_resolved_addr`]
+    #[serde(default)]
+    pub resolved_addr: Option<SocketAddr>,
 }
 
 impl Default for Endpoint {
@@ -54,6 +67,7 @@ impl Default for Endpoint {
             api_key: None,
             timeout_ms: Self::DEFAULT_TIMEOUT,
             test_token: None,
+            resolved_addr: None,
         }
     }
 }
@@ -151,10 +165,74 @@ pub fn decode_uri_path_in_authority(uri: &hyper::Uri) -> anyhow::Result<PathBuf>
     }
 }
 
+/// The unix domain socket the agent listens on by default.
+#[cfg(unix)]
+const DEFAULT_AGENT_SOCKET_PATH: &str = "/var/run/datadog/apm.socket";
+const DEFAULT_AGENT_HOST: &str = "localhost";
+const DEFAULT_AGENT_PORT: u16 = 8126;
+
 impl Endpoint {
     /// Default value for the timeout field in milliseconds.
     pub const DEFAULT_TIMEOUT: u64 = 3_000;
 
+    /// Resolves the endpoint of the agent from the environment, in order of precedence:
+    /// - `DD_TRACE_AGENT_URL`, if it is a http, https or unix url
+    /// - `DD_AGENT_HOST` and `DD_TRACE_AGENT_PORT`, either one defaulting to `localhost:8126`
+    /// - on unix, the socket from `DD_APM_RECEIVER_SOCKET`, or `/var/run/datadog/apm.socket` if it
+    ///   exists
+    /// - `http://localhost:8126`
+    pub fn from_agent_env() -> Endpoint {
+        Self::from_agent_settings(config::parse_env::str_not_empty, |path| path.exists())
+    }
+
+    fn from_agent_settings(
+        var: impl Fn(&str) -> Option<String>,
+        socket_exists: impl Fn(&Path) -> bool,
+    ) -> Endpoint {
+        let url = None
+            .or_else(|| {
+                var("DD_TRACE_AGENT_URL")
+                    .filter(|u| {
+                        u.starts_with("unix://")
+                            || u.starts_with("http://")
+                            || u.starts_with("https://")
+                    })
+                    .and_then(|u| parse_uri(&u).ok())
+            })
+            .or_else(|| {
+                let host = var("DD_AGENT_HOST");
+                let port = var("DD_TRACE_AGENT_PORT").and_then(|p| p.parse::<u16>().ok());
+                if host.is_none() && port.is_none() {
+                    return None;
+                }
+                let host = host.as_deref().unwrap_or(DEFAULT_AGENT_HOST);
+                let port = port.unwrap_or(DEFAULT_AGENT_PORT);
+                // IPv6 addresses must be bracketed in urls
+                if host.contains(':') && !host.starts_with('[') {
+                    parse_uri(&format!("http://[{host}]:{port}")).ok()
+                } else {
+                    parse_uri(&format!("http://{host}:{port}")).ok()
+                }
+            })
+            .or_else(|| {
+                #[cfg(unix)]
+                return var("DD_APM_RECEIVER_SOCKET")
+                    .map(PathBuf::from)
+                    .or_else(|| {
+                        Some(PathBuf::from(DEFAULT_AGENT_SOCKET_PATH))
+                            .filter(|path| socket_exists(path))
+                    })
+                    .and_then(|path| connector::uds::socket_path_to_uri(&path).ok());
+                #[cfg(not(unix))]
+                return {
+                    let _ = &socket_exists;
+                    None
+                };
+            })
+            .unwrap_or_else(|| hyper::Uri::from_static("http://localhost:8126"));
+        Endpoint::from_url(url)
+    }
+
     /// Return a request builder with the following headers:
     /// - User agent
     /// - Api key
@@ -210,4 +288,313 @@ impl Endpoint {
             ..Default::default()
         }
     }
+
+    /// Checks that the scheme of the url is supported on this platform: http, https and file, as
+    /// well as unix sockets on unix and named pipes (windows) on windows. This allows rejecting an
+    /// endpoint up front rather than when sending the first request.
+    pub fn validate_scheme(&self) -> anyhow::Result<()> {
+        match self.url.scheme_str() {
+            Some("http" | "https" | "file") => Ok(()),
+            #[cfg(unix)]
+            Some("unix") => Ok(()),
+            #[cfg(windows)]
+            Some("windows") => Ok(()),
+            Some(scheme) => anyhow::bail!("unsupported endpoint scheme: {scheme}"),
+            None => anyhow::bail!("endpoint url {} has no scheme", self.url),
+        }
+    }
+
+    /// Pins the address requests are sent to, skipping DNS resolution of the host of the url. This
+    /// is applied by the connector returned by [`connector::Connector::for_endpoint`]: the Host
+    /// header and the TLS server name are still the ones of the url.
+    /// Returns an error for local schemes (unix, windows, file), which have no host to resolve.
+    pub fn with_resolved_addr(mut self, addr: SocketAddr) -> anyhow::Result<Endpoint> {
+        match self.url.scheme_str() {
+            Some(scheme @ ("unix" | "windows" | "file")) => {
+                anyhow::bail!("cannot pin the address of a {scheme} endpoint")
+            }
+            _ if self.url.authority().is_none() => {
+                anyhow::bail!("cannot pin the address of an endpoint without host")
+            }
+            _ => {}
+        }
+        self.resolved_addr = Some(addr);
+        Ok(self)
+    }
+
+    /// Returns a copy of this endpoint with the url replaced. The pinned address, if any, is only
+    /// kept if the authority of the url is unchanged, as it was resolved for the previous host.
+    pub fn with_url(&self, url: hyper::Uri) -> Endpoint {
+        Endpoint {
+            resolved_addr: self
+                .resolved_addr
+                .filter(|_| url.authority() == self.url.authority()),
+            url,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of this endpoint with the scheme of the url replaced, e.g. to switch between
+    /// http and https. Authority, path, query and all other fields are preserved.
+    /// Local schemes (unix, windows, file) store a path in the authority and cannot be swapped.
+    pub fn with_scheme(&self, scheme: &str) -> anyhow::Result<Endpoint> {
+        const LOCAL_SCHEMES: [&str; 3] = ["unix", "windows", "file"];
+        if let Some(current) = self.url.scheme_str().filter(|s| LOCAL_SCHEMES.contains(s)) {
+            anyhow::bail!("cannot change the scheme of a {current} endpoint");
+        }
+        if LOCAL_SCHEMES.contains(&scheme) {
+            anyhow::bail!("cannot change the scheme of an endpoint to {scheme}");
+        }
+        let mut parts = self.url.clone().into_parts();
+        parts.scheme = Some(scheme.parse()?);
+        if parts.path_and_query.is_none() {
+            parts.path_and_query = Some(hyper::http::uri::PathAndQuery::from_static("/"));
+        }
+        Ok(Endpoint {
+            url: hyper::Uri::from_parts(parts)?,
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent_endpoint(vars: &[(&str, &str)], socket_exists: bool) -> String {
+        Endpoint::from_agent_settings(
+            |name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            },
+            |_| socket_exists,
+        )
+        .url
+        .to_string()
+    }
+
+    #[test]
+    fn test_from_agent_env() {
+        // Default
+        assert_eq!(agent_endpoint(&[], false), "http://localhost:8126/");
+
+        // The url takes precedence over everything
+        let all = [
+            ("DD_TRACE_AGENT_URL", "http://agent:1234"),
+            ("DD_AGENT_HOST", "example.org"),
+            ("DD_TRACE_AGENT_PORT", "1"),
+            ("DD_APM_RECEIVER_SOCKET", "/tmp/apm.socket"),
+        ];
+        assert_eq!(agent_endpoint(&all, true), "http://agent:1234/");
+        assert_eq!(
+            agent_endpoint(&[("DD_TRACE_AGENT_URL", "unix:///tmp/apm.socket")], false),
+            "unix://2f746d702f61706d2e736f636b6574/"
+        );
+
+        // Unsupported urls are ignored
+        assert_eq!(
+            agent_endpoint(&[("DD_TRACE_AGENT_URL", "ftp://agent:1234")], false),
+            "http://localhost:8126/"
+        );
+
+        // Host and port then, defaulting the missing one
+        assert_eq!(agent_endpoint(&all[1..], true), "http://example.org:1/");
+        assert_eq!(
+            agent_endpoint(&[("DD_AGENT_HOST", "example.org")], true),
+            "http://example.org:8126/"
+        );
+        assert_eq!(
+            agent_endpoint(&[("DD_TRACE_AGENT_PORT", "1")], true),
+            "http://localhost:1/"
+        );
+        assert_eq!(
+            agent_endpoint(&[("DD_AGENT_HOST", "::1")], false),
+            "http://[::1]:8126/"
+        );
+        assert_eq!(
+            agent_endpoint(&[("DD_TRACE_AGENT_PORT", "invalid")], false),
+            "http://localhost:8126/"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_from_agent_env_socket() {
+        // The configured socket is used even if it does not exist yet
+        assert_eq!(
+            agent_endpoint(&[("DD_APM_RECEIVER_SOCKET", "/tmp/apm.socket")], false),
+            "unix://2f746d702f61706d2e736f636b6574/"
+        );
+        // The default socket only if it exists
+        assert_eq!(
+            agent_endpoint(&[], true),
+            connector::uds::socket_path_to_uri(DEFAULT_AGENT_SOCKET_PATH.as_ref())
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_validate_scheme() {
+        for url in [
+            "http://localhost:8126/",
+            "https://intake.datadoghq.com/",
+            "file:///tmp/telemetry.log",
+        ] {
+            assert!(Endpoint::from_slice(url).validate_scheme().is_ok(), "{url}");
+        }
+        #[cfg(unix)]
+        {
+            assert!(Endpoint::from_slice("unix:///var/run/datadog/apm.socket")
+                .validate_scheme()
+                .is_ok());
+            assert!(Endpoint::from_slice("windows:\\\\.\\pipe\\datadog")
+                .validate_scheme()
+                .is_err());
+        }
+        #[cfg(windows)]
+        {
+            assert!(Endpoint::from_slice("windows:\\\\.\\pipe\\datadog")
+                .validate_scheme()
+                .is_ok());
+            assert!(Endpoint::from_slice("unix:///var/run/datadog/apm.socket")
+                .validate_scheme()
+                .is_err());
+        }
+
+        let err = Endpoint::from_slice("ftp://localhost/")
+            .validate_scheme()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "unsupported endpoint scheme: ftp");
+        assert!(Endpoint::from_slice("/v0.4/traces")
+            .validate_scheme()
+            .is_err());
+    }
+
+    #[test]
+    fn test_with_scheme() {
+        let endpoint = Endpoint {
+            api_key: Some("key".into()),
+            timeout_ms: 1234,
+            ..Endpoint::from_slice("http://localhost:8126/v0.4/traces?foo=bar")
+        };
+
+        let https = endpoint.with_scheme("https").unwrap();
+        assert_eq!(
+            https.url.to_string(),
+            "https://localhost:8126/v0.4/traces?foo=bar"
+        );
+        assert_eq!(https.api_key, endpoint.api_key);
+        assert_eq!(https.timeout_ms, 1234);
+
+        let http = https.with_scheme("http").unwrap();
+        assert_eq!(http, endpoint);
+
+        assert!(endpoint.with_scheme("unix").is_err());
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_with_resolved_addr() {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = vec![];
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                head.push(line.trim_end().to_string());
+            }
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+            head
+        });
+
+        // .invalid is guaranteed to never resolve
+        let endpoint = Endpoint::from_slice("http://agent.invalid:8126/info")
+            .with_resolved_addr(addr)
+            .unwrap();
+        let request = endpoint
+            .into_request_builder("test")
+            .unwrap()
+            .body(hyper::Body::empty())
+            .unwrap();
+        let response = hyper::Client::builder()
+            .build(connector::Connector::for_endpoint(&endpoint))
+            .request(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::OK);
+
+        let head = server.join().unwrap();
+        assert_eq!(head[0], "GET /info HTTP/1.1");
+        assert!(
+            head.iter()
+                .any(|h| h.eq_ignore_ascii_case("host: agent.invalid:8126")),
+            "{head:?}"
+        );
+
+        let unix = Endpoint::from_slice("unix:///var/run/datadog/apm.socket");
+        assert!(unix.with_resolved_addr(addr).is_err());
+
+        // The pinned address is dropped along with the host it was resolved for
+        let path = endpoint.with_url(parse_uri("http://agent.invalid:8126/v0.4/traces").unwrap());
+        assert_eq!(path.resolved_addr, Some(addr));
+        let host = endpoint.with_url(parse_uri("http://other.invalid:8126/info").unwrap());
+        assert_eq!(host.resolved_addr, None);
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_with_resolved_addr_keeps_tls_server_name() {
+        use std::io::Read;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            // The ClientHello carries the server name in clear, then the handshake is aborted
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut hello = vec![0; 4096];
+            let len = stream.read(&mut hello).unwrap();
+            hello.truncate(len);
+            hello
+        });
+
+        let endpoint = Endpoint::from_slice("https://agent.invalid:8126/info")
+            .with_resolved_addr(addr)
+            .unwrap();
+        let request = endpoint
+            .into_request_builder("test")
+            .unwrap()
+            .body(hyper::Body::empty())
+            .unwrap();
+        let client = hyper::Client::builder().build(connector::Connector::for_endpoint(&endpoint));
+        let _ =
+            tokio::time::timeout(std::time::Duration::from_secs(1), client.request(request)).await;
+
+        let hello = server.join().unwrap();
+        assert!(
+            hello.windows(13).any(|w| w == b"agent.invalid"),
+            "no server name in {hello:?}"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_with_scheme_local_endpoint() {
+        let endpoint = Endpoint::from_url(
+            connector::uds::socket_path_to_uri("/var/run/datadog/apm.socket".as_ref()).unwrap(),
+        );
+        assert!(endpoint.with_scheme("https").is_err());
+    }
 }
@@ -7,6 +7,9 @@ use std::fmt::{Debug, Display, Formatter};
 
 pub use static_assertions::{const_assert, const_assert_ne};
 
+/// The maximum length of a tag in bytes, as enforced by the agent.
+const MAX_TAG_LENGTH: usize = 200;
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct Tag {
@@ -131,6 +134,62 @@ impl Tag {
 
         Tag::from_value(format!("{key}:{value}"))
     }
+
+    /// Returns the tag normalized like the agent does, so that both agree on it: the key is
+    /// lowercased, runs of characters other than letters, digits, `_`, `-`, `.` and `/` in the key
+    /// are replaced by a single `_` and trailing underscores are trimmed. The value is left as is,
+    /// but the whole tag is truncated to 200 bytes. A tag without a colon is normalized as a key.
+    pub fn normalize(&self) -> Tag {
+        let (key, value) = match self.value.split_once(':') {
+            Some((key, value)) => (key, Some(value)),
+            None => (self.value.as_ref(), None),
+        };
+
+        let mut normalized = String::with_capacity(self.value.len());
+        for c in key.chars().flat_map(char::to_lowercase) {
+            if c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/') {
+                normalized.push(c);
+            } else if !normalized.ends_with('_') {
+                normalized.push('_');
+            }
+        }
+        // Keep a single underscore rather than an empty key
+        let trimmed_len = normalized.trim_end_matches('_').len();
+        normalized.truncate(trimmed_len.max(1));
+
+        if let Some(value) = value {
+            normalized.push(':');
+            normalized.push_str(value);
+        }
+        if normalized.len() > MAX_TAG_LENGTH {
+            let mut end = MAX_TAG_LENGTH;
+            while !normalized.is_char_boundary(end) {
+                end -= 1;
+            }
+            normalized.truncate(end);
+            // Truncating must not leave a dangling separator
+            let trimmed_len = normalized.trim_end_matches(':').len();
+            normalized.truncate(trimmed_len);
+        }
+
+        Tag {
+            value: Cow::Owned(normalized),
+        }
+    }
+}
+
+/// How [`parse_tags_with`] splits a string of tags.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TagSeparators {
+    /// Tags are either space or comma separated, see [`parse_tags`].
+    #[default]
+    CommaOrSpace,
+    /// The parsing rules of the agent for `DD_TAGS`: if the string contains a comma, tags are
+    /// comma separated and surrounding whitespace is trimmed, so values may contain spaces:
+    ///     "key1:value1, key2:value 2"
+    /// Otherwise tags are whitespace separated:
+    ///     "key1:value1 key2:value2"
+    Auto,
 }
 
 /// Parse a string of tags typically provided by environment variables
@@ -142,8 +201,19 @@ impl Tag {
 /// Returns a tuple of the correctly parsed tags and an optional error message
 /// describing issues encountered during parsing.
 pub fn parse_tags(str: &str) -> (Vec<Tag>, Option<String>) {
-    let chunks = str
-        .split(&[',', ' '][..])
+    parse_tags_with(str, TagSeparators::CommaOrSpace)
+}
+
+/// Same as [`parse_tags`], splitting tags according to `separators`. The error message names
+/// every rejected fragment.
+pub fn parse_tags_with(str: &str, separators: TagSeparators) -> (Vec<Tag>, Option<String>) {
+    let chunks: Vec<&str> = match separators {
+        TagSeparators::CommaOrSpace => str.split(&[',', ' '][..]).collect(),
+        TagSeparators::Auto if str.contains(',') => str.split(',').map(str::trim).collect(),
+        TagSeparators::Auto => str.split_whitespace().collect(),
+    };
+    let chunks = chunks
+        .into_iter()
         .filter(|str| !str.is_empty())
         .map(Tag::from_value);
 
@@ -171,6 +241,37 @@ pub fn parse_tags(str: &str) -> (Vec<Tag>, Option<String>) {
     (tags, error_message)
 }
 
+/// Returns the unified service tagging tags, i.e. `service`, `env` and `version`, as set via
+/// `DD_SERVICE`, `DD_ENV` and `DD_VERSION`. Empty values are omitted, as well as values which
+/// can't form a valid tag, e.g. ending with a colon.
+pub fn unified_service_tags(service: &str, env: &str, version: &str) -> Vec<Tag> {
+    [("service", service), ("env", env), ("version", version)]
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .filter_map(|(key, value)| Tag::new(key, value).ok())
+        .collect()
+}
+
+/// Removes tags with duplicate keys, keeping the value of the last occurrence of each key, and
+/// sorts the result by key so the output does not depend on the order tags were combined in. The
+/// key is the part before the first colon, or the whole tag if it has none, meaning that e.g.
+/// `foo` and `foo:bar` have the same key.
+pub fn dedupe_tags(tags: Vec<Tag>) -> Vec<Tag> {
+    fn key(tag: &Tag) -> &str {
+        let tag = tag.as_ref();
+        tag.split_once(':').map_or(tag, |(key, _)| key)
+    }
+
+    let mut deduped: Vec<Tag> = Vec::with_capacity(tags.len());
+    for tag in tags.into_iter().rev() {
+        if !deduped.iter().any(|seen| key(seen) == key(&tag)) {
+            deduped.push(tag);
+        }
+    }
+    deduped.sort_by(|a, b| key(a).cmp(key(b)));
+    deduped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,4 +400,149 @@ mod tests {
             assert!(error_message.is_none());
         }
     }
+
+    #[test]
+    fn test_tags_parsing_auto_separators() {
+        let cases = [
+            ("", vec![], None),
+            (" , ", vec![], None),
+            // Comma separated, surrounding whitespace is trimmed
+            (
+                "key1:value1, key2:value2 ,key3:value3",
+                vec!["key1:value1", "key2:value2", "key3:value3"],
+                None,
+            ),
+            // Whitespace separated when there is no comma
+            (
+                "key1:value1  key2:value2\tkey3:value3",
+                vec!["key1:value1", "key2:value2", "key3:value3"],
+                None,
+            ),
+            // With any comma, spaces are part of the values
+            (
+                "key1:value1 key2:value2,key3:value 3",
+                vec!["key1:value1 key2:value2", "key3:value 3"],
+                None,
+            ),
+            // Invalid fragments are reported
+            (
+                ":key1,key2:value2,key3:",
+                vec!["key2:value2"],
+                Some(
+                    "Errors while parsing tags: tag ':key1' begins with a colon, \
+                     tag 'key3:' ends with a colon",
+                ),
+            ),
+            (
+                ":key1 key2:value2",
+                vec!["key2:value2"],
+                Some("Errors while parsing tags: tag ':key1' begins with a colon"),
+            ),
+        ];
+
+        for (input, expected, expected_error) in cases {
+            let (actual, error_message) = parse_tags_with(input, TagSeparators::Auto);
+            let actual: Vec<String> = actual.iter().map(Tag::to_string).collect();
+            assert_eq!(expected, actual, "parsing {input:?}");
+            assert_eq!(
+                expected_error,
+                error_message.as_deref(),
+                "parsing {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let cases = [
+            ("env:prod", "env:prod"),
+            // Only the key is lowercased
+            ("Env:Prod", "env:Prod"),
+            ("SERVICE.Name:Value", "service.name:Value"),
+            // Invalid characters are collapsed into a single underscore
+            ("my key:value", "my_key:value"),
+            ("my  @#key:value with spaces", "my_key:value with spaces"),
+            ("a-b/c_d.e:value", "a-b/c_d.e:value"),
+            ("_dd.p.dm:-1", "_dd.p.dm:-1"),
+            ("key!!:value", "key:value"),
+            ("!!:value", "_:value"),
+            // Without colon, the tag is a key
+            ("Some Tag", "some_tag"),
+            ("ÉTÉ:value", "été:value"),
+        ];
+        for (input, expected) in cases {
+            let tag = Tag::from_value(input).unwrap();
+            assert_eq!(expected, tag.normalize().as_ref(), "normalizing {input:?}");
+        }
+
+        let long = Tag::new("key", "v".repeat(300)).unwrap().normalize();
+        assert_eq!(MAX_TAG_LENGTH, long.as_ref().len());
+        assert!(long.as_ref().starts_with("key:vvv"));
+
+        // Truncation respects char boundaries
+        let long = Tag::new("keys", "é".repeat(150)).unwrap().normalize();
+        assert_eq!(MAX_TAG_LENGTH - 1, long.as_ref().len());
+
+        // A tag truncated right after its separator doesn't end with a colon
+        let long = Tag::new("k".repeat(MAX_TAG_LENGTH - 1), "value")
+            .unwrap()
+            .normalize();
+        assert_eq!("k".repeat(MAX_TAG_LENGTH - 1), long.as_ref());
+    }
+
+    #[test]
+    fn test_dedupe_tags() {
+        let tags = [
+            "service:a",
+            "env:prod",
+            "version:1",
+            "service:b",
+            "env:staging",
+            "flag",
+            "flag:set",
+            "a_b:x",
+            "a:y",
+        ]
+        .map(|t| Tag::from_value(t).unwrap());
+
+        let deduped = dedupe_tags(tags.to_vec());
+        let deduped: Vec<&str> = deduped.iter().map(Tag::as_ref).collect();
+        // The last value of each key wins and tags are sorted by key
+        assert_eq!(
+            vec![
+                "a:y",
+                "a_b:x",
+                "env:staging",
+                "flag:set",
+                "service:b",
+                "version:1"
+            ],
+            deduped
+        );
+
+        // The output doesn't depend on the order of tags with distinct keys
+        let mut reordered = tags.to_vec();
+        reordered.swap(0, 2);
+        reordered.swap(7, 8);
+        assert_eq!(dedupe_tags(tags.to_vec()), dedupe_tags(reordered));
+
+        assert!(dedupe_tags(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_unified_service_tags() {
+        let tags = unified_service_tags("my-service", "prod", "1.2.3");
+        let tags: Vec<&str> = tags.iter().map(Tag::as_ref).collect();
+        assert_eq!(
+            vec!["service:my-service", "env:prod", "version:1.2.3"],
+            tags
+        );
+
+        let tags = unified_service_tags("my-service", "", "1.2.3");
+        let tags: Vec<&str> = tags.iter().map(Tag::as_ref).collect();
+        assert_eq!(vec!["service:my-service", "version:1.2.3"], tags);
+
+        assert!(unified_service_tags("", "", "").is_empty());
+        assert_eq!(1, unified_service_tags("my-service", "", "1.0:").len());
+    }
 }
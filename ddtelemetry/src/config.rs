@@ -263,10 +263,7 @@ impl Config {
     pub fn set_host_from_url(&mut self, host_url: &str) -> anyhow::Result<()> {
         let endpoint = self.endpoint.take().unwrap_or_default();
 
-        self.set_endpoint(Endpoint {
-            url: parse_uri(host_url)?,
-            ..endpoint
-        })
+        self.set_endpoint(endpoint.with_url(parse_uri(host_url)?))
     }
 }
 
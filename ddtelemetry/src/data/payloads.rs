@@ -68,6 +68,7 @@ pub struct Distributions {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct Log {
     pub message: String,
     pub level: LogLevel,
@@ -79,6 +80,24 @@ pub struct Log {
     pub tags: String,
     #[serde(default)]
     pub is_sensitive: bool,
+    /// Whether the log reports a crash, which the backend handles separately from other logs.
+    #[serde(default)]
+    pub is_crash: bool,
+}
+
+impl Log {
+    /// A log occurring once, without stack trace nor tags, neither sensitive nor a crash.
+    pub fn new(message: String, level: LogLevel) -> Self {
+        Log {
+            message,
+            level,
+            count: 1,
+            stack_trace: None,
+            tags: String::new(),
+            is_sensitive: false,
+            is_crash: false,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
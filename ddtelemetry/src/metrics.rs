@@ -46,6 +46,62 @@ impl MetricBucket {
     }
 }
 
+/// Points of a distribution are kept as is until there are too many of them, after which they are
+/// summarized in a sketch. This bounds the memory used by high-frequency distributions.
+#[derive(Debug)]
+enum DistributionBuffer {
+    Raw(Vec<f64>),
+    Sketch(DDSketch),
+}
+
+impl Default for DistributionBuffer {
+    fn default() -> Self {
+        DistributionBuffer::Raw(Vec::new())
+    }
+}
+
+impl DistributionBuffer {
+    const MAX_RAW_POINTS: usize = 256;
+
+    fn add_point(&mut self, point: f64) {
+        match self {
+            DistributionBuffer::Raw(points) if points.len() < Self::MAX_RAW_POINTS => {
+                points.push(point)
+            }
+            DistributionBuffer::Raw(points) => {
+                let mut sketch = DDSketch::default();
+                for point in points.drain(..).chain(std::iter::once(point)) {
+                    let _ = sketch.add(point);
+                }
+                *self = DistributionBuffer::Sketch(sketch);
+            }
+            DistributionBuffer::Sketch(sketch) => {
+                let _ = sketch.add(point);
+            }
+        }
+    }
+
+    fn point_count(&self) -> u32 {
+        match self {
+            DistributionBuffer::Raw(points) => points.len() as u32,
+            DistributionBuffer::Sketch(sketch) => sketch.count() as u32,
+        }
+    }
+
+    fn into_sketch(self) -> DDSketch {
+        match self {
+            DistributionBuffer::Raw(points) => {
+                let mut sketch = DDSketch::default();
+                for point in points {
+                    let _ = sketch.add(point);
+                }
+                sketch
+            }
+            DistributionBuffer::Sketch(sketch) => sketch,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 #[repr(C)]
 pub struct ContextKey(u32, metrics::MetricType);
@@ -60,7 +116,7 @@ struct BucketKey {
 pub struct MetricBuckets {
     buckets: HashMap<BucketKey, MetricBucket>,
     series: HashMap<BucketKey, Vec<(u64, f64)>>,
-    distributions: HashMap<BucketKey, DDSketch>,
+    distributions: HashMap<BucketKey, DistributionBuffer>,
 }
 
 #[derive(Default, Serialize, Deserialize)]
@@ -109,7 +165,7 @@ impl MetricBuckets {
                     extra_tags,
                 },
                 points,
-            )| (context_key, extra_tags, points),
+            )| (context_key, extra_tags, points.into_sketch()),
         )
     }
 
@@ -134,7 +190,10 @@ impl MetricBuckets {
                 })
                 .add_point(point),
             metrics::MetricType::Distribution => {
-                let _ = self.distributions.entry(bucket_key).or_default().add(point);
+                self.distributions
+                    .entry(bucket_key)
+                    .or_default()
+                    .add_point(point);
             }
         }
     }
@@ -148,12 +207,7 @@ impl MetricBuckets {
             distributions_points: self
                 .distributions
                 .values()
-                .flat_map(|sketch| {
-                    sketch
-                        .ordered_bins()
-                        .into_iter()
-                        .map(|(_, weight)| weight as u32)
-                })
+                .map(DistributionBuffer::point_count)
                 .sum(),
         }
     }
@@ -453,6 +507,53 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_distribution_summarized_past_threshold() {
+        let mut buckets = MetricBuckets::default();
+        let contexts = MetricContexts::default();
+        let context_key = contexts.register_metric_context(
+            "metric_distribution".into(),
+            Vec::new(),
+            MetricType::Distribution,
+            false,
+            MetricNamespace::Tracers,
+        );
+
+        let points: Vec<f64> = (1..=10_000).map(|i| i as f64).collect();
+        for point in &points {
+            buckets.add_point(context_key, *point, Vec::new());
+        }
+        assert!(matches!(
+            buckets.distributions.values().next(),
+            Some(DistributionBuffer::Sketch(_))
+        ));
+        assert_eq!(buckets.stats().distributions_points, 10_000);
+
+        let distributions: Vec<_> = buckets.flush_distributions().collect();
+        assert_eq!(distributions.len(), 1);
+        let bins = distributions[0].2.ordered_bins();
+
+        let count: f64 = bins.iter().map(|(_, w)| w).sum();
+        assert_approx_eq!(count, 10_000.0);
+        let sum: f64 = bins.iter().map(|(v, w)| v * w).sum();
+        let true_sum: f64 = points.iter().sum();
+        assert_approx_eq!(sum, true_sum, true_sum * 0.02);
+
+        let quantile = |q: f64| {
+            let rank = q * (count - 1.0);
+            let mut seen = 0.0;
+            for (value, weight) in &bins {
+                seen += weight;
+                if seen > rank {
+                    return *value;
+                }
+            }
+            unreachable!()
+        };
+        assert_approx_eq!(quantile(0.5), 5000.0, 5000.0 * 0.02);
+        assert_approx_eq!(quantile(0.99), 9900.0, 9900.0 * 0.02);
+    }
+
     #[test]
     fn test_stats() {
         let mut buckets = MetricBuckets::default();
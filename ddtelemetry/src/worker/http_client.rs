@@ -57,10 +57,15 @@ pub fn from_config(c: &Config) -> Box<dyn HttpClient + Sync + Send> {
         }
         Some(_) | None => {}
     };
+    let connector = c
+        .endpoint
+        .as_ref()
+        .map(ddcommon::connector::Connector::for_endpoint)
+        .unwrap_or_default();
     Box::new(HyperClient {
         inner: hyper::Client::builder()
             .pool_idle_timeout(std::time::Duration::from_secs(30))
-            .build(ddcommon::connector::Connector::default()),
+            .build(connector),
     })
 }
 
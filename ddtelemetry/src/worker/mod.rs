@@ -12,6 +12,7 @@ use crate::{
     metrics::{ContextKey, MetricBuckets, MetricContexts},
     worker::builder::ConfigBuilder,
 };
+use ddcommon::channel::DropCountingSender;
 use ddcommon::tag::Tag;
 use ddcommon::Endpoint;
 
@@ -80,6 +81,9 @@ pub enum TelemetryActions {
     AddIntegration(Integration),
     AddLog((LogIdentifier, Log)),
     Lifecycle(LifecycleAction),
+    /// Forgets the accumulated dependencies, integrations, configurations and logs, e.g. when the
+    /// runtime re-initializes, without stopping the worker
+    Reset,
     #[serde(skip)]
     CollectStats(oneshot::Sender<TelemetryWorkerStats>),
 }
@@ -125,6 +129,7 @@ pub struct TelemetryWorker {
     client: Box<dyn http_client::HttpClient + Sync + Send>,
     deadlines: scheduler::Scheduler<LifecycleAction>,
     data: TelemetryWorkerData,
+    last_heartbeat: Arc<Mutex<Option<time::Instant>>>,
 }
 
 #[derive(Default, Serialize, Deserialize)]
@@ -281,6 +286,7 @@ impl TelemetryWorker {
                     .schedule_event(LifecycleAction::FlushData)
                     .unwrap();
             }
+            Reset => self.data.logs = store::QueueHashMap::default(),
             AddConfig(_) | AddDependecy(_) | AddIntegration(_) | Lifecycle(ExtendedHeartbeat) => {}
             Lifecycle(Stop) => {
                 if !self.data.started {
@@ -355,6 +361,12 @@ impl TelemetryWorker {
             AddDependecy(dep) => self.data.dependencies.insert(dep),
             AddIntegration(integration) => self.data.integrations.insert(integration),
             AddConfig(cfg) => self.data.configurations.insert(cfg),
+            Reset => {
+                self.data.dependencies.clear();
+                self.data.integrations.clear();
+                self.data.configurations.clear();
+                self.data.logs = store::QueueHashMap::default();
+            }
             AddLog((identifier, log)) => {
                 let (l, new) = self.data.logs.get_mut_or_insert(identifier, log);
                 if !new {
@@ -385,6 +397,7 @@ impl TelemetryWorker {
                     Ok(()) => self.payload_sent_success(&payload),
                     Err(err) => self.log_err(&err),
                 }
+                *self.last_heartbeat.lock().unwrap() = Some(time::Instant::now());
 
                 let batch = self.build_observability_batch();
                 if !batch.is_empty() {
@@ -738,13 +751,14 @@ impl InnerTelemetryShutdown {
 /// To stop the worker, call `TelemetryWorkerHandle::send_stop` which trigger flush asynchronously
 /// then `TelemetryWorkerHandle::wait_for_shutdown`
 pub struct TelemetryWorkerHandle {
-    sender: mpsc::Sender<TelemetryActions>,
+    sender: DropCountingSender<TelemetryActions>,
     shutdown: Arc<InnerTelemetryShutdown>,
     cancellation_token: CancellationToken,
     // Used to spawn cancellation tasks
     runtime: runtime::Handle,
 
     contexts: MetricContexts,
+    last_heartbeat: Arc<Mutex<Option<time::Instant>>>,
 }
 
 impl TelemetryWorkerHandle {
@@ -765,7 +779,7 @@ impl TelemetryWorkerHandle {
     }
 
     pub async fn send_msg(&self, msg: TelemetryActions) -> Result<()> {
-        Ok(self.sender.send(msg).await?)
+        Ok(self.sender.inner().send(msg).await?)
     }
 
     pub async fn send_msgs<T>(&self, msgs: T) -> Result<()>
@@ -773,7 +787,7 @@ impl TelemetryWorkerHandle {
         T: IntoIterator<Item = TelemetryActions>,
     {
         for msg in msgs {
-            self.sender.send(msg).await?;
+            self.sender.inner().send(msg).await?;
         }
 
         Ok(())
@@ -784,7 +798,7 @@ impl TelemetryWorkerHandle {
         msg: TelemetryActions,
         timeout: time::Duration,
     ) -> Result<()> {
-        Ok(self.sender.send_timeout(msg, timeout).await?)
+        Ok(self.sender.inner().send_timeout(msg, timeout).await?)
     }
 
     pub fn send_start(&self) -> Result<()> {
@@ -844,9 +858,39 @@ impl TelemetryWorkerHandle {
         message: String,
         level: data::LogLevel,
         stack_trace: Option<String>,
+    ) -> Result<()> {
+        self.add_log_inner(identifier, message, level, stack_trace, false)
+    }
+
+    /// Adds an error log flagged as a crash report. Like other logs, it is deduplicated by
+    /// identifier and aggregated into a count.
+    pub fn add_crash_log<T: Hash>(
+        &self,
+        identifier: T,
+        message: String,
+        stack_trace: Option<String>,
+    ) -> Result<()> {
+        self.add_log_inner(
+            identifier,
+            message,
+            data::LogLevel::Error,
+            stack_trace,
+            true,
+        )
+    }
+
+    fn add_log_inner<T: Hash>(
+        &self,
+        identifier: T,
+        message: String,
+        level: data::LogLevel,
+        stack_trace: Option<String>,
+        is_crash: bool,
     ) -> Result<()> {
         let mut hasher = DefaultHasher::new();
         identifier.hash(&mut hasher);
+        // A crash report must not be merged into a regular log with the same identifier
+        is_crash.hash(&mut hasher);
         self.sender.try_send(TelemetryActions::AddLog((
             LogIdentifier {
                 indentifier: hasher.finish(),
@@ -858,6 +902,7 @@ impl TelemetryWorkerHandle {
                 count: 1,
                 tags: String::new(),
                 is_sensitive: false,
+                is_crash,
             },
         )))?;
         Ok(())
@@ -879,6 +924,21 @@ impl TelemetryWorkerHandle {
             .try_send(TelemetryActions::CollectStats(sender))?;
         Ok(receiver)
     }
+
+    pub fn send_reset(&self) -> Result<()> {
+        Ok(self.sender.try_send(TelemetryActions::Reset)?)
+    }
+
+    /// Number of actions which could not be enqueued because the worker mailbox was full or
+    /// already closed.
+    pub fn dropped_actions(&self) -> u64 {
+        self.sender.dropped()
+    }
+
+    /// When the worker last emitted a heartbeat, successfully sent or not, if it did yet.
+    pub fn last_heartbeat(&self) -> Option<time::Instant> {
+        *self.last_heartbeat.lock().unwrap()
+    }
 }
 
 /// How many dependencies/integrations/configs we keep in memory at most
@@ -949,7 +1009,7 @@ impl TelemetryWorkerBuilder {
         external_config: Config,
         tokio_runtime: Handle,
     ) -> Result<(TelemetryWorkerHandle, TelemetryWorker)> {
-        let (tx, mailbox) = mpsc::channel(5000);
+        let (tx, mailbox) = DropCountingSender::channel(5000);
         let shutdown = Arc::new(InnerTelemetryShutdown {
             is_shutdown: Mutex::new(false),
             condvar: Condvar::new(),
@@ -959,6 +1019,7 @@ impl TelemetryWorkerBuilder {
         let config = self.config.merge(external_config);
         let telemetry_hearbeat_interval = config.telemetry_hearbeat_interval;
         let client = http_client::from_config(&config);
+        let last_heartbeat = Arc::new(Mutex::new(None));
 
         let worker = TelemetryWorker {
             data: TelemetryWorkerData {
@@ -991,6 +1052,7 @@ impl TelemetryWorkerBuilder {
                 ),
             ]),
             cancellation_token: token.clone(),
+            last_heartbeat: last_heartbeat.clone(),
         };
 
         Ok((
@@ -1000,6 +1062,7 @@ impl TelemetryWorkerBuilder {
                 cancellation_token: token,
                 runtime: tokio_runtime,
                 contexts,
+                last_heartbeat,
             },
             worker,
         ))
@@ -1064,7 +1127,10 @@ impl TelemetryWorkerBuilder {
 
 #[cfg(test)]
 mod tests {
-    use crate::worker::TelemetryWorkerHandle;
+    use crate::config::Config;
+    use crate::data::{Dependency, Payload};
+    use crate::worker::{TelemetryActions, TelemetryWorkerBuilder, TelemetryWorkerHandle};
+    use std::time::Duration;
 
     fn is_send<T: Send>(_: T) {}
     fn is_sync<T: Sync>(_: T) {}
@@ -1076,4 +1142,118 @@ mod tests {
         #[allow(clippy::redundant_closure)]
         let _ = |h: TelemetryWorkerHandle| is_sync(h);
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_last_heartbeat() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let config = Config {
+            telemetry_hearbeat_interval: Duration::from_millis(50),
+            ..Config::default()
+        };
+        let builder = TelemetryWorkerBuilder::new(
+            "host".to_string(),
+            "service".to_string(),
+            "rust".to_string(),
+            "1.0".to_string(),
+            "1.0".to_string(),
+        );
+        let (handle, _) = runtime.block_on(builder.spawn_with_config(config)).unwrap();
+        assert_eq!(handle.last_heartbeat(), None);
+
+        handle.send_start().unwrap();
+        std::thread::sleep(Duration::from_millis(500));
+        let last_heartbeat = handle.last_heartbeat().expect("no heartbeat was recorded");
+        assert!(last_heartbeat.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_reset() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let builder = TelemetryWorkerBuilder::new(
+            "host".to_string(),
+            "service".to_string(),
+            "rust".to_string(),
+            "1.0".to_string(),
+            "1.0".to_string(),
+        );
+        let (_handle, mut worker) = builder
+            .build_worker(Config::default(), runtime.handle().clone())
+            .unwrap();
+
+        runtime.block_on(async {
+            for name in ["dep1", "dep2"] {
+                let dependency = TelemetryActions::AddDependecy(Dependency {
+                    name: name.to_string(),
+                    version: None,
+                });
+                let _ = worker.dispatch_action(dependency).await;
+            }
+            assert_eq!(worker.build_app_events_batch().len(), 1);
+
+            let _ = worker.dispatch_action(TelemetryActions::Reset).await;
+        });
+        assert!(worker.build_app_events_batch().is_empty());
+        assert_eq!(worker.stats().dependencies_stored, 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_crash_log() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let builder = TelemetryWorkerBuilder::new(
+            "host".to_string(),
+            "service".to_string(),
+            "rust".to_string(),
+            "1.0".to_string(),
+            "1.0".to_string(),
+        );
+        let (handle, mut worker) = builder
+            .build_worker(Config::default(), runtime.handle().clone())
+            .unwrap();
+
+        for _ in 0..2 {
+            handle
+                .add_crash_log("crash", "segfault".to_string(), Some("trace".to_string()))
+                .unwrap();
+        }
+        // Same identifier, but not a crash
+        handle
+            .add_log(
+                "crash",
+                "message".to_string(),
+                crate::data::LogLevel::Warn,
+                None,
+            )
+            .unwrap();
+        runtime.block_on(async {
+            while let Ok(action) = worker.mailbox.try_recv() {
+                let _ = worker.dispatch_action(action).await;
+            }
+        });
+
+        let batch = worker.build_observability_batch();
+        let [Payload::Logs(logs)] = batch.as_slice() else {
+            panic!("Expected a single logs payload, got {batch:?}");
+        };
+        let logs = serde_json::to_value(logs).unwrap();
+        let logs = logs.as_array().unwrap();
+        assert_eq!(logs.len(), 2);
+        let crash = logs.iter().find(|l| l["message"] == "segfault").unwrap();
+        assert_eq!(crash["is_crash"], true);
+        assert_eq!(crash["level"], "ERROR");
+        assert_eq!(crash["count"], 2);
+        let log = logs.iter().find(|l| l["message"] == "message").unwrap();
+        assert_eq!(log["is_crash"], false);
+    }
 }
@@ -204,6 +204,11 @@ where
     pub fn len_stored(&self) -> usize {
         self.items.len()
     }
+
+    // Forget all stored items, flushed or not
+    pub fn clear(&mut self) {
+        *self = Self::new(self.max_items);
+    }
 }
 
 impl<T> Extend<T> for Store<T>
@@ -278,6 +283,20 @@ mod tests {
         assert_eq!(store.unflushed().collect::<Vec<_>>(), &[&6, &7, &8, &9]);
     }
 
+    #[test]
+    fn test_clear() {
+        let mut store = Store::new(10);
+        store.insert("hello");
+        store.removed_flushed(1);
+        store.insert("world");
+        store.clear();
+        assert_eq!(store.len_stored(), 0);
+        assert!(!store.flush_not_empty());
+
+        store.insert("hello");
+        assert_eq!(store.unflushed().collect::<Vec<_>>(), vec![&"hello"]);
+    }
+
     #[test]
     fn test_unflush_stored() {
         let mut store = Store::new(5);
@@ -5,25 +5,41 @@
 //! dogstatsd-client implements a client to emit metrics to a dogstatsd server.
 //! This is made use of in at least the data-pipeline and sidecar crates.
 
-use ddcommon::tag::Tag;
+use ddcommon::tag::{dedupe_tags, Tag};
 use ddcommon::Endpoint;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Debug;
-use tracing::{debug, error, info};
+use std::str::FromStr;
+use tracing::{debug, error, info, warn};
 
 use anyhow::anyhow;
 use cadence::prelude::*;
 #[cfg(unix)]
 use cadence::UnixMetricSink;
-use cadence::{Metric, MetricBuilder, QueuingMetricSink, StatsdClient, UdpMetricSink};
+use cadence::{
+    Metric, MetricBuilder, MetricError, MetricSink, QueuingMetricSink, SinkStats, StatsdClient,
+    UdpMetricSink,
+};
 #[cfg(unix)]
 use ddcommon::connector::uds::socket_path_from_uri;
+use std::io;
 use std::net::{ToSocketAddrs, UdpSocket};
 #[cfg(unix)]
 use std::os::unix::net::UnixDatagram;
+use std::panic::RefUnwindSafe;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
 // Queue with a maximum capacity of 32K elements
 const QUEUE_SIZE: usize = 32 * 1024;
+// Maximum time spent waiting for the queue to be drained when flushing
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+// Appended to truncated tag values
+const TRUNCATION_INDICATOR: &str = "...";
+// Minimum time between two warnings about dropped tags
+const TAGS_DROPPED_WARNING_INTERVAL: Duration = Duration::from_secs(60);
 
 /// The `DogStatsDActionOwned` enum gathers the metric types that can be sent to the DogStatsD
 /// server. This type takes ownership of the relevant data to support the sidecar better.
@@ -47,6 +63,90 @@ pub enum DogStatsDActionOwned {
     /// but Golang implementation uses string (https://github.com/DataDog/datadog-go/blob/331d24832f7eac97b091efd696278fe2c4192b29/statsd/statsd.go#L230)
     /// and PHP implementation uses float or string (https://github.com/DataDog/php-datadogstatsd/blob/0efdd1c38f6d3dd407efbb899ad1fd2e5cd18085/src/DogStatsd.php#L251)
     Set(String, i64, Vec<Tag>),
+    /// A set counting unique string values, e.g. user ids. Cadence has no support for these, the
+    /// datagram is formatted by this crate.
+    SetString(String, String, Vec<Tag>),
+    /// A distribution the caller only sends a fraction of the values of, given by the sample rate
+    /// in (0, 1), e.g. 0.1 when one in ten values is sent. The rate is appended to the datagram as
+    /// `|@rate` for the agent to scale the counts back up, any other rate sends the value
+    /// unsampled. Cadence has no support for sample rates, the datagram is formatted by this crate.
+    SampledDistribution(String, f64, f64, Vec<Tag>),
+    /// A histogram with a sample rate, see [`DogStatsDActionOwned::SampledDistribution`].
+    SampledHistogram(String, f64, f64, Vec<Tag>),
+}
+
+impl DogStatsDActionOwned {
+    /// The tags of the metric, e.g. to add a tag to any kind of action.
+    pub fn tags_mut(&mut self) -> &mut Vec<Tag> {
+        match self {
+            DogStatsDActionOwned::Count(_, _, tags)
+            | DogStatsDActionOwned::Distribution(_, _, tags)
+            | DogStatsDActionOwned::Gauge(_, _, tags)
+            | DogStatsDActionOwned::Histogram(_, _, tags)
+            | DogStatsDActionOwned::Set(_, _, tags)
+            | DogStatsDActionOwned::SetString(_, _, tags)
+            | DogStatsDActionOwned::SampledDistribution(_, _, _, tags)
+            | DogStatsDActionOwned::SampledHistogram(_, _, _, tags) => tags,
+        }
+    }
+
+    /// The metric type of the action.
+    pub fn kind(&self) -> MetricKind {
+        match self {
+            DogStatsDActionOwned::Count(..) => MetricKind::Count,
+            DogStatsDActionOwned::Distribution(..)
+            | DogStatsDActionOwned::SampledDistribution(..) => MetricKind::Distribution,
+            DogStatsDActionOwned::Gauge(..) => MetricKind::Gauge,
+            DogStatsDActionOwned::Histogram(..) | DogStatsDActionOwned::SampledHistogram(..) => {
+                MetricKind::Histogram
+            }
+            DogStatsDActionOwned::Set(..) => MetricKind::Set,
+            DogStatsDActionOwned::SetString(..) => MetricKind::SetString,
+        }
+    }
+}
+
+/// The metric types supported by the client, one per variant of [`DogStatsDActionOwned`] and
+/// [`DogStatsDAction`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetricKind {
+    #[allow(missing_docs)]
+    Count,
+    #[allow(missing_docs)]
+    Distribution,
+    #[allow(missing_docs)]
+    Gauge,
+    #[allow(missing_docs)]
+    Histogram,
+    #[allow(missing_docs)]
+    Set,
+    #[allow(missing_docs)]
+    SetString,
+}
+
+impl MetricKind {
+    /// The type of the metric in the datagram, e.g. `c` in `page.views:1|c`.
+    pub fn wire_suffix(&self) -> &'static str {
+        match self {
+            MetricKind::Count => "c",
+            MetricKind::Distribution => "d",
+            MetricKind::Gauge => "g",
+            MetricKind::Histogram => "h",
+            MetricKind::Set | MetricKind::SetString => "s",
+        }
+    }
+}
+
+/// All the metric types supported by the client, e.g. to generate test matrices.
+pub fn all_metric_kinds() -> &'static [MetricKind] {
+    &[
+        MetricKind::Count,
+        MetricKind::Distribution,
+        MetricKind::Gauge,
+        MetricKind::Histogram,
+        MetricKind::Set,
+        MetricKind::SetString,
+    ]
 }
 
 /// The `DogStatsDAction` enum gathers the metric types that can be sent to the DogStatsD server.
@@ -65,94 +165,744 @@ pub enum DogStatsDAction<'a, T: AsRef<str>, V: IntoIterator<Item = &'a Tag>> {
     /// but Golang implementation uses string (https://github.com/DataDog/datadog-go/blob/331d24832f7eac97b091efd696278fe2c4192b29/statsd/statsd.go#L230)
     /// and PHP implementation uses float or string (https://github.com/DataDog/php-datadogstatsd/blob/0efdd1c38f6d3dd407efbb899ad1fd2e5cd18085/src/DogStatsd.php#L251)
     Set(T, i64, V),
+    /// A set counting unique string values, e.g. user ids. Cadence has no support for these, the
+    /// datagram is formatted by this crate.
+    SetString(T, T, V),
+    /// A distribution with a sample rate, see [`DogStatsDActionOwned::SampledDistribution`].
+    SampledDistribution(T, f64, f64, V),
+    /// A histogram with a sample rate, see [`DogStatsDActionOwned::SampledDistribution`].
+    SampledHistogram(T, f64, f64, V),
+}
+
+impl<'a, T: AsRef<str>, V: IntoIterator<Item = &'a Tag>> DogStatsDAction<'a, T, V> {
+    /// The metric type of the action.
+    pub fn kind(&self) -> MetricKind {
+        match self {
+            DogStatsDAction::Count(..) => MetricKind::Count,
+            DogStatsDAction::Distribution(..) | DogStatsDAction::SampledDistribution(..) => {
+                MetricKind::Distribution
+            }
+            DogStatsDAction::Gauge(..) => MetricKind::Gauge,
+            DogStatsDAction::Histogram(..) | DogStatsDAction::SampledHistogram(..) => {
+                MetricKind::Histogram
+            }
+            DogStatsDAction::Set(..) => MetricKind::Set,
+            DogStatsDAction::SetString(..) => MetricKind::SetString,
+        }
+    }
+}
+
+/// The tag cardinality the agent should use for a metric, sent as the `|card:<level>` field of the
+/// datagram. Older agents ignore the field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Cardinality {
+    #[allow(missing_docs)]
+    None,
+    #[allow(missing_docs)]
+    Low,
+    #[allow(missing_docs)]
+    Orchestrator,
+    #[allow(missing_docs)]
+    High,
+}
+
+impl Cardinality {
+    const ALL: [Cardinality; 4] = [
+        Cardinality::None,
+        Cardinality::Low,
+        Cardinality::Orchestrator,
+        Cardinality::High,
+    ];
+
+    /// The level as sent in the datagram, e.g. `low` in `page.views:1|c|card:low`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Cardinality::None => "none",
+            Cardinality::Low => "low",
+            Cardinality::Orchestrator => "orchestrator",
+            Cardinality::High => "high",
+        }
+    }
+}
+
+impl FromStr for Cardinality {
+    type Err = anyhow::Error;
+
+    /// Parses a cardinality level, case-insensitively. Returns an error for unknown levels.
+    fn from_str(level: &str) -> anyhow::Result<Self> {
+        Cardinality::ALL
+            .into_iter()
+            .find(|c| c.as_str().eq_ignore_ascii_case(level))
+            .ok_or_else(|| anyhow!("invalid cardinality level {level:?}"))
+    }
+}
+
+/// A metric sink which can be shared between the cadence client and raw sends.
+#[derive(Clone)]
+struct SharedSink(Arc<dyn MetricSink + Send + Sync + RefUnwindSafe>);
+
+impl Debug for SharedSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedSink").finish_non_exhaustive()
+    }
+}
+
+impl MetricSink for SharedSink {
+    fn emit(&self, metric: &str) -> io::Result<usize> {
+        self.0.emit(metric)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.0.flush()
+    }
+
+    fn stats(&self) -> SinkStats {
+        self.0.stats()
+    }
+}
+
+/// A metric sink keeping the emitted datagrams in memory instead of sending them.
+#[derive(Default)]
+struct CaptureSink(Mutex<Vec<String>>);
+
+impl MetricSink for CaptureSink {
+    fn emit(&self, metric: &str) -> io::Result<usize> {
+        self.0.lock().unwrap().push(metric.to_string());
+        Ok(metric.len())
+    }
+}
+
+/// A metric sink appending the `|c:<origin>` and `|card:<level>` fields, if any, to every
+/// datagram.
+struct FieldsSink {
+    inner: SharedSink,
+    origin: Option<String>,
+    cardinality: Option<Cardinality>,
+}
+
+impl MetricSink for FieldsSink {
+    fn emit(&self, metric: &str) -> io::Result<usize> {
+        if self.origin.is_none() && self.cardinality.is_none() {
+            return self.inner.emit(metric);
+        }
+        let mut line = metric.to_string();
+        push_fields(&mut line, self.origin.as_deref(), self.cardinality);
+        self.inner.emit(&line)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    fn stats(&self) -> SinkStats {
+        self.inner.stats()
+    }
+}
+
+/// Appends the `|c:<origin>` and `|card:<level>` fields to a datagram, in the order the agent
+/// expects them.
+fn push_fields(line: &mut String, origin: Option<&str>, cardinality: Option<Cardinality>) {
+    if let Some(origin) = origin {
+        line.push_str("|c:");
+        line.push_str(origin);
+    }
+    if let Some(cardinality) = cardinality {
+        line.push_str("|card:");
+        line.push_str(cardinality.as_str());
+    }
+}
+
+/// Number of metrics processed by a [`CountingSink`], notifying waiters on every change.
+#[derive(Default)]
+struct ProcessedCount {
+    count: Mutex<u64>,
+    changed: Condvar,
+}
+
+/// A metric sink counting the metrics it has processed, whether they were sent successfully or not.
+struct CountingSink<T> {
+    inner: T,
+    processed: Arc<ProcessedCount>,
+}
+
+impl<T: MetricSink> MetricSink for CountingSink<T> {
+    fn emit(&self, metric: &str) -> io::Result<usize> {
+        let result = self.inner.emit(metric);
+        *self.processed.count.lock().unwrap() += 1;
+        self.processed.changed.notify_all();
+        result
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    fn stats(&self) -> SinkStats {
+        self.inner.stats()
+    }
+}
+
+/// A metric sink queuing metrics to be sent from a background thread. Unlike a bare
+/// `QueuingMetricSink`, flushing waits for all the metrics queued so far to have been sent.
+struct DrainableQueuingSink {
+    queue: QueuingMetricSink,
+    processed: Arc<ProcessedCount>,
+}
+
+impl DrainableQueuingSink {
+    fn new<T>(sink: T) -> Self
+    where
+        T: MetricSink + Send + Sync + RefUnwindSafe + 'static,
+    {
+        let processed = Arc::new(ProcessedCount::default());
+        let sink = CountingSink {
+            inner: sink,
+            processed: processed.clone(),
+        };
+        DrainableQueuingSink {
+            queue: QueuingMetricSink::with_capacity(sink, QUEUE_SIZE),
+            processed,
+        }
+    }
+}
+
+impl MetricSink for DrainableQueuingSink {
+    fn emit(&self, metric: &str) -> io::Result<usize> {
+        self.queue.emit(metric)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let submitted = self.queue.submitted();
+        let (processed, _) = self
+            .processed
+            .changed
+            .wait_timeout_while(
+                self.processed.count.lock().unwrap(),
+                FLUSH_TIMEOUT,
+                |processed| *processed < submitted,
+            )
+            .unwrap();
+        let drained = *processed >= submitted;
+        drop(processed);
+        if !drained {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "timed out waiting for queued metrics to be sent",
+            ));
+        }
+        self.queue.flush()
+    }
+
+    fn stats(&self) -> SinkStats {
+        self.queue.stats()
+    }
+}
+
+/// A metric sink emitting every metric to all of the wrapped sinks.
+/// A failing sink is logged and does not prevent emitting to the others.
+struct TeeSink(Vec<SharedSink>);
+
+impl MetricSink for TeeSink {
+    fn emit(&self, metric: &str) -> io::Result<usize> {
+        let mut result = Ok(0);
+        let mut emitted = false;
+        for sink in &self.0 {
+            match sink.emit(metric) {
+                Ok(written) => {
+                    emitted = true;
+                    result = Ok(written);
+                }
+                Err(err) => {
+                    error!(
+                        "Error while emitting metric to one of the endpoints: {}",
+                        err
+                    );
+                    if !emitted {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut result = Ok(());
+        for sink in &self.0 {
+            if let Err(err) = sink.flush() {
+                error!("Error while flushing one of the endpoints: {}", err);
+                result = Err(err);
+            }
+        }
+        result
+    }
+
+    fn stats(&self) -> SinkStats {
+        self.0
+            .iter()
+            .map(|sink| sink.stats())
+            .fold(SinkStats::default(), |mut total, stats| {
+                total.bytes_sent += stats.bytes_sent;
+                total.packets_sent += stats.packets_sent;
+                total.bytes_dropped += stats.bytes_dropped;
+                total.packets_dropped += stats.packets_dropped;
+                total
+            })
+    }
 }
 
 /// A dogstatsd-client that flushes stats to a given endpoint. Use `new_flusher` to build one.
 #[derive(Debug)]
 pub struct Client {
     client: StatsdClient,
+    /// Same as `client`, additionally sending each cardinality in the `|card:` field.
+    cardinality_clients: HashMap<Cardinality, StatsdClient>,
+    /// The sink all metrics are emitted to, teeing to `endpoint_sinks` if there are several.
+    sink: SharedSink,
+    /// The sink of the main endpoint, followed by the ones of additional endpoints.
+    endpoint_sinks: Vec<SharedSink>,
+    /// The endpoints `endpoint_sinks` were created from, in the same order.
+    endpoints: Vec<Endpoint>,
+    namespace_prefix: String,
+    buffered_async: bool,
+    /// The cardinality sent with every metric unless overridden when sending.
+    cardinality: Option<Cardinality>,
+    /// The `host` tag added to every metric, if any.
+    host_tag: Option<Tag>,
+    /// The `|c:` origin detection field sent with every metric, if enabled.
+    origin: Option<String>,
+    /// Tag values longer than this are truncated, if set.
+    max_tag_value_length: Option<usize>,
+    /// Whether tags are normalized like the agent does before being sent.
+    normalize_tags: bool,
+    /// Whether tags with duplicate keys are removed before being sent.
+    dedupe_tags: bool,
+    /// Tags of a metric beyond this number are dropped, if set.
+    max_tags: Option<usize>,
+    /// When dropping tags was last logged, to rate limit the warning.
+    tags_dropped_logged_at: Mutex<Option<Instant>>,
 }
 
 /// Build a new flusher instance pointed at the provided endpoint.
 /// Returns error if the provided endpoint is not valid.
-pub fn new_flusher(endpoint: Endpoint) -> anyhow::Result<Client> {
-    Ok(Client {
-        client: create_client(&endpoint)?,
-    })
+pub fn new_flusher(endpoint: Endpoint) -> Result<Client, DogStatsDError> {
+    let sink = create_sink(&endpoint, true)?;
+    Ok(Client::from_sink(sink, Some(endpoint), true))
 }
 
 impl Client {
+    /// A client with default settings emitting to `sink`, which was created from `endpoint`, if
+    /// any.
+    fn from_sink(sink: SharedSink, endpoint: Option<Endpoint>, buffered_async: bool) -> Self {
+        let mut client = Client {
+            client: StatsdClient::from_sink("", sink.clone()),
+            cardinality_clients: HashMap::new(),
+            sink: sink.clone(),
+            endpoint_sinks: endpoint.iter().map(|_| sink.clone()).collect(),
+            endpoints: endpoint.into_iter().collect(),
+            namespace_prefix: String::new(),
+            buffered_async,
+            cardinality: None,
+            host_tag: None,
+            origin: None,
+            max_tag_value_length: None,
+            normalize_tags: false,
+            dedupe_tags: false,
+            max_tags: None,
+            tags_dropped_logged_at: Mutex::default(),
+        };
+        client.rebuild_clients();
+        client
+    }
+
+    /// Prepend the given namespace to the name of every metric emitted by this client, e.g. a
+    /// prefix of `datadog.tracer` turns `requests` into `datadog.tracer.requests`. The separating
+    /// dot is added by the client. An empty prefix removes any previously set namespace.
+    /// Returns an error if the prefix contains characters reserved by the dogstatsd protocol.
+    pub fn with_namespace_prefix(mut self, prefix: &str) -> anyhow::Result<Self> {
+        let prefix = prefix.strip_suffix('.').unwrap_or(prefix);
+        if let Some(c) = prefix
+            .chars()
+            .find(|c| matches!(c, ':' | '|' | '@' | '#' | ',') || c.is_whitespace())
+        {
+            anyhow::bail!("invalid character {c:?} in namespace prefix {prefix:?}");
+        }
+        if prefix.starts_with('.') || prefix.contains("..") {
+            anyhow::bail!("namespace prefix {prefix:?} contains an empty segment");
+        }
+        self.namespace_prefix = prefix.to_string();
+        self.rebuild_clients();
+        Ok(self)
+    }
+
+    /// Add a `host:<hostname>` tag to every metric emitted by this client, e.g. when the hostname
+    /// of the OS is a meaningless container id. `None` (the default) adds no host tag, letting the
+    /// agent assign one.
+    /// Returns an error if the hostname is not a valid tag value.
+    pub fn with_hostname(mut self, hostname: Option<String>) -> anyhow::Result<Self> {
+        self.host_tag = hostname.map(|h| Tag::new("host", h)).transpose()?;
+        self.rebuild_clients();
+        Ok(self)
+    }
+
+    /// Send the origin of every metric in the `|c:` field, see
+    /// [`ddcommon::entity_id::origin_detection_value`], so that the agent can attribute metrics
+    /// sent over UDS without entity headers. Nothing is sent when the origin cannot be determined.
+    /// Disabled by default.
+    pub fn with_origin_detection(self, enabled: bool) -> Self {
+        self.with_origin(
+            enabled
+                .then(ddcommon::entity_id::origin_detection_value)
+                .flatten(),
+        )
+    }
+
+    fn with_origin(mut self, origin: Option<String>) -> Self {
+        self.origin = origin;
+        self.rebuild_clients();
+        self
+    }
+
+    /// The tags added to every metric: the host tag, if any.
+    fn client_tags(&self) -> impl Iterator<Item = &Tag> {
+        self.host_tag.iter()
+    }
+
+    /// Truncate tag values longer than `max_length` bytes, so that a single oversized tag cannot push
+    /// the datagram past the MTU and get the whole metric truncated. Truncated values end with
+    /// `...`, which counts in the length. `None` (the default) disables the limit.
+    pub fn with_max_tag_value_length(mut self, max_length: Option<usize>) -> Self {
+        self.max_tag_value_length = max_length;
+        self
+    }
+
+    /// Normalize tags like the agent does before sending them, see [`Tag::normalize`], so that the
+    /// tags seen client-side match the ones stored by the agent. Disabled by default.
+    pub fn with_tag_normalization(mut self, normalize_tags: bool) -> Self {
+        self.normalize_tags = normalize_tags;
+        self
+    }
+
+    /// Only keep the last tag of each key, sorting tags by key, see [`dedupe_tags`]. This happens
+    /// before tags are capped. Disabled by default.
+    pub fn with_tag_deduplication(mut self, dedupe_tags: bool) -> Self {
+        self.dedupe_tags = dedupe_tags;
+        self
+    }
+
+    /// Drop the tags of a metric beyond the first `max_tags`, logging a warning at most once a
+    /// minute, to protect against accidental high cardinality. `None` (the default) disables the
+    /// limit.
+    pub fn with_max_tags(mut self, max_tags: Option<usize>) -> Self {
+        self.max_tags = max_tags;
+        self
+    }
+
+    /// Returns the tags of `metric` deduplicated if enabled, capped to the maximum number of tags,
+    /// normalized if enabled, with their values truncated to the maximum length of tag values.
+    fn prepare_tags<'t>(
+        &self,
+        metric: &str,
+        tags: impl IntoIterator<Item = &'t Tag>,
+    ) -> Vec<Cow<'t, str>> {
+        let mut tags: Vec<Cow<'t, Tag>> = if self.dedupe_tags {
+            dedupe_tags(tags.into_iter().cloned().collect())
+                .into_iter()
+                .map(Cow::Owned)
+                .collect()
+        } else {
+            tags.into_iter().map(Cow::Borrowed).collect()
+        };
+        if let Some(max_tags) = self.max_tags.filter(|max_tags| tags.len() > *max_tags) {
+            let mut logged_at = self.tags_dropped_logged_at.lock().unwrap();
+            if logged_at.map_or(true, |at| at.elapsed() >= TAGS_DROPPED_WARNING_INTERVAL) {
+                *logged_at = Some(Instant::now());
+                warn!(
+                    "Dropping {} of the {} tags of metric {metric}, only {max_tags} are sent",
+                    tags.len() - max_tags,
+                    tags.len()
+                );
+            }
+            tags.truncate(max_tags);
+        }
+        tags.into_iter()
+            .map(|tag| {
+                let tag = match tag {
+                    tag if self.normalize_tags => Cow::Owned(tag.normalize().to_string()),
+                    Cow::Borrowed(tag) => Cow::Borrowed(tag.as_ref()),
+                    Cow::Owned(tag) => Cow::Owned(tag.to_string()),
+                };
+                let capped = self.max_tag_value_length.and_then(|max_length| {
+                    match cap_tag_value(&tag, max_length) {
+                        Cow::Owned(capped) => Some(capped),
+                        Cow::Borrowed(_) => None,
+                    }
+                });
+                capped.map_or(tag, Cow::Owned)
+            })
+            .collect()
+    }
+
+    /// Builds a cadence client emitting to the given sink with the namespace and constant tags of
+    /// self.
+    fn statsd_client<T>(&self, sink: T) -> StatsdClient
+    where
+        T: MetricSink + Sync + Send + RefUnwindSafe + 'static,
+    {
+        let mut builder = StatsdClient::builder(&self.namespace_prefix, sink);
+        for tag in self.client_tags() {
+            builder = builder.with_tag_value(tag.as_ref());
+        }
+        builder.build()
+    }
+
+    /// Additionally send every metric to the given endpoint, e.g. to emit to both an old and a new
+    /// dogstatsd endpoint during a migration. Failing to emit to one of the endpoints is logged and
+    /// does not prevent emitting to the others.
+    /// Returns an error if the provided endpoint is invalid.
+    pub fn with_additional_endpoint(mut self, endpoint: Endpoint) -> anyhow::Result<Self> {
+        self.endpoint_sinks
+            .push(create_sink(&endpoint, self.buffered_async)?);
+        self.endpoints.push(endpoint);
+        self.rebuild_sink();
+        Ok(self)
+    }
+
+    /// By default (`true`) metrics are queued and sent from a background thread, so that sending
+    /// never blocks the caller. When `false`, metrics are sent synchronously by the calling thread,
+    /// which is deterministic and avoids spawning a thread, e.g. for tests or low-volume callers.
+    /// Returns an error if the sinks could not be recreated.
+    pub fn with_buffered_async(mut self, buffered_async: bool) -> anyhow::Result<Self> {
+        if buffered_async != self.buffered_async {
+            self.endpoint_sinks = self
+                .endpoints
+                .iter()
+                .map(|endpoint| create_sink(endpoint, buffered_async))
+                .collect::<Result<_, DogStatsDError>>()?;
+            self.buffered_async = buffered_async;
+            self.rebuild_sink();
+        }
+        Ok(self)
+    }
+
+    /// Send the given tag cardinality with every metric, unless overridden when sending. `None`
+    /// (the default) omits the `|card:` field and lets the agent use its configured cardinality.
+    pub fn with_cardinality(mut self, cardinality: Option<Cardinality>) -> Self {
+        self.cardinality = cardinality;
+        self
+    }
+
+    /// Returns the cadence client sending the given cardinality, or the one of the client, if any.
+    fn cardinality_client(&self, cardinality: Option<Cardinality>) -> &StatsdClient {
+        cardinality
+            .or(self.cardinality)
+            .and_then(|cardinality| self.cardinality_clients.get(&cardinality))
+            .unwrap_or(&self.client)
+    }
+
+    /// The sink of self, appending the origin of self and the given cardinality to datagrams.
+    fn fields_sink(&self, cardinality: Option<Cardinality>) -> FieldsSink {
+        FieldsSink {
+            inner: self.sink.clone(),
+            origin: self.origin.clone(),
+            cardinality,
+        }
+    }
+
+    fn rebuild_sink(&mut self) {
+        self.sink = match &self.endpoint_sinks[..] {
+            [sink] => sink.clone(),
+            sinks => SharedSink(Arc::new(TeeSink(sinks.to_vec()))),
+        };
+        self.rebuild_clients();
+    }
+
+    /// Rebuilds the cadence clients of self, after a change of their sink, namespace or tags.
+    fn rebuild_clients(&mut self) {
+        self.client = self.statsd_client(self.fields_sink(None));
+        self.cardinality_clients = Cardinality::ALL
+            .into_iter()
+            .map(|cardinality| {
+                let client = self.statsd_client(self.fields_sink(Some(cardinality)));
+                (cardinality, client)
+            })
+            .collect();
+    }
+
     /// Set the destination for dogstatsd metrics, if an API Key is provided the client is disabled
     /// as dogstatsd is not allowed in agentless mode. Returns an error if the provided endpoint
     /// is invalid.
     pub fn set_endpoint(&mut self, endpoint: Endpoint) -> anyhow::Result<()> {
-        self.client = match endpoint.api_key {
+        self.endpoint_sinks[0] = match endpoint.api_key {
             Some(_) => {
                 info!("DogStatsD is not available in agentless mode");
                 anyhow::bail!("DogStatsD is not available in agentless mode");
             }
             None => {
                 debug!("Updating DogStatsD endpoint to {}", endpoint.url);
-                create_client(&endpoint)?
+                create_sink(&endpoint, self.buffered_async)?
             }
         };
+        self.endpoints[0] = endpoint;
+        self.rebuild_sink();
+        Ok(())
+    }
+
+    /// Blocks until all the metrics sent so far have been handed to the endpoints, e.g. before the
+    /// process exits. Waiting is bounded: an error is returned if the queue is not drained in time
+    /// or flushing one of the endpoints failed.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        self.sink.flush()?;
+        Ok(())
+    }
+
+    /// Send an already formatted dogstatsd datagram, e.g. `custom.metric:1|c|#a:b`, as is.
+    ///
+    /// The line bypasses all metric builders: no namespace prefix is applied and no validation of
+    /// the dogstatsd format is performed, the caller is responsible for correct formatting.
+    /// Returns an error if the line contains a NUL byte or could not be handed to the sink.
+    pub fn send_raw(&self, line: &str) -> anyhow::Result<()> {
+        if line.contains('\0') {
+            anyhow::bail!("raw dogstatsd line must not contain NUL bytes");
+        }
+        self.sink.emit(line)?;
         Ok(())
     }
 
     /// Send a vector of DogStatsDActionOwned, this is the same as `send` except it uses the "owned"
     /// version of DogStatsDAction. See the docs for DogStatsDActionOwned for details.
     pub fn send_owned(&self, actions: Vec<DogStatsDActionOwned>) {
-        let client = &self.client;
+        self.send_owned_with_cardinality(actions, None)
+    }
 
+    /// Same as `send_owned`, sending the given cardinality instead of the one of the client, if
+    /// any.
+    pub fn send_owned_with_cardinality(
+        &self,
+        actions: Vec<DogStatsDActionOwned>,
+        cardinality: Option<Cardinality>,
+    ) {
         for action in actions {
-            if let Err(err) = match action {
-                DogStatsDActionOwned::Count(metric, value, tags) => {
-                    do_send(client.count_with_tags(metric.as_ref(), value), &tags)
-                }
-                DogStatsDActionOwned::Distribution(metric, value, tags) => {
-                    do_send(client.distribution_with_tags(metric.as_ref(), value), &tags)
-                }
-                DogStatsDActionOwned::Gauge(metric, value, tags) => {
-                    do_send(client.gauge_with_tags(metric.as_ref(), value), &tags)
-                }
-                DogStatsDActionOwned::Histogram(metric, value, tags) => {
-                    do_send(client.histogram_with_tags(metric.as_ref(), value), &tags)
-                }
-                DogStatsDActionOwned::Set(metric, value, tags) => {
-                    do_send(client.set_with_tags(metric.as_ref(), value), &tags)
-                }
-            } {
+            if let Err(err) = self.send_owned_action(&action, &[], cardinality) {
                 error!("Error while sending metric: {}", err);
             }
         }
     }
 
+    fn send_owned_action(
+        &self,
+        action: &DogStatsDActionOwned,
+        constant_tags: &[Tag],
+        cardinality: Option<Cardinality>,
+    ) -> anyhow::Result<()> {
+        let client = self.cardinality_client(cardinality);
+        match action {
+            DogStatsDActionOwned::Count(metric, value, tags) => {
+                let tags = self.prepare_tags(metric, constant_tags.iter().chain(tags));
+                do_send(client.count_with_tags(metric.as_ref(), *value), &tags)
+            }
+            DogStatsDActionOwned::Distribution(metric, value, tags) => {
+                let tags = self.prepare_tags(metric, constant_tags.iter().chain(tags));
+                do_send(
+                    client.distribution_with_tags(metric.as_ref(), *value),
+                    &tags,
+                )
+            }
+            DogStatsDActionOwned::Gauge(metric, value, tags) => {
+                let tags = self.prepare_tags(metric, constant_tags.iter().chain(tags));
+                do_send(client.gauge_with_tags(metric.as_ref(), *value), &tags)
+            }
+            DogStatsDActionOwned::Histogram(metric, value, tags) => {
+                let tags = self.prepare_tags(metric, constant_tags.iter().chain(tags));
+                do_send(client.histogram_with_tags(metric.as_ref(), *value), &tags)
+            }
+            DogStatsDActionOwned::Set(metric, value, tags) => {
+                let tags = self.prepare_tags(metric, constant_tags.iter().chain(tags));
+                do_send(client.set_with_tags(metric.as_ref(), *value), &tags)
+            }
+            DogStatsDActionOwned::SetString(metric, value, tags) => {
+                self.send_set_string(metric, value, constant_tags.iter().chain(tags), cardinality)
+            }
+            DogStatsDActionOwned::SampledDistribution(metric, value, rate, tags) => self
+                .send_sampled(
+                    metric,
+                    *value,
+                    MetricKind::Distribution,
+                    *rate,
+                    constant_tags.iter().chain(tags),
+                    cardinality,
+                ),
+            DogStatsDActionOwned::SampledHistogram(metric, value, rate, tags) => self.send_sampled(
+                metric,
+                *value,
+                MetricKind::Histogram,
+                *rate,
+                constant_tags.iter().chain(tags),
+                cardinality,
+            ),
+        }
+    }
+
     /// Send a vector of DogStatsDAction, this is the same as `send_owned` except it only borrows
     /// the provided values.See the docs for DogStatsDActionOwned for details.
     pub fn send<'a, T: AsRef<str>, V: IntoIterator<Item = &'a Tag>>(
         &self,
         actions: Vec<DogStatsDAction<'a, T, V>>,
     ) {
-        let client = &self.client;
+        self.send_with_cardinality(actions, None)
+    }
+
+    /// Same as `send`, sending the given cardinality instead of the one of the client, if any.
+    pub fn send_with_cardinality<'a, T: AsRef<str>, V: IntoIterator<Item = &'a Tag>>(
+        &self,
+        actions: Vec<DogStatsDAction<'a, T, V>>,
+        cardinality: Option<Cardinality>,
+    ) {
+        let client = self.cardinality_client(cardinality);
 
         for action in actions {
             if let Err(err) = match action {
                 DogStatsDAction::Count(metric, value, tags) => {
+                    let tags = self.prepare_tags(metric.as_ref(), tags);
                     let metric_builder = client.count_with_tags(metric.as_ref(), value);
-                    do_send(metric_builder, tags)
+                    do_send(metric_builder, &tags)
                 }
                 DogStatsDAction::Distribution(metric, value, tags) => {
-                    do_send(client.distribution_with_tags(metric.as_ref(), value), tags)
+                    let tags = self.prepare_tags(metric.as_ref(), tags);
+                    do_send(client.distribution_with_tags(metric.as_ref(), value), &tags)
                 }
                 DogStatsDAction::Gauge(metric, value, tags) => {
-                    do_send(client.gauge_with_tags(metric.as_ref(), value), tags)
+                    let tags = self.prepare_tags(metric.as_ref(), tags);
+                    do_send(client.gauge_with_tags(metric.as_ref(), value), &tags)
                 }
                 DogStatsDAction::Histogram(metric, value, tags) => {
-                    do_send(client.histogram_with_tags(metric.as_ref(), value), tags)
+                    let tags = self.prepare_tags(metric.as_ref(), tags);
+                    do_send(client.histogram_with_tags(metric.as_ref(), value), &tags)
                 }
                 DogStatsDAction::Set(metric, value, tags) => {
-                    do_send(client.set_with_tags(metric.as_ref(), value), tags)
+                    let tags = self.prepare_tags(metric.as_ref(), tags);
+                    do_send(client.set_with_tags(metric.as_ref(), value), &tags)
                 }
+                DogStatsDAction::SetString(metric, value, tags) => {
+                    self.send_set_string(metric.as_ref(), value.as_ref(), tags, cardinality)
+                }
+                DogStatsDAction::SampledDistribution(metric, value, rate, tags) => self
+                    .send_sampled(
+                        metric.as_ref(),
+                        value,
+                        MetricKind::Distribution,
+                        rate,
+                        tags,
+                        cardinality,
+                    ),
+                DogStatsDAction::SampledHistogram(metric, value, rate, tags) => self.send_sampled(
+                    metric.as_ref(),
+                    value,
+                    MetricKind::Histogram,
+                    rate,
+                    tags,
+                    cardinality,
+                ),
             } {
                 error!("Error while sending metric: {}", err);
             }
@@ -160,85 +910,358 @@ impl Client {
     }
 }
 
-fn do_send<'m, 't, T, V: IntoIterator<Item = &'t Tag>>(
-    mut builder: MetricBuilder<'m, '_, T>,
-    tags: V,
-) -> anyhow::Result<()>
-where
-    T: Metric + From<String>,
-    't: 'm,
-{
-    let mut tags_iter = tags.into_iter();
-    let mut tag_opt = tags_iter.next();
-    while tag_opt.is_some() {
-        builder = builder.with_tag_value(tag_opt.unwrap().as_ref());
-        tag_opt = tags_iter.next();
+impl Client {
+    /// Send a summary of a distribution computed elsewhere, e.g. by another subsystem which already
+    /// aggregated the samples. See [`DistributionSummary::samples`] for how the distribution is
+    /// approximated. Returns an error if the summary is invalid or could not be handed to the sink.
+    pub fn send_distribution_summary<'t, V: IntoIterator<Item = &'t Tag>>(
+        &self,
+        metric: &str,
+        summary: &DistributionSummary,
+        tags: V,
+    ) -> anyhow::Result<()> {
+        let samples = summary.samples()?;
+        let values = samples
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(":");
+        let kind = if samples.len() as u64 == summary.count {
+            "d".to_string()
+        } else {
+            // Scale the representative samples back up to the summarized count
+            format!("d|@{}", samples.len() as f64 / summary.count as f64)
+        };
+        self.emit_line(metric, &values, &kind, tags, None)
     }
-    builder.try_send()?;
-    Ok(())
-}
 
-fn create_client(endpoint: &Endpoint) -> anyhow::Result<StatsdClient> {
-    match endpoint.url.scheme_str() {
-        #[cfg(unix)]
-        Some("unix") => {
-            let socket = UnixDatagram::unbound()
-                .map_err(|e| anyhow!("failed to make unbound unix port: {}", e))?;
-            socket
-                .set_nonblocking(true)
-                .map_err(|e| anyhow!("failed to set socket to nonblocking: {}", e))?;
-            let sink = QueuingMetricSink::with_capacity(
-                UnixMetricSink::from(
-                    socket_path_from_uri(&endpoint.url)
-                        .map_err(|e| anyhow!("failed to build socket path from uri: {}", e))?,
-                    socket,
-                ),
-                QUEUE_SIZE,
-            );
+    fn send_set_string<'t, V: IntoIterator<Item = &'t Tag>>(
+        &self,
+        metric: &str,
+        value: &str,
+        tags: V,
+        cardinality: Option<Cardinality>,
+    ) -> anyhow::Result<()> {
+        if let Some(c) = value.chars().find(|c| matches!(c, '|' | '\n' | '\0')) {
+            anyhow::bail!("invalid character {c:?} in set value {value:?}");
+        }
+        self.emit_line(metric, value, "s", tags, cardinality)
+    }
+
+    fn send_sampled<'t, V: IntoIterator<Item = &'t Tag>>(
+        &self,
+        metric: &str,
+        value: f64,
+        kind: MetricKind,
+        rate: f64,
+        tags: V,
+        cardinality: Option<Cardinality>,
+    ) -> anyhow::Result<()> {
+        let kind = if rate > 0.0 && rate < 1.0 {
+            format!("{}|@{rate}", kind.wire_suffix())
+        } else {
+            kind.wire_suffix().to_string()
+        };
+        self.emit_line(metric, &value.to_string(), &kind, tags, cardinality)
+    }
 
-            Ok(StatsdClient::from_sink("", sink))
+    /// Formats and emits a datagram for metric types or syntaxes cadence does not support.
+    fn emit_line<'t, V: IntoIterator<Item = &'t Tag>>(
+        &self,
+        metric: &str,
+        value: &str,
+        kind: &str,
+        tags: V,
+        cardinality: Option<Cardinality>,
+    ) -> anyhow::Result<()> {
+        let mut line = String::new();
+        if !self.namespace_prefix.is_empty() {
+            line.push_str(&self.namespace_prefix);
+            line.push('.');
         }
-        _ => {
-            let host = endpoint.url.host().ok_or(anyhow!("invalid host"))?;
-            let port = endpoint.url.port().ok_or(anyhow!("invalid port"))?.as_u16();
+        line.push_str(metric);
+        line.push(':');
+        line.push_str(value);
+        line.push('|');
+        line.push_str(kind);
+        let tags = self.prepare_tags(metric, tags);
+        for (i, tag) in self
+            .client_tags()
+            .map(|tag| -> &str { tag.as_ref() })
+            .chain(tags.iter().map(|tag| tag.as_ref()))
+            .enumerate()
+        {
+            line.push_str(if i == 0 { "|#" } else { "," });
+            line.push_str(tag);
+        }
+        push_fields(
+            &mut line,
+            self.origin.as_deref(),
+            cardinality.or(self.cardinality),
+        );
+        self.sink.emit(&line)?;
+        Ok(())
+    }
+}
 
-            let server_address = (host, port)
-                .to_socket_addrs()?
-                .next()
-                .ok_or(anyhow!("invalid address"))?;
+/// The maximum number of samples emitted for a [`DistributionSummary`].
+const MAX_SUMMARY_SAMPLES: u64 = 64;
 
-            let socket = if server_address.is_ipv4() {
-                UdpSocket::bind("0.0.0.0:0")
-                    .map_err(|e| anyhow!("failed to bind to 0.0.0.0:0: {}", e))?
-            } else {
-                UdpSocket::bind("[::]:0").map_err(|e| anyhow!("failed to bind to [::]:0: {}", e))?
-            };
-            socket.set_nonblocking(true)?;
-
-            let sink = QueuingMetricSink::with_capacity(
-                UdpMetricSink::from((host, port), socket)
-                    .map_err(|e| anyhow!("failed to build UdpMetricSink: {}", e))?,
-                QUEUE_SIZE,
-            );
+/// A pre-aggregated distribution, to be emitted with [`Client::send_distribution_summary`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DistributionSummary {
+    /// The smallest value of the distribution
+    pub min: f64,
+    /// The largest value of the distribution
+    pub max: f64,
+    /// The number of values of the distribution
+    pub count: u64,
+    /// The sum of the values of the distribution
+    pub sum: f64,
+}
 
-            Ok(StatsdClient::from_sink("", sink))
+impl DistributionSummary {
+    /// Returns the representative samples emitted for this summary.
+    ///
+    /// dogstatsd has no way of sending a summary, so the distribution is approximated: `min` and
+    /// `max` are emitted as is and all the other samples take the mean of the remaining values,
+    /// clamped between `min` and `max`. Min, max, count and sum are thus preserved (unless the sum
+    /// is inconsistent with the bounds), but percentiles are not. At most 64 samples are returned,
+    /// they are sent with a sample rate accounting for the remaining ones: min and max then count
+    /// for several values, the other samples are adjusted to keep the sum.
+    /// Returns an error if `count` is 0 or `min` is not lower than or equal to `max`.
+    pub fn samples(&self) -> anyhow::Result<Vec<f64>> {
+        if self.count == 0 {
+            anyhow::bail!("a distribution summary must have a positive count");
+        }
+        if self.min.is_nan() || self.max.is_nan() || self.min > self.max {
+            anyhow::bail!(
+                "invalid distribution summary bounds: min {} is greater than max {}",
+                self.min,
+                self.max
+            );
+        }
+        if self.count == 1 {
+            return Ok(vec![self.min]);
         }
+        let emitted = self.count.min(MAX_SUMMARY_SAMPLES) as usize;
+        // Once scaled back up by the sample rate, the samples should add up to the sum
+        let scale = emitted as f64 / self.count as f64;
+        let middle = (self.sum * scale - self.min - self.max) / (emitted.max(3) - 2) as f64;
+        let mut samples = Vec::with_capacity(emitted);
+        samples.push(self.min);
+        samples.resize(emitted - 1, middle.clamp(self.min, self.max));
+        samples.push(self.max);
+        Ok(samples)
     }
 }
 
-#[cfg(test)]
-mod test {
-    use crate::DogStatsDAction::{Count, Distribution, Gauge, Histogram, Set};
-    use crate::{create_client, new_flusher, DogStatsDActionOwned};
-    #[cfg(unix)]
-    use ddcommon::connector::uds::socket_path_to_uri;
-    use ddcommon::{tag, Endpoint};
-    #[cfg(unix)]
-    use http::Uri;
-    use std::net;
-    use std::time::Duration;
+/// Returns the datagram the client would emit for the given action, followed by the given constant
+/// tags, e.g. to debug metric formatting without capturing traffic. No namespace prefix is applied.
+/// Returns an error if the action cannot be formatted, e.g. a set value with reserved characters.
+pub fn format_action(
+    action: &DogStatsDActionOwned,
+    constant_tags: &[Tag],
+) -> anyhow::Result<String> {
+    let capture = Arc::new(CaptureSink::default());
+    let client = Client::from_sink(SharedSink(capture.clone()), None, false);
+    client.send_owned_action(action, constant_tags, None)?;
 
-    #[test]
+    let mut lines = capture.0.lock().unwrap();
+    lines
+        .pop()
+        .ok_or_else(|| anyhow!("no datagram was emitted for {action:?}"))
+}
+
+/// The errors returned when creating a [`Client`] or sending a metric with [`send_one`].
+#[derive(Debug)]
+pub enum DogStatsDError {
+    /// The endpoint does not designate a dogstatsd server, e.g. it has no host or port.
+    InvalidEndpoint(String),
+    /// The socket to send metrics from could not be set up, or the address of the endpoint could
+    /// not be resolved.
+    Socket(io::Error),
+    /// The metric is malformed, e.g. a set value with reserved characters.
+    InvalidMetric(String),
+    /// The metric could not be handed to the socket.
+    Send(io::Error),
+}
+
+impl std::fmt::Display for DogStatsDError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidEndpoint(reason) => f.write_str(reason),
+            Self::Socket(e) => write!(f, "failed to set up the socket: {e}"),
+            Self::InvalidMetric(reason) => write!(f, "invalid metric: {reason}"),
+            Self::Send(e) => write!(f, "failed to send the metric: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DogStatsDError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Socket(e) | Self::Send(e) => Some(e),
+            Self::InvalidEndpoint(_) | Self::InvalidMetric(_) => None,
+        }
+    }
+}
+
+/// Sends a single metric to the given endpoint and returns once the datagram was handed to the
+/// socket. Unlike a [`Client`], this spawns no background thread, nothing outlives the call: this
+/// is meant for short-lived processes emitting a metric before exiting.
+/// Returns an error if the endpoint is invalid or the metric could not be sent.
+pub fn send_one(endpoint: &Endpoint, action: DogStatsDActionOwned) -> Result<(), DogStatsDError> {
+    let sink = create_sink(endpoint, false)?;
+    let client = Client::from_sink(sink, Some(endpoint.clone()), false);
+    client
+        .send_owned_action(&action, &[], None)
+        .map_err(|e| match e.downcast::<io::Error>() {
+            Ok(e) => DogStatsDError::Send(e),
+            Err(e) => match e.downcast::<MetricError>() {
+                Ok(e) => DogStatsDError::Send(io::Error::other(e)),
+                Err(e) => DogStatsDError::InvalidMetric(e.to_string()),
+            },
+        })
+}
+
+/// Returns the tag with its value truncated to `max_length` bytes, ending with an indicator.
+fn cap_tag_value(tag: &str, max_length: usize) -> Cow<'_, str> {
+    let value_start = tag.find(':').map_or(0, |i| i + 1);
+    let value = &tag[value_start..];
+    if value.len() <= max_length {
+        return Cow::Borrowed(tag);
+    }
+    let (mut end, indicator) = match max_length.checked_sub(TRUNCATION_INDICATOR.len()) {
+        Some(end) => (end, TRUNCATION_INDICATOR),
+        None => (max_length, ""),
+    };
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    debug!(
+        "Truncating the value of tag {:?} from {} to {max_length} bytes",
+        &tag[..value_start],
+        value.len()
+    );
+    Cow::Owned(format!(
+        "{}{}{indicator}",
+        &tag[..value_start],
+        &value[..end]
+    ))
+}
+
+fn do_send<'m, T>(
+    mut builder: MetricBuilder<'m, '_, T>,
+    tags: &'m [Cow<'_, str>],
+) -> anyhow::Result<()>
+where
+    T: Metric + From<String>,
+{
+    for tag in tags {
+        builder = builder.with_tag_value(tag.as_ref());
+    }
+    builder.try_send()?;
+    Ok(())
+}
+
+fn shared_sink<T>(sink: T, buffered_async: bool) -> SharedSink
+where
+    T: MetricSink + Send + Sync + RefUnwindSafe + 'static,
+{
+    if buffered_async {
+        SharedSink(Arc::new(DrainableQueuingSink::new(sink)))
+    } else {
+        SharedSink(Arc::new(sink))
+    }
+}
+
+fn create_sink(endpoint: &Endpoint, buffered_async: bool) -> Result<SharedSink, DogStatsDError> {
+    let invalid_endpoint = |reason: &str| DogStatsDError::InvalidEndpoint(reason.to_string());
+    match endpoint.url.scheme_str() {
+        #[cfg(unix)]
+        Some("unix") => {
+            let socket = UnixDatagram::unbound().map_err(DogStatsDError::Socket)?;
+            socket
+                .set_nonblocking(true)
+                .map_err(DogStatsDError::Socket)?;
+            let sink = UnixMetricSink::from(
+                socket_path_from_uri(&endpoint.url).map_err(|e| {
+                    invalid_endpoint(&format!("failed to build socket path from uri: {e}"))
+                })?,
+                socket,
+            );
+
+            Ok(shared_sink(sink, buffered_async))
+        }
+        _ => {
+            let host = endpoint
+                .url
+                .host()
+                .ok_or_else(|| invalid_endpoint("invalid host"))?;
+            let port = endpoint
+                .url
+                .port()
+                .ok_or_else(|| invalid_endpoint("invalid port"))?
+                .as_u16();
+
+            let server_address = (host, port)
+                .to_socket_addrs()
+                .map_err(DogStatsDError::Socket)?
+                .next()
+                .ok_or_else(|| invalid_endpoint("invalid address"))?;
+
+            let socket = if server_address.is_ipv4() {
+                UdpSocket::bind("0.0.0.0:0")
+            } else {
+                UdpSocket::bind("[::]:0")
+            }
+            .map_err(DogStatsDError::Socket)?;
+            socket
+                .set_nonblocking(true)
+                .map_err(DogStatsDError::Socket)?;
+
+            let sink = UdpMetricSink::from((host, port), socket)
+                .map_err(|e| DogStatsDError::Socket(io::Error::other(e)))?;
+
+            Ok(shared_sink(sink, buffered_async))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::DogStatsDAction::{
+        Count, Distribution, Gauge, Histogram, SampledDistribution, SampledHistogram, Set,
+        SetString,
+    };
+    use crate::{
+        all_metric_kinds, create_sink, format_action, new_flusher, send_one, Cardinality,
+        DistributionSummary, DogStatsDActionOwned, DogStatsDError, MetricKind,
+    };
+    #[cfg(unix)]
+    use ddcommon::connector::uds::socket_path_to_uri;
+    use ddcommon::tag::Tag;
+    use ddcommon::{tag, Endpoint};
+    #[cfg(unix)]
+    use http::Uri;
+    use std::net;
+    use std::time::Duration;
+
+    fn read_large(socket: &net::UdpSocket) -> String {
+        let mut buf = [0; 8192];
+        let len = socket.recv(&mut buf).expect("No data");
+        String::from_utf8_lossy(&buf[..len]).to_string()
+    }
+
+    fn read(socket: &net::UdpSocket) -> String {
+        let mut buf = [0; 100];
+        socket.recv(&mut buf).expect("No data");
+        let datagram = String::from_utf8_lossy(buf.strip_suffix(&[0]).unwrap());
+        datagram.trim_matches(char::from(0)).to_string()
+    }
+
+    #[test]
     #[cfg_attr(miri, ignore)]
     fn test_flusher() {
         let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
@@ -256,15 +1279,10 @@ mod test {
             Histogram("test_histogram", 8.0, &vec![]),
             Set("test_set", 9, &vec![tag!("the", "end")]),
             Set("test_neg_set", -1, &vec![]),
+            SampledDistribution("test_distribution", 4.2, 0.25, &vec![tag!("foo", "bar")]),
+            SampledHistogram("test_histogram", 8.0, 1.0, &vec![]),
         ]);
 
-        fn read(socket: &net::UdpSocket) -> String {
-            let mut buf = [0; 100];
-            socket.recv(&mut buf).expect("No data");
-            let datagram = String::from_utf8_lossy(buf.strip_suffix(&[0]).unwrap());
-            datagram.trim_matches(char::from(0)).to_string()
-        }
-
         assert_eq!("test_count:3|c|#foo:bar", read(&socket));
         assert_eq!("test_neg_count:-2|c", read(&socket));
         assert_eq!("test_distribution:4.2|d", read(&socket));
@@ -272,73 +1290,714 @@ mod test {
         assert_eq!("test_histogram:8|h", read(&socket));
         assert_eq!("test_set:9|s|#the:end", read(&socket));
         assert_eq!("test_neg_set:-1|s", read(&socket));
+        assert_eq!("test_distribution:4.2|d|@0.25|#foo:bar", read(&socket));
+        assert_eq!("test_histogram:8|h", read(&socket));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_cardinality() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_buffered_async(false)
+        .unwrap();
+        flusher.send(vec![Count("test_count", 1, &vec![tag!("foo", "bar")])]);
+        assert_eq!("test_count:1|c|#foo:bar", read(&socket));
+
+        let flusher = flusher.with_cardinality(Some(Cardinality::Low));
+        flusher.send(vec![
+            Count("test_count", 2, &vec![tag!("foo", "bar")]),
+            SetString("test_set", "user", &vec![]),
+        ]);
+        assert_eq!("test_count:2|c|#foo:bar|card:low", read(&socket));
+        assert_eq!("test_set:user|s|card:low", read(&socket));
+
+        flusher.send_with_cardinality(
+            vec![Gauge("test_gauge", 3.0, &vec![])],
+            Some(Cardinality::Orchestrator),
+        );
+        assert_eq!("test_gauge:3|g|card:orchestrator", read(&socket));
+        flusher.send_owned_with_cardinality(
+            vec![DogStatsDActionOwned::Count("test_count".into(), 4, vec![])],
+            Some(Cardinality::High),
+        );
+        assert_eq!("test_count:4|c|card:high", read(&socket));
+
+        assert_eq!("None".parse::<Cardinality>().unwrap(), Cardinality::None);
+        assert_eq!(
+            "orchestrator".parse::<Cardinality>().unwrap(),
+            Cardinality::Orchestrator
+        );
+        assert!("medium".parse::<Cardinality>().is_err());
+        assert!("".parse::<Cardinality>().is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_hostname() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_buffered_async(false)
+        .unwrap();
+        flusher.send(vec![Count("test_count", 1, &vec![tag!("foo", "bar")])]);
+        assert_eq!("test_count:1|c|#foo:bar", read(&socket));
+
+        let flusher = flusher
+            .with_hostname(Some("my-host".to_string()))
+            .unwrap()
+            .with_namespace_prefix("ns")
+            .unwrap();
+        flusher.send(vec![
+            Count("test_count", 2, &vec![tag!("foo", "bar")]),
+            Gauge("test_gauge", 3.0, &vec![]),
+            SetString("test_set", "user", &vec![tag!("a", "b")]),
+        ]);
+        assert_eq!("ns.test_count:2|c|#host:my-host,foo:bar", read(&socket));
+        assert_eq!("ns.test_gauge:3|g|#host:my-host", read(&socket));
+        assert_eq!("ns.test_set:user|s|#host:my-host,a:b", read(&socket));
+
+        let flusher = flusher.with_hostname(None).unwrap();
+        flusher.send(vec![Gauge("test_gauge", 4.0, &vec![])]);
+        assert_eq!("ns.test_gauge:4|g", read(&socket));
+
+        assert!(flusher.with_hostname(Some(String::new())).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_origin_detection() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_buffered_async(false)
+        .unwrap()
+        .with_origin(Some("in-12345".to_string()));
+        flusher.send(vec![Count("test_count", 1, &vec![tag!("foo", "bar")])]);
+        assert_eq!("test_count:1|c|#foo:bar|c:in-12345", read(&socket));
+
+        // The origin precedes the cardinality
+        flusher.send_with_cardinality(
+            vec![SetString("test_set", "user", &vec![])],
+            Some(Cardinality::Low),
+        );
+        assert_eq!("test_set:user|s|c:in-12345|card:low", read(&socket));
+
+        let flusher = flusher.with_origin_detection(false);
+        flusher.send(vec![Gauge("test_gauge", 2.0, &vec![])]);
+        assert_eq!("test_gauge:2|g", read(&socket));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_tag_normalization() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_buffered_async(false)
+        .unwrap();
+        let tags = vec![Tag::new("My Key", "Value").unwrap(), tag!("foo", "bar")];
+        flusher.send(vec![Count("test_count", 1, &tags)]);
+        assert_eq!("test_count:1|c|#My Key:Value,foo:bar", read(&socket));
+
+        let flusher = flusher.with_tag_normalization(true);
+        flusher.send(vec![
+            Count("test_count", 2, &tags),
+            SetString("test_set", "user", &tags),
+        ]);
+        assert_eq!("test_count:2|c|#my_key:Value,foo:bar", read(&socket));
+        assert_eq!("test_set:user|s|#my_key:Value,foo:bar", read(&socket));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_max_tag_value_length() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_buffered_async(false)
+        .unwrap();
+        // Tag values are not truncated by default
+        let huge_tag = Tag::new("huge", "x".repeat(5000)).unwrap();
+        flusher.send(vec![Count(
+            "test_count",
+            1,
+            &vec![tag!("foo", "bar"), huge_tag.clone()],
+        )]);
+        assert_eq!(
+            format!("test_count:1|c|#foo:bar,huge:{}", "x".repeat(5000)),
+            read_large(&socket)
+        );
+
+        let flusher = flusher.with_max_tag_value_length(Some(8));
+        flusher.send(vec![
+            Count("test_count", 2, &vec![huge_tag.clone(), tag!("foo", "bar")]),
+            SetString("test_set", "user", &vec![huge_tag.clone()]),
+        ]);
+        flusher.send_owned(vec![DogStatsDActionOwned::Gauge(
+            "test_gauge".into(),
+            3.0,
+            vec![Tag::new("unicode", "ééééé").unwrap()],
+        )]);
+        assert_eq!("test_count:2|c|#huge:xxxxx...,foo:bar", read(&socket));
+        assert_eq!("test_set:user|s|#huge:xxxxx...", read(&socket));
+        // Truncation happens on a char boundary
+        assert_eq!("test_gauge:3|g|#unicode:éé...", read(&socket));
+
+        let flusher = flusher.with_max_tag_value_length(None);
+        flusher.send(vec![Count("test_count", 4, &vec![huge_tag])]);
+        assert_eq!(
+            format!("test_count:4|c|#huge:{}", "x".repeat(5000)),
+            read_large(&socket)
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_max_tags() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_buffered_async(false)
+        .unwrap()
+        .with_max_tags(Some(3));
+        let tags: Vec<Tag> = (0..5)
+            .map(|i| Tag::new(format!("key{i}"), "value").unwrap())
+            .collect();
+        flusher.send(vec![Count("test_count", 1, &tags)]);
+        flusher.send_owned(vec![DogStatsDActionOwned::SetString(
+            "test_set".into(),
+            "user".into(),
+            tags.clone(),
+        )]);
+        assert_eq!(
+            "test_count:1|c|#key0:value,key1:value,key2:value",
+            read(&socket)
+        );
+        assert_eq!(
+            "test_set:user|s|#key0:value,key1:value,key2:value",
+            read(&socket)
+        );
+
+        let flusher = flusher.with_max_tags(None);
+        flusher.send(vec![Count("test_count", 2, &tags)]);
+        assert_eq!(
+            "test_count:2|c|#key0:value,key1:value,key2:value,key3:value,key4:value",
+            read(&socket)
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_tag_deduplication() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_buffered_async(false)
+        .unwrap()
+        .with_tag_deduplication(true);
+        let tags = vec![
+            tag!("service", "a"),
+            tag!("env", "prod"),
+            tag!("service", "b"),
+        ];
+        flusher.send(vec![Count("test_count", 1, &tags)]);
+        assert_eq!("test_count:1|c|#env:prod,service:b", read(&socket));
+
+        let flusher = flusher.with_tag_deduplication(false);
+        flusher.send(vec![Count("test_count", 2, &tags)]);
+        assert_eq!(
+            "test_count:2|c|#service:a,env:prod,service:b",
+            read(&socket)
+        );
+    }
+
+    #[test]
+    fn test_format_action() {
+        let cases = [
+            (
+                DogStatsDActionOwned::Count("test_count".into(), 3, vec![tag!("foo", "bar")]),
+                "test_count:3|c|#foo:bar",
+            ),
+            (
+                DogStatsDActionOwned::Count("test_neg_count".into(), -2, vec![]),
+                "test_neg_count:-2|c",
+            ),
+            (
+                DogStatsDActionOwned::Distribution("test_distribution".into(), 4.2, vec![]),
+                "test_distribution:4.2|d",
+            ),
+            (
+                DogStatsDActionOwned::Gauge("test_gauge".into(), 7.6, vec![]),
+                "test_gauge:7.6|g",
+            ),
+            (
+                DogStatsDActionOwned::Histogram("test_histogram".into(), 8.0, vec![]),
+                "test_histogram:8|h",
+            ),
+            (
+                DogStatsDActionOwned::Set("test_set".into(), 9, vec![tag!("the", "end")]),
+                "test_set:9|s|#the:end",
+            ),
+            (
+                DogStatsDActionOwned::SetString("users".into(), "user-42".into(), vec![]),
+                "users:user-42|s",
+            ),
+            (
+                DogStatsDActionOwned::SampledDistribution(
+                    "test_distribution".into(),
+                    4.2,
+                    0.25,
+                    vec![tag!("foo", "bar")],
+                ),
+                "test_distribution:4.2|d|@0.25|#foo:bar",
+            ),
+            (
+                DogStatsDActionOwned::SampledHistogram("test_histogram".into(), 8.0, 0.5, vec![]),
+                "test_histogram:8|h|@0.5",
+            ),
+            (
+                DogStatsDActionOwned::SampledDistribution("unsampled".into(), 1.5, 1.0, vec![]),
+                "unsampled:1.5|d",
+            ),
+            (
+                DogStatsDActionOwned::SampledHistogram("unsampled".into(), 1.5, f64::NAN, vec![]),
+                "unsampled:1.5|h",
+            ),
+        ];
+        for (action, expected) in &cases {
+            assert_eq!(format_action(action, &[]).unwrap(), *expected);
+        }
+
+        let constant_tags = [tag!("env", "prod")];
+        let action = DogStatsDActionOwned::Count("test_count".into(), 3, vec![tag!("foo", "bar")]);
+        assert_eq!(
+            format_action(&action, &constant_tags).unwrap(),
+            "test_count:3|c|#env:prod,foo:bar"
+        );
+        let action =
+            DogStatsDActionOwned::SetString("users".into(), "user-42".into(), vec![tag!("a", "b")]);
+        assert_eq!(
+            format_action(&action, &constant_tags).unwrap(),
+            "users:user-42|s|#env:prod,a:b"
+        );
+
+        let action = DogStatsDActionOwned::SetString("users".into(), "a|b".into(), vec![]);
+        assert!(format_action(&action, &[]).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_set_string() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_namespace_prefix("datadog.tracer")
+        .unwrap();
+        flusher.send(vec![
+            SetString(
+                "users",
+                "user-42",
+                &vec![tag!("foo", "bar"), tag!("the", "end")],
+            ),
+            SetString("users", "invalid|value", &vec![]),
+        ]);
+        flusher.send_owned(vec![DogStatsDActionOwned::SetString(
+            "requests".to_string(),
+            "2b5e1aa6".to_string(),
+            vec![],
+        )]);
+
+        assert_eq!(
+            "datadog.tracer.users:user-42|s|#foo:bar,the:end",
+            read(&socket)
+        );
+        assert_eq!("datadog.tracer.requests:2b5e1aa6|s", read(&socket));
+    }
+
+    #[test]
+    fn test_distribution_summary_samples() {
+        let summary = |min, max, count, sum| DistributionSummary {
+            min,
+            max,
+            count,
+            sum,
+        };
+
+        let samples = summary(1.0, 9.0, 5, 20.0).samples().unwrap();
+        assert_eq!(samples, vec![1.0, 10.0 / 3.0, 10.0 / 3.0, 10.0 / 3.0, 9.0]);
+        assert_eq!(summary(2.0, 2.0, 1, 2.0).samples().unwrap(), vec![2.0]);
+        assert_eq!(summary(1.0, 3.0, 2, 4.0).samples().unwrap(), vec![1.0, 3.0]);
+
+        // Large summaries are sampled, keeping the bounds and the scaled sum
+        let samples = summary(0.0, 100.0, 1000, 50_000.0).samples().unwrap();
+        assert_eq!(samples.len(), 64);
+        assert_eq!(samples.iter().copied().fold(f64::MAX, f64::min), 0.0);
+        assert_eq!(samples.iter().copied().fold(f64::MIN, f64::max), 100.0);
+        let scaled_sum = samples.iter().sum::<f64>() * 1000.0 / 64.0;
+        assert!((scaled_sum - 50_000.0).abs() < 1e-6);
+
+        assert!(summary(1.0, 2.0, 0, 0.0).samples().is_err());
+        assert!(summary(2.0, 1.0, 3, 4.0).samples().is_err());
+        assert!(summary(f64::NAN, 1.0, 3, 4.0).samples().is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_send_distribution_summary() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap();
+        let summary = |count, sum| DistributionSummary {
+            min: 1.0,
+            max: 3.0,
+            count,
+            sum,
+        };
+        flusher
+            .send_distribution_summary("latency", &summary(3, 6.0), &vec![tag!("foo", "bar")])
+            .unwrap();
+        flusher
+            .send_distribution_summary("latency", &summary(128, 256.0), &vec![])
+            .unwrap();
+        assert!(flusher
+            .send_distribution_summary("latency", &summary(0, 0.0), &vec![])
+            .is_err());
+
+        assert_eq!("latency:1:2:3|d|#foo:bar", read(&socket));
+        let mut buf = [0; 1024];
+        let len = socket.recv(&mut buf).expect("No data");
+        let datagram = std::str::from_utf8(&buf[..len]).unwrap();
+        let (values, rate) = datagram.split_once("|d|@").unwrap();
+        assert_eq!(rate, "0.5");
+        assert_eq!(
+            values.strip_prefix("latency:").unwrap().split(':').count(),
+            64
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_namespace_prefix() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let endpoint = Endpoint::from_slice(socket.local_addr().unwrap().to_string().as_str());
+        let flusher = new_flusher(endpoint.clone())
+            .unwrap()
+            .with_namespace_prefix("datadog.tracer")
+            .unwrap();
+        flusher.send(vec![Count("test_count", 3, &vec![tag!("foo", "bar")])]);
+        assert_eq!("datadog.tracer.test_count:3|c|#foo:bar", read(&socket));
+
+        // a trailing dot is accepted, the separator is not doubled
+        let flusher = new_flusher(endpoint)
+            .unwrap()
+            .with_namespace_prefix("datadog.tracer.")
+            .unwrap();
+        flusher.send(vec![Gauge("test_gauge", 7.6, &vec![])]);
+        assert_eq!("datadog.tracer.test_gauge:7.6|g", read(&socket));
+
+        for invalid in [
+            "data dog",
+            "datadog|tracer",
+            "datadog:tracer",
+            ".datadog",
+            "a..b",
+        ] {
+            assert!(new_flusher(Endpoint::from_slice("localhost:80"))
+                .unwrap()
+                .with_namespace_prefix(invalid)
+                .is_err());
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_additional_endpoint() {
+        let old_socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = old_socket.set_read_timeout(Some(Duration::from_millis(500)));
+        let new_socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = new_socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            old_socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_additional_endpoint(Endpoint::from_slice(
+            new_socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap();
+        flusher.send(vec![Count("test_count", 3, &vec![tag!("foo", "bar")])]);
+
+        assert_eq!("test_count:3|c|#foo:bar", read(&old_socket));
+        assert_eq!("test_count:3|c|#foo:bar", read(&new_socket));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_send_raw() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(500)));
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_namespace_prefix("ignored")
+        .unwrap();
+        flusher.send_raw("custom.metric:1|c|#a:b").unwrap();
+        assert_eq!("custom.metric:1|c|#a:b", read(&socket));
+
+        assert!(flusher.send_raw("custom.metric:1|c\0").is_err());
     }
 
     #[test]
     #[cfg_attr(miri, ignore)]
-    fn test_create_client_udp() {
-        let res = create_client(&Endpoint::default());
+    fn test_synchronous_send() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap()
+        .with_buffered_async(false)
+        .unwrap();
+        flusher.send(vec![Count("test_count", 3, &vec![tag!("foo", "bar")])]);
+
+        // The datagram was sent before send() returned, it's already waiting in the socket buffer
+        socket.set_nonblocking(true).unwrap();
+        assert_eq!("test_count:3|c|#foo:bar", read(&socket));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_send_one_udp() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+
+        send_one(
+            &Endpoint::from_slice(socket.local_addr().unwrap().to_string().as_str()),
+            DogStatsDActionOwned::Gauge("test_gauge".into(), 2.0, vec![tag!("foo", "bar")]),
+        )
+        .unwrap();
+
+        // Sent synchronously, without a queue drained by a background thread
+        socket.set_nonblocking(true).unwrap();
+        assert_eq!("test_gauge:2|g|#foo:bar", read(&socket));
+        assert!(socket.recv(&mut [0; 100]).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_send_one_errors() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+        let endpoint = Endpoint::from_slice(socket.local_addr().unwrap().to_string().as_str());
+
+        let res = send_one(
+            &Endpoint::default(),
+            DogStatsDActionOwned::Count("test_count".into(), 1, vec![]),
+        );
+        assert!(matches!(res, Err(DogStatsDError::InvalidEndpoint(_))));
+
+        let res = send_one(
+            &endpoint,
+            DogStatsDActionOwned::SetString("test_set".into(), "a|b".into(), vec![]),
+        );
+        assert!(matches!(res, Err(DogStatsDError::InvalidMetric(_))));
+
+        socket.set_nonblocking(true).unwrap();
+        assert!(socket.recv(&mut [0; 100]).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_send_one_unix_domain_socket() {
+        let path =
+            std::env::temp_dir().join(format!("dogstatsd-send-one-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let socket = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+
+        let result = send_one(
+            &Endpoint::from_url(socket_path_to_uri(&path).unwrap()),
+            DogStatsDActionOwned::Count("test_count".into(), 5, vec![]),
+        );
+        socket.set_nonblocking(true).unwrap();
+        let mut buf = [0; 100];
+        let received = socket.recv(&mut buf);
+        let _ = std::fs::remove_file(&path);
+
+        result.unwrap();
+        assert_eq!(
+            "test_count:5|c",
+            String::from_utf8_lossy(&buf[..received.unwrap()])
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_flush_drains_queue() {
+        let socket = net::UdpSocket::bind("127.0.0.1:0").expect("failed to bind host socket");
+
+        let flusher = new_flusher(Endpoint::from_slice(
+            socket.local_addr().unwrap().to_string().as_str(),
+        ))
+        .unwrap();
+        for i in 0..10 {
+            flusher.send(vec![Count("test_count", i, &vec![])]);
+        }
+        flusher.flush().unwrap();
+
+        // All the queued datagrams were sent before flush() returned
+        socket.set_nonblocking(true).unwrap();
+        for i in 0..10 {
+            assert_eq!(format!("test_count:{i}|c"), read(&socket));
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_create_sink_udp() {
+        let res = create_sink(&Endpoint::default(), true);
         assert!(res.is_err());
         assert_eq!("invalid host", res.unwrap_err().to_string().as_str());
 
-        let res = create_client(&Endpoint::from_slice("localhost:99999"));
+        let res = create_sink(&Endpoint::from_slice("localhost:99999"), true);
         assert!(res.is_err());
         assert_eq!("invalid port", res.unwrap_err().to_string().as_str());
 
-        let res = create_client(&Endpoint::from_slice("localhost:80"));
+        let res = create_sink(&Endpoint::from_slice("localhost:80"), true);
         assert!(res.is_ok());
 
-        let res = create_client(&Endpoint::from_slice("http://localhost:80"));
+        let res = create_sink(&Endpoint::from_slice("http://localhost:80"), true);
         assert!(res.is_ok());
     }
 
     #[test]
     #[cfg(unix)]
     #[cfg_attr(miri, ignore)]
-    fn test_create_client_unix_domain_socket() {
-        let res = create_client(&Endpoint::from_url(
-            "unix://localhost:80".parse::<Uri>().unwrap(),
-        ));
+    fn test_create_sink_unix_domain_socket() {
+        let res = create_sink(
+            &Endpoint::from_url("unix://localhost:80".parse::<Uri>().unwrap()),
+            true,
+        );
         assert!(res.is_err());
         assert_eq!(
             "failed to build socket path from uri: invalid url",
             res.unwrap_err().to_string().as_str()
         );
 
-        let res = create_client(&Endpoint::from_url(
-            socket_path_to_uri("/path/to/a/socket.sock".as_ref()).unwrap(),
-        ));
+        let res = create_sink(
+            &Endpoint::from_url(socket_path_to_uri("/path/to/a/socket.sock".as_ref()).unwrap()),
+            true,
+        );
         assert!(res.is_ok());
     }
 
+    fn actions_of_kind(
+        kind: MetricKind,
+    ) -> (
+        DogStatsDActionOwned,
+        crate::DogStatsDAction<'static, &'static str, Vec<&'static ddcommon::tag::Tag>>,
+    ) {
+        match kind {
+            MetricKind::Count => (
+                DogStatsDActionOwned::Count("test".to_string(), 1, vec![]),
+                Count("test", 1, vec![]),
+            ),
+            MetricKind::Distribution => (
+                DogStatsDActionOwned::Distribution("test".to_string(), 1.0, vec![]),
+                Distribution("test", 1.0, vec![]),
+            ),
+            MetricKind::Gauge => (
+                DogStatsDActionOwned::Gauge("test".to_string(), 1.0, vec![]),
+                Gauge("test", 1.0, vec![]),
+            ),
+            MetricKind::Histogram => (
+                DogStatsDActionOwned::Histogram("test".to_string(), 1.0, vec![]),
+                Histogram("test", 1.0, vec![]),
+            ),
+            MetricKind::Set => (
+                DogStatsDActionOwned::Set("test".to_string(), 1, vec![]),
+                Set("test", 1, vec![]),
+            ),
+            MetricKind::SetString => (
+                DogStatsDActionOwned::SetString("test".to_string(), "a".to_string(), vec![]),
+                SetString("test", "a", vec![]),
+            ),
+        }
+    }
+
     #[test]
     fn test_owned_sync() {
-        // This test ensures that if a new variant is added to either `DogStatsDActionOwned` or
-        // `DogStatsDAction` this test will NOT COMPILE to act as a reminder that BOTH locations
-        // must be updated.
-        let owned_act = DogStatsDActionOwned::Count("test".to_string(), 1, vec![]);
-        match owned_act {
-            DogStatsDActionOwned::Count(_, _, _) => {}
-            DogStatsDActionOwned::Distribution(_, _, _) => {}
-            DogStatsDActionOwned::Gauge(_, _, _) => {}
-            DogStatsDActionOwned::Histogram(_, _, _) => {}
-            DogStatsDActionOwned::Set(_, _, _) => {}
-        }
-
-        let act = Count("test".to_string(), 1, vec![]);
-        match act {
-            Count(_, _, _) => {}
-            Distribution(_, _, _) => {}
-            Gauge(_, _, _) => {}
-            Histogram(_, _, _) => {}
-            Set(_, _, _) => {}
-        }
-
-        // TODO: when std::mem::variant_count is in stable we can do this instead
-        // assert_eq!(
-        //     std::mem::variant_count::<DogStatsDActionOwned>(),
-        //     std::mem::variant_count::<DogStatsDAction<String, Vec<&Tag>>>(),
-        //     "DogStatsDActionOwned and DogStatsDAction should have the same number of variants,
-        // did you forget to update one?", );
+        // `DogStatsDActionOwned::kind` and `DogStatsDAction::kind` exhaustively match the actions,
+        // so a variant added to either enum will NOT COMPILE until it is given a `MetricKind`, and
+        // each kind must then map to both an owned and a borrowed action here.
+        for &kind in all_metric_kinds() {
+            let (owned_act, act) = actions_of_kind(kind);
+            assert_eq!(owned_act.kind(), kind);
+            assert_eq!(act.kind(), kind);
+        }
+
+        // Variants sharing a kind with another one
+        let tags: Vec<&Tag> = vec![];
+        assert_eq!(
+            DogStatsDActionOwned::SampledDistribution("test".to_string(), 1.0, 0.5, vec![]).kind(),
+            SampledDistribution("test", 1.0, 0.5, tags.clone()).kind()
+        );
+        assert_eq!(
+            DogStatsDActionOwned::SampledHistogram("test".to_string(), 1.0, 0.5, vec![]).kind(),
+            SampledHistogram("test", 1.0, 0.5, tags).kind()
+        );
+    }
+
+    #[test]
+    fn test_metric_kind_wire_suffix() {
+        for &kind in all_metric_kinds() {
+            let (action, _) = actions_of_kind(kind);
+            let datagram = format_action(&action, &[]).unwrap();
+            assert_eq!(
+                datagram.split('|').nth(1),
+                Some(kind.wire_suffix()),
+                "unexpected type in {datagram:?} for {kind:?}"
+            );
+        }
     }
 }
@@ -4,6 +4,8 @@
 use crate::handles::{HandlesTransport, TransferHandles};
 use crate::platform::{mmap_handle, munmap_handle, OwnedFileHandle, PlatformHandle};
 use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
 use std::{ffi::CString, io};
 #[cfg(feature = "tiny-bytes")]
 use tinybytes::UnderlyingBytes;
@@ -14,6 +16,16 @@ pub struct ShmHandle {
     pub(crate) size: usize,
 }
 
+impl ShmHandle {
+    /// Returns the capacity of the shared memory, as known from the handle, without mapping it.
+    pub fn size(&self) -> usize {
+        #[cfg(any(windows, target_os = "macos"))]
+        return self.size & !crate::platform::NOT_COMMITTED;
+        #[cfg(not(any(windows, target_os = "macos")))]
+        self.size
+    }
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct AnonHandle {
@@ -50,6 +62,64 @@ impl NamedShmHandle {
     }
 }
 
+/// Environment variable holding the namespace of the named resources (shared memory segments,
+/// sockets) shared by the sidecar and its clients. Isolated instances sharing a host, e.g. nested
+/// containers, should use distinct namespaces. All processes communicating with each other, i.e.
+/// the sidecar and its clients, must agree on it.
+pub const ENV_SHM_NAMESPACE: &str = "_DD_SHM_NAMESPACE";
+
+/// The namespace configured via [`ENV_SHM_NAMESPACE`], read once. Empty by default.
+pub fn shm_namespace() -> &'static str {
+    static NAMESPACE: OnceLock<String> = OnceLock::new();
+    NAMESPACE.get_or_init(|| std::env::var(ENV_SHM_NAMESPACE).unwrap_or_default())
+}
+
+/// Reason why a shared memory handle could not be mapped into memory.
+#[derive(Debug)]
+pub enum MapError {
+    /// The handle has a size of zero, there is nothing to map.
+    ZeroSize,
+    /// The underlying file handle is not usable, e.g. it was already moved to another process.
+    InvalidHandle(io::Error),
+    /// The mapping syscall itself failed, e.g. out of memory or insufficient permissions.
+    Mmap(io::Error),
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapError::ZeroSize => f.write_str("Size of handle used for mmap() is zero. When used for shared memory this may originate from race conditions between creation and truncation of the shared memory file."),
+            MapError::InvalidHandle(e) => write!(f, "invalid shm handle: {e}"),
+            MapError::Mmap(e) => {
+                #[cfg(unix)]
+                if let Some(errno) = e.raw_os_error() {
+                    return write!(f, "mmap failed: {:?} ({e})", nix::errno::Errno::from_i32(errno));
+                }
+                write!(f, "mmap failed: {e}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MapError::ZeroSize => None,
+            MapError::InvalidHandle(e) | MapError::Mmap(e) => Some(e),
+        }
+    }
+}
+
+impl From<MapError> for io::Error {
+    fn from(e: MapError) -> io::Error {
+        let kind = match &e {
+            MapError::ZeroSize => io::ErrorKind::Other,
+            MapError::InvalidHandle(e) | MapError::Mmap(e) => e.kind(),
+        };
+        io::Error::new(kind, e)
+    }
+}
+
 fn page_aligned_size(size: usize) -> usize {
     let page_size = page_size::get();
     // round up to nearest page
@@ -79,7 +149,10 @@ pub trait FileBackedHandle
 where
     Self: Sized,
 {
-    fn map(self) -> io::Result<MappedMem<Self>>;
+    /// Maps the shared memory into the address space of the current process. Clones of the
+    /// handle, or handles received from other processes, may be mapped at the same time and share
+    /// the memory.
+    fn map(self) -> Result<MappedMem<Self>, MapError>;
     fn get_shm(&self) -> &ShmHandle;
     fn get_shm_mut(&mut self) -> &mut ShmHandle;
     #[cfg(all(unix, not(target_os = "macos")))]
@@ -106,7 +179,7 @@ where
 }
 
 impl FileBackedHandle for ShmHandle {
-    fn map(self) -> io::Result<MappedMem<ShmHandle>> {
+    fn map(self) -> Result<MappedMem<ShmHandle>, MapError> {
         mmap_handle(self)
     }
 
@@ -119,7 +192,7 @@ impl FileBackedHandle for ShmHandle {
 }
 
 impl FileBackedHandle for NamedShmHandle {
-    fn map(self) -> io::Result<MappedMem<NamedShmHandle>> {
+    fn map(self) -> Result<MappedMem<NamedShmHandle>, MapError> {
         mmap_handle(self)
     }
 
@@ -143,6 +216,40 @@ impl<T: MemoryHandle> MappedMem<T> {
     pub fn get_size(&self) -> usize {
         self.mem.get_size()
     }
+
+    /// Returns `len` bytes starting at `offset`, or `None` if the range exceeds the mapping.
+    pub fn read_slice(&self, offset: usize, len: usize) -> Option<&[u8]> {
+        self.as_slice().get(offset..offset.checked_add(len)?)
+    }
+
+    /// Copies `data` into the mapping at `offset`. Returns false, writing nothing, if the range
+    /// exceeds the mapping.
+    pub fn write_slice(&mut self, offset: usize, data: &[u8]) -> bool {
+        let Some(end) = offset.checked_add(data.len()) else {
+            return false;
+        };
+        match self.as_slice_mut().get_mut(offset..end) {
+            Some(slice) => {
+                slice.copy_from_slice(data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reads a native-endian u32 at `offset`, or `None` if it exceeds the mapping.
+    /// The offset does not need to be aligned.
+    pub fn read_u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes = self.read_slice(offset, 4)?;
+        Some(u32::from_ne_bytes(bytes.try_into().ok()?))
+    }
+
+    /// Reads a native-endian u64 at `offset`, or `None` if it exceeds the mapping.
+    /// The offset does not need to be aligned.
+    pub fn read_u64_at(&self, offset: usize) -> Option<u64> {
+        let bytes = self.read_slice(offset, 8)?;
+        Some(u64::from_ne_bytes(bytes.try_into().ok()?))
+    }
 }
 
 impl<T: MemoryHandle> AsRef<[u8]> for MappedMem<T> {
@@ -214,6 +321,8 @@ impl UnderlyingBytes for MappedMem<ShmHandle> {}
 
 #[cfg(test)]
 mod tests {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    use crate::platform::MapError;
     use crate::platform::{FileBackedHandle, NamedShmHandle, ShmHandle};
     use std::ffi::CString;
     use std::io::Write;
@@ -231,6 +340,54 @@ mod tests {
         assert_eq!(mapped.as_slice(), exp.as_slice());
     }
 
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_shm_size_without_mapping() {
+        let shm = ShmHandle::new(12345).unwrap();
+        assert_eq!(shm.size(), 12345);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_bounds_checked_reads() {
+        let mut mapped = ShmHandle::new(16).unwrap().map().unwrap();
+        let size = mapped.get_size();
+        let slice = mapped.as_slice_mut();
+        slice[1..5].copy_from_slice(&0xdeadbeefu32.to_ne_bytes());
+        slice[size - 8..].copy_from_slice(&u64::MAX.to_ne_bytes());
+
+        assert_eq!(mapped.read_u32_at(1), Some(0xdeadbeef));
+        assert_eq!(mapped.read_u64_at(size - 8), Some(u64::MAX));
+        assert_eq!(
+            mapped.read_slice(1, 4),
+            Some(&0xdeadbeefu32.to_ne_bytes()[..])
+        );
+        assert_eq!(mapped.read_slice(size, 0), Some(&[][..]));
+
+        assert_eq!(mapped.read_u32_at(size - 3), None);
+        assert_eq!(mapped.read_u64_at(size - 7), None);
+        assert_eq!(mapped.read_u64_at(usize::MAX), None);
+        assert_eq!(mapped.read_slice(size, 1), None);
+        assert_eq!(mapped.read_slice(1, usize::MAX), None);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_write_and_read_across_mappings() {
+        let shm = ShmHandle::new(64).unwrap();
+        let mut writer = shm.clone().map().unwrap();
+        let size = writer.get_size();
+        assert!(writer.write_slice(3, b"hello"));
+        assert!(writer.write_slice(size - 2, b"!!"));
+        assert!(!writer.write_slice(size - 1, b"!!"));
+        assert!(!writer.write_slice(usize::MAX, b"!"));
+
+        let reader = shm.map().unwrap();
+        assert_eq!(reader.read_slice(3, 5), Some(&b"hello"[..]));
+        assert_eq!(reader.read_slice(size - 2, 2), Some(&b"!!"[..]));
+        assert_eq!(reader.read_slice(size - 1, 2), None);
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn test_named_shm() {
@@ -246,4 +403,17 @@ mod tests {
         _ = (&mut exp[..5]).write(&[1, 2, 3, 4, 5]).unwrap();
         assert_eq!(other.as_slice(), exp.as_slice());
     }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[cfg_attr(miri, ignore)]
+    fn test_map_error_is_descriptive() {
+        let mut shm = ShmHandle::new(5).unwrap();
+        // Way more than any address space can hold
+        unsafe { shm.set_mapping_size(1 << 62) }.unwrap();
+        let err = shm.map().err().unwrap();
+        assert!(matches!(err, MapError::Mmap(_)));
+        let message = err.to_string();
+        assert!(message.starts_with("mmap failed: E"), "{message}");
+    }
 }
@@ -50,6 +50,11 @@ impl Channel {
         sock.set_write_timeout(timeout)
     }
 
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        let sock = self.inner.as_socketlike_view()?;
+        sock.read_timeout()
+    }
+
     pub fn set_nonblocking(&mut self, nonblocking: bool) -> io::Result<()> {
         let sock = self.inner.as_socketlike_view()?;
         sock.set_nonblocking(nonblocking)
@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::platform::{
-    FileBackedHandle, MappedMem, MemoryHandle, NamedShmHandle, PlatformHandle, ShmHandle, ShmPath,
+    FileBackedHandle, MapError, MappedMem, MemoryHandle, NamedShmHandle, PlatformHandle, ShmHandle,
+    ShmPath,
 };
+use anyhow::Context;
 use io_lifetimes::OwnedFd;
 use libc::off_t;
 use nix::fcntl::OFlag;
@@ -17,8 +19,12 @@ use std::num::NonZeroUsize;
 use std::os::unix::fs::MetadataExt;
 use std::sync::atomic::{AtomicI32, Ordering};
 
-pub(crate) fn mmap_handle<T: FileBackedHandle>(handle: T) -> io::Result<MappedMem<T>> {
-    let fd = handle.get_shm().handle.as_owned_fd()?;
+pub(crate) fn mmap_handle<T: FileBackedHandle>(handle: T) -> Result<MappedMem<T>, MapError> {
+    let fd = handle
+        .get_shm()
+        .handle
+        .as_owned_fd()
+        .map_err(MapError::InvalidHandle)?;
     if let Some(size) = NonZeroUsize::new(handle.get_shm().size) {
         Ok(MappedMem {
             ptr: unsafe {
@@ -29,12 +35,13 @@ pub(crate) fn mmap_handle<T: FileBackedHandle>(handle: T) -> io::Result<MappedMe
                     MapFlags::MAP_SHARED,
                     Some(fd),
                     0,
-                )?
+                )
+                .map_err(|e| MapError::Mmap(e.into()))?
             },
             mem: handle,
         })
     } else {
-        Err(io::Error::other("Size of handle used for mmap() is zero. When used for shared memory this may originate from race conditions between creation and truncation of the shared memory file."))
+        Err(MapError::ZeroSize)
     }
 }
 
@@ -76,29 +83,40 @@ impl ShmHandle {
         Self::open_anon_shm_generic(name)
     }
 
+    /// Allocates anonymous shared memory of `size` bytes, to be mapped with
+    /// [`FileBackedHandle::map`] or sent to another process.
     pub fn new(size: usize) -> anyhow::Result<ShmHandle> {
         Self::new_named(size, "anon-handle")
     }
 
+    /// Like [`ShmHandle::new`], with `name` appearing in the name of the underlying file to ease
+    /// debugging. The memory is still anonymous, it cannot be opened by name.
     pub fn new_named(size: usize, name: &str) -> anyhow::Result<ShmHandle> {
         let fd = Self::open_anon_shm(name)?;
         let handle: PlatformHandle<OwnedFd> = fd.into();
-        ftruncate(handle.as_owned_fd()?, size as off_t)?;
+        ftruncate(handle.as_owned_fd()?, size as off_t)
+            .with_context(|| format!("failed to resize anonymous shm to {size} bytes"))?;
         Ok(ShmHandle { handle, size })
     }
 }
 
 impl NamedShmHandle {
+    /// Creates or opens the shared memory at `path`, e.g. `/my-shm`, resized to `size` bytes and
+    /// only accessible by the current user. It is unlinked once the handle, or the memory mapped
+    /// from it, is dropped.
     pub fn create(path: CString, size: usize) -> io::Result<NamedShmHandle> {
         Self::create_mode(path, size, Mode::S_IWUSR | Mode::S_IRUSR)
     }
 
+    /// Like [`NamedShmHandle::create`], with the given permissions.
     pub fn create_mode(path: CString, size: usize, mode: Mode) -> io::Result<NamedShmHandle> {
         let fd = shm_open(path.as_bytes(), OFlag::O_CREAT | OFlag::O_RDWR, mode)?;
         ftruncate(&fd, size as off_t)?;
         Self::new(fd, Some(path), size)
     }
 
+    /// Opens the existing shared memory at `path`, created by [`NamedShmHandle::create`]. It is
+    /// not unlinked when this handle is dropped.
     pub fn open(path: &CStr) -> io::Result<NamedShmHandle> {
         let file: File = shm_open(path, OFlag::O_RDWR, Mode::empty())?.into();
         let size = file.metadata()?.size() as usize;
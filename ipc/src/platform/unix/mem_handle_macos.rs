@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::platform::{
-    FileBackedHandle, MappedMem, MemoryHandle, NamedShmHandle, ShmHandle, ShmPath,
+    FileBackedHandle, MapError, MappedMem, MemoryHandle, NamedShmHandle, ShmHandle, ShmPath,
 };
 use libc::off_t;
 use nix::errno::Errno;
@@ -17,11 +17,11 @@ use std::os::fd::OwnedFd;
 use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
 
 const MAPPING_MAX_SIZE: usize = 1 << 17; // 128 MiB ought to be enough for everybody?
-const NOT_COMMITTED: usize = 1 << (usize::BITS - 1);
+pub(crate) const NOT_COMMITTED: usize = 1 << (usize::BITS - 1);
 
-pub(crate) fn mmap_handle<T: FileBackedHandle>(mut handle: T) -> io::Result<MappedMem<T>> {
+pub(crate) fn mmap_handle<T: FileBackedHandle>(mut handle: T) -> Result<MappedMem<T>, MapError> {
     let shm = handle.get_shm_mut();
-    let fd = shm.handle.as_owned_fd()?;
+    let fd = shm.handle.as_owned_fd().map_err(MapError::InvalidHandle)?;
     if shm.size & NOT_COMMITTED != 0 {
         shm.size &= !NOT_COMMITTED;
         let page_size = NonZeroUsize::try_from(page_size::get()).unwrap();
@@ -33,7 +33,8 @@ pub(crate) fn mmap_handle<T: FileBackedHandle>(mut handle: T) -> io::Result<Mapp
                 MapFlags::MAP_SHARED,
                 Some(fd),
                 (MAPPING_MAX_SIZE - usize::from(page_size)) as off_t,
-            )?;
+            )
+            .map_err(|e| MapError::Mmap(e.into()))?;
             if shm.size == 0 {
                 shm.size = *(ptr as *mut usize);
             } else {
@@ -52,7 +53,8 @@ pub(crate) fn mmap_handle<T: FileBackedHandle>(mut handle: T) -> io::Result<Mapp
                 MapFlags::MAP_SHARED,
                 Some(fd),
                 0,
-            )?
+            )
+            .map_err(|e| MapError::Mmap(e.into()))?
         },
         mem: handle,
     })
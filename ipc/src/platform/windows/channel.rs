@@ -63,6 +63,10 @@ impl Channel {
         Ok(())
     }
 
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(self.inner.read_timeout)
+    }
+
     pub fn set_nonblocking(&mut self, nonblocking: bool) -> io::Result<()> {
         self.inner.blocking = !nonblocking;
         let mode = if nonblocking { PIPE_NOWAIT } else { PIPE_WAIT };
@@ -2,7 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::platform::{
-    FileBackedHandle, MappedMem, MemoryHandle, NamedShmHandle, PlatformHandle, ShmHandle, ShmPath,
+    FileBackedHandle, MapError, MappedMem, MemoryHandle, NamedShmHandle, PlatformHandle,
+    ShmHandle, ShmPath,
 };
 use std::ffi::{CStr, CString};
 use std::io::Error;
@@ -22,9 +23,9 @@ use winapi::um::winnt::{
 };
 
 const MAPPING_MAX_SIZE: usize = 100_000_000; // 100 MB ought to be enough for everybody?
-const NOT_COMMITTED: usize = 1 << (usize::BITS - 1);
+pub(crate) const NOT_COMMITTED: usize = 1 << (usize::BITS - 1);
 
-pub(crate) fn mmap_handle<T: FileBackedHandle>(mut handle: T) -> io::Result<MappedMem<T>> {
+pub(crate) fn mmap_handle<T: FileBackedHandle>(mut handle: T) -> Result<MappedMem<T>, MapError> {
     let shm = handle.get_shm_mut();
     let ptr = unsafe {
         MapViewOfFile(
@@ -36,7 +37,7 @@ pub(crate) fn mmap_handle<T: FileBackedHandle>(mut handle: T) -> io::Result<Mapp
         )
     };
     if ptr.is_null() {
-        return Err(Error::last_os_error());
+        return Err(MapError::Mmap(Error::last_os_error()));
     }
     if shm.size & NOT_COMMITTED != 0 {
         shm.size &= !NOT_COMMITTED;
@@ -50,7 +51,7 @@ pub(crate) fn mmap_handle<T: FileBackedHandle>(mut handle: T) -> io::Result<Mapp
                     mem::size_of::<MEMORY_BASIC_INFORMATION>(),
                 ) == 0
                 {
-                    return Err(Error::last_os_error());
+                    return Err(MapError::Mmap(Error::last_os_error()));
                 }
                 info.assume_init().RegionSize
             };
@@ -90,10 +91,14 @@ fn alloc_shm(name: LPCSTR) -> io::Result<RawHandle> {
 static ANON_HANDLE_COUNTER: AtomicU32 = AtomicU32::new(0);
 
 impl ShmHandle {
+    /// Allocates anonymous shared memory of `size` bytes, to be mapped with
+    /// [`FileBackedHandle::map`] or sent to another process.
     pub fn new(size: usize) -> anyhow::Result<ShmHandle> {
         Self::new_named(size, "shm-handle")
     }
 
+    /// Like [`ShmHandle::new`], with `name` appearing in the name of the file mapping to ease
+    /// debugging.
     pub fn new_named(size: usize, name: &str) -> anyhow::Result<ShmHandle> {
         // If one uses null_mut() for the name, DuplicateHandle will emit a very
         // confusing "The system cannot find the file specified. (os error 2)".
@@ -124,6 +129,7 @@ impl NamedShmHandle {
         .unwrap() // strip leading slash
     }
 
+    /// Creates or opens the shared memory at `path`, e.g. `/my-shm`, reserving `size` bytes.
     pub fn create(path: CString, size: usize) -> io::Result<NamedShmHandle> {
         let name = Self::format_name(&path);
         Self::new(
@@ -133,6 +139,7 @@ impl NamedShmHandle {
         )
     }
 
+    /// Opens the existing shared memory at `path`, created by [`NamedShmHandle::create`].
     pub fn open(path: &CStr) -> io::Result<NamedShmHandle> {
         let name = Self::format_name(path);
         let handle = unsafe { OpenFileMappingA(FILE_MAP_WRITE, 0, name.as_ptr() as LPCSTR) };
@@ -168,6 +168,10 @@ where
         self.transport.channel.set_write_timeout(timeout)
     }
 
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.transport.channel.read_timeout()
+    }
+
     pub fn is_closed(&self) -> bool {
         // The blocking transport is not supposed to be readable on the client side unless it's a
         // response. So, outside of waiting for a response, it will never be readable unless
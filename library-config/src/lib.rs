@@ -3,9 +3,9 @@
 
 use std::borrow::Cow;
 use std::cell::OnceCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 use anyhow::Context;
@@ -88,19 +88,48 @@ impl<'a, T: Deref<Target = [u8]>> Matcher<'a, T> {
         }
     }
 
-    /// Returns the first set of configurations that match the current process
+    /// Returns the index of the first rule matching the current process, along with its set of
+    /// configurations
     fn find_stable_config<'b>(
         &'a self,
         cfg: &'b StableConfig,
-    ) -> Option<&'b HashMap<LibraryConfigName, String>> {
-        for rule in &cfg.rules {
+    ) -> Option<(usize, &'b HashMap<LibraryConfigName, String>)> {
+        for (index, rule) in cfg.rules.iter().enumerate() {
             if rule.selectors.iter().all(|s| self.selector_match(s)) {
-                return Some(&rule.configuration);
+                return Some((index, &rule.configuration));
             }
         }
         None
     }
 
+    /// Evaluates the rules in order like [`Matcher::find_stable_config`], reporting for each of
+    /// them the first selector which did not match. Stops after the first matching rule.
+    fn explain_rules(&'a self, cfg: &StableConfig) -> Vec<RuleExplanation> {
+        let mut explanations = Vec::new();
+        for (rule_index, rule) in cfg.rules.iter().enumerate() {
+            let failed_selector = rule
+                .selectors
+                .iter()
+                .enumerate()
+                .find(|(_, s)| !self.selector_match(s))
+                .map(|(index, selector)| FailedSelector {
+                    index,
+                    origin: selector.origin.name(),
+                    key: selector.key.clone(),
+                    operator: selector.operator.name(),
+                });
+            let matched = failed_selector.is_none();
+            explanations.push(RuleExplanation {
+                rule_index,
+                failed_selector,
+            });
+            if matched {
+                break;
+            }
+        }
+        explanations
+    }
+
     /// Returns true if the selector matches the process
     ///
     /// Any element in the "matches" section of the selector must match, they are ORed,
@@ -132,6 +161,8 @@ impl<'a, T: Deref<Target = [u8]>> Matcher<'a, T> {
     fn template_configs(
         &'a self,
         config: &HashMap<LibraryConfigName, String>,
+        source: LibraryConfigSource,
+        (origin_path, rule_index): (Option<&Path>, usize),
     ) -> anyhow::Result<Vec<LibraryConfig>> {
         config
             .iter()
@@ -139,6 +170,9 @@ impl<'a, T: Deref<Target = [u8]>> Matcher<'a, T> {
                 Ok(LibraryConfig {
                     name,
                     value: self.template_config(v)?,
+                    source,
+                    origin_path: origin_path.map(Path::to_path_buf),
+                    rule_index: Some(rule_index),
                 })
             })
             .collect()
@@ -153,19 +187,19 @@ impl<'a, T: Deref<Target = [u8]>> Matcher<'a, T> {
     /// with the following varriable definition, var = "abc" var2 = "def", this transforms \
     /// "foo_{{ var }}_bar_{{ var2 }}" -> "foo_abc_bar_def"
     fn template_config(&'a self, config_val: &str) -> anyhow::Result<String> {
-        let mut rest = config_val;
         let mut templated = String::with_capacity(config_val.len());
-        loop {
-            let Some((head, after_bracket)) = rest.split_once("{{") else {
-                templated.push_str(rest);
-                return Ok(templated);
-            };
-            templated.push_str(head);
-            let Some((template_var, tail)) = after_bracket.split_once("}}") else {
-                anyhow::bail!("unterminated template in config")
+        for part in parse_template(config_val) {
+            let (variable, index) = match part {
+                TemplatePart::Text(text) => {
+                    templated.push_str(text);
+                    continue;
+                }
+                TemplatePart::Expression {
+                    variable, index, ..
+                } => (variable, index),
+                TemplatePart::Unterminated(_) => anyhow::bail!("unterminated template in config"),
             };
-            let (template_var, index) = parse_template_var(template_var.trim());
-            let val = match template_var {
+            let val = match variable {
                 "language" => String::from_utf8_lossy(self.process_info.language.deref()),
                 "environment_variables" => {
                     template_map_key(index, self.match_maps.env(self.process_info))
@@ -177,9 +211,51 @@ impl<'a, T: Deref<Target = [u8]>> Matcher<'a, T> {
                 _ => std::borrow::Cow::Borrowed("UNDEFINED"),
             };
             templated.push_str(&val);
-            rest = tail;
         }
+        Ok(templated)
+    }
+}
+
+/// A piece of a configuration value template, see [`parse_template`].
+enum TemplatePart<'t> {
+    Text(&'t str),
+    /// A `{{ variable[index] }}` expression
+    Expression {
+        /// The trimmed text between the curly brackets
+        expression: &'t str,
+        variable: &'t str,
+        index: Option<&'t str>,
+    },
+    /// A `{{` without matching `}}`, followed by the rest of the template
+    Unterminated(&'t str),
+}
+
+/// Splits a configuration value template into text and expressions, up to the first unterminated
+/// expression. Shared by the templating and the validation of templates, so that they agree.
+fn parse_template(template: &str) -> Vec<TemplatePart<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some((head, after_bracket)) = rest.split_once("{{") {
+        if !head.is_empty() {
+            parts.push(TemplatePart::Text(head));
+        }
+        let Some((expression, tail)) = after_bracket.split_once("}}") else {
+            parts.push(TemplatePart::Unterminated(after_bracket));
+            return parts;
+        };
+        let expression = expression.trim();
+        let (variable, index) = parse_template_var(expression);
+        parts.push(TemplatePart::Expression {
+            expression,
+            variable,
+            index,
+        });
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        parts.push(TemplatePart::Text(rest));
     }
+    parts
 }
 
 fn map_operator_match(selector: &Selector, map: &impl Get, key: &str) -> bool {
@@ -215,6 +291,40 @@ pub struct ProcessInfo<'a, T: Deref<Target = [u8]>> {
     pub language: T,
 }
 
+impl<'a, T: Deref<Target = [u8]>> ProcessInfo<'a, T> {
+    /// Builds the process info from arbitrary arguments, environment and language, without
+    /// looking at the current process.
+    ///
+    /// This allows evaluating the stable config on behalf of another process, e.g. a child
+    /// process which is about to be exec'd. `envp` entries have the `KEY=VALUE` format.
+    ///
+    /// The arguments and environment are borrowed, as this struct is also exposed over FFI, but
+    /// each entry can be owned, e.g. `ProcessInfo::<Vec<u8>>::from_parts(&args, &envp, lang)`
+    /// with `Vec<Vec<u8>>` arguments and environment.
+    pub fn from_parts(args: &'a [T], envp: &'a [T], language: T) -> Self {
+        ProcessInfo {
+            args,
+            envp,
+            language,
+        }
+    }
+}
+
+impl<'a> ProcessInfo<'a, &'a [u8]> {
+    /// Same as [`ProcessInfo::from_parts`], from string arguments, environment and language.
+    pub fn from_str_slices(args: &'a [&'a str], envp: &'a [&'a str], language: &'a str) -> Self {
+        fn as_byte_slices<'a>(strs: &'a [&'a str]) -> &'a [&'a [u8]] {
+            // Safety: &str and &[u8] have the same layout, a str being a slice of UTF-8 bytes
+            unsafe { std::slice::from_raw_parts(strs.as_ptr() as *const &[u8], strs.len()) }
+        }
+        ProcessInfo::from_parts(
+            as_byte_slices(args),
+            as_byte_slices(envp),
+            language.as_bytes(),
+        )
+    }
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, serde::Deserialize, Debug, PartialEq, Eq, Hash)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -249,6 +359,18 @@ enum Origin {
     Tags,
 }
 
+impl Origin {
+    /// The name of the origin in the configuration file
+    fn name(&self) -> &'static str {
+        match self {
+            Origin::ProcessArguments => "process_arguments",
+            Origin::EnvironmentVariables => "environment_variables",
+            Origin::Language => "language",
+            Origin::Tags => "tags",
+        }
+    }
+}
+
 #[derive(serde::Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "operator")]
@@ -261,6 +383,18 @@ enum Operator {
     // WildcardMatches,
 }
 
+impl Operator {
+    /// The name of the operator in the configuration file
+    fn name(&self) -> &'static str {
+        match self {
+            Operator::Exists => "exists",
+            Operator::Equals { .. } => "equals",
+            Operator::PrefixMatches { .. } => "prefix_matches",
+            Operator::SuffixMatches { .. } => "suffix_matches",
+        }
+    }
+}
+
 #[derive(serde::Deserialize, Debug, PartialEq, Eq)]
 struct Selector {
     origin: Origin,
@@ -273,9 +407,48 @@ struct Selector {
 #[derive(serde::Deserialize, Debug, PartialEq, Eq)]
 struct Rule {
     selectors: Vec<Selector>,
+    #[serde(deserialize_with = "deserialize_configuration")]
     configuration: HashMap<LibraryConfigName, String>,
 }
 
+/// Deserializes the configuration of a rule. Scalar values are kept as strings and lists are
+/// stored as their comma-joined items, see [`LibraryConfig::as_list`].
+fn deserialize_configuration<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<LibraryConfigName, String>, D::Error> {
+    fn scalar_to_string(value: serde_yaml::Value) -> Option<String> {
+        match value {
+            serde_yaml::Value::String(s) => Some(s),
+            serde_yaml::Value::Bool(b) => Some(b.to_string()),
+            serde_yaml::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    }
+
+    let configuration: HashMap<LibraryConfigName, serde_yaml::Value> =
+        serde::Deserialize::deserialize(deserializer)?;
+    configuration
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                serde_yaml::Value::Sequence(items) => items
+                    .into_iter()
+                    .map(scalar_to_string)
+                    .collect::<Option<Vec<_>>>()
+                    .map(|items| items.join(",")),
+                value => scalar_to_string(value),
+            };
+            let value = value.ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "{}: expected a string or a list of strings",
+                    name.to_str()
+                ))
+            })?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
 #[derive(serde::Deserialize, Default, Debug, PartialEq, Eq)]
 struct StableConfig {
     #[serde(default)]
@@ -283,6 +456,61 @@ struct StableConfig {
     rules: Vec<Rule>,
 }
 
+impl StableConfig {
+    /// Adds the given tags, unless the configuration defines a tag with the same key.
+    fn seed_tags(&mut self, tags: &HashMap<String, String>) {
+        for (key, value) in tags {
+            self.tags
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    /// Checks the templates of every configuration value without evaluating them, flagging
+    /// references to unknown variables and malformed expressions, which would otherwise silently
+    /// be templated as `UNDEFINED` or fail when applied.
+    fn validate_templates(&self) -> Vec<TemplateWarning> {
+        let mut warnings = Vec::new();
+        for (rule_index, rule) in self.rules.iter().enumerate() {
+            for (&name, value) in &rule.configuration {
+                let mut warn = |kind| {
+                    warnings.push(TemplateWarning {
+                        rule_index,
+                        name,
+                        kind,
+                    })
+                };
+                for part in parse_template(value) {
+                    let (expression, variable, index) = match part {
+                        TemplatePart::Text(_) => continue,
+                        TemplatePart::Expression {
+                            expression,
+                            variable,
+                            index,
+                        } => (expression, variable, index),
+                        TemplatePart::Unterminated(rest) => {
+                            warn(TemplateWarningKind::Malformed(rest.to_string()));
+                            continue;
+                        }
+                    };
+                    match (variable, index) {
+                        ("language", None) => {}
+                        ("environment_variables" | "process_arguments" | "tags", Some(key))
+                            if !key.is_empty() => {}
+                        ("environment_variables" | "process_arguments" | "tags", _)
+                        | ("language", Some(_)) => {
+                            warn(TemplateWarningKind::Malformed(expression.to_string()))
+                        }
+                        _ => warn(TemplateWarningKind::UnknownVariable(variable.to_string())),
+                    }
+                }
+            }
+        }
+        warnings.sort_by_key(|w| (w.rule_index, w.name as u32));
+        warnings
+    }
+}
+
 /// Helper trait so we don't have to duplicate code for
 /// HashMap<&str, &str> and HashMap<String, String>
 trait Get {
@@ -327,20 +555,172 @@ fn string_operator_match(op: &Operator, matches: &[u8], value: &[u8]) -> bool {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Where the value of a configuration entry comes from, by increasing priority.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LibraryConfigSource {
+    LocalStableConfig = 0,
+    FleetStableConfig = 1,
+    EnvVar = 2,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct LibraryConfig {
     pub name: LibraryConfigName,
     pub value: String,
+    pub source: LibraryConfigSource,
+    /// The file the entry was read from, `None` if it was not read from a file
+    pub origin_path: Option<PathBuf>,
+    /// Index of the rule the entry comes from within its stable configuration, e.g. to report it
+    /// via telemetry. `None` if it does not come from a stable configuration
+    pub rule_index: Option<usize>,
+}
+
+impl LibraryConfig {
+    /// Parses the value as a boolean, accepting `true/false`, `1/0` and `yes/no` (case
+    /// insensitive, surrounding whitespace ignored). Returns `None` for any other value.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Some(true),
+            "false" | "0" | "no" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Parses the value as an integer, ignoring surrounding whitespace.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.value.trim().parse().ok()
+    }
+
+    /// Parses the value as a floating point number, ignoring surrounding whitespace.
+    pub fn as_f64(&self) -> Option<f64> {
+        self.value.trim().parse().ok()
+    }
+
+    /// Parses the value as a comma separated list, trimming whitespace around the items and
+    /// skipping empty ones. A value written as a YAML list is stored comma-joined.
+    pub fn as_list(&self) -> Vec<String> {
+        self.value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// A problem found in a configuration value template, see [`Configurator::validate_templates`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct TemplateWarning {
+    /// Index of the rule in the stable configuration
+    pub rule_index: usize,
+    /// The configuration entry whose value contains the template
+    pub name: LibraryConfigName,
+    /// What is wrong with the template
+    pub kind: TemplateWarningKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TemplateWarningKind {
+    /// The variable is none of `language`, `environment_variables`, `process_arguments` or `tags`
+    UnknownVariable(String),
+    /// The expression is unterminated, or misses or has an unexpected `[key]` index
+    Malformed(String),
+}
+
+/// How a rule was evaluated against a process, see [`Configurator::explain_config`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct RuleExplanation {
+    /// Index of the rule in the stable configuration
+    pub rule_index: usize,
+    /// The first selector of the rule which did not match, `None` if the rule matched
+    pub failed_selector: Option<FailedSelector>,
+}
+
+/// A selector which did not match the process, as written in the configuration.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FailedSelector {
+    /// Index of the selector in the rule
+    pub index: usize,
+    /// The origin of the selector, e.g. `process_arguments`
+    pub origin: &'static str,
+    /// The key of the selector, if any
+    pub key: Option<String>,
+    /// The operator of the selector, e.g. `equals`
+    pub operator: &'static str,
+}
+
+/// Difference between two evaluations of the stable configuration, see [`Configurator::diff`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ConfigDiff {
+    /// Entries only present in the new configuration
+    pub added: Vec<LibraryConfig>,
+    /// Names of the entries only present in the old configuration
+    pub removed: Vec<LibraryConfigName>,
+    /// Entries present in both configurations, with their new value
+    pub changed: Vec<LibraryConfig>,
 }
 
 #[derive(Debug)]
 pub struct Configurator {
     debug_logs: bool,
+    best_effort: bool,
 }
 
 impl Configurator {
     pub fn new(debug_logs: bool) -> Self {
-        Self { debug_logs }
+        Self {
+            debug_logs,
+            best_effort: false,
+        }
+    }
+
+    /// Creates a configurator which treats a configuration file failing to parse as empty
+    /// instead of failing the whole load, so that a broken source doesn't discard the valid ones.
+    pub fn new_best_effort(debug_logs: bool) -> Self {
+        Self {
+            best_effort: true,
+            ..Self::new(debug_logs)
+        }
+    }
+
+    /// Computes which entries were added, removed or had their value changed between two
+    /// evaluations of the configuration. Entries are matched by name, unchanged ones are omitted.
+    pub fn diff(old: &[LibraryConfig], new: &[LibraryConfig]) -> ConfigDiff {
+        let old_values: HashMap<_, _> = old.iter().map(|c| (c.name, &c.value)).collect();
+        let new_names: HashSet<_> = new.iter().map(|c| c.name).collect();
+
+        let mut diff = ConfigDiff::default();
+        for config in new {
+            match old_values.get(&config.name) {
+                None => diff.added.push(config.clone()),
+                Some(old_value) if **old_value != config.value => diff.changed.push(config.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .iter()
+            .map(|c| c.name)
+            .filter(|name| !new_names.contains(name))
+            .collect();
+        diff
+    }
+
+    /// Renders resolved entries as a stable configuration with a single rule matching any
+    /// process, e.g. to inspect or replay the outcome of an evaluation. Entries are written in
+    /// order, a later entry with the same name overriding an earlier one. Values are written
+    /// verbatim, so a value containing `{{` would be evaluated as a template when read back.
+    pub fn to_yaml(configs: &[LibraryConfig]) -> String {
+        let mut configuration = serde_yaml::Mapping::new();
+        for config in configs {
+            configuration.insert(config.name.to_str().into(), config.value.as_str().into());
+        }
+        let mut rule = serde_yaml::Mapping::new();
+        rule.insert("selectors".into(), serde_yaml::Sequence::new().into());
+        rule.insert("configuration".into(), configuration.into());
+        let mut stable_config = serde_yaml::Mapping::new();
+        stable_config.insert("rules".into(), vec![serde_yaml::Value::from(rule)].into());
+        serde_yaml::to_string(&stable_config).expect("a mapping of strings is serializable")
     }
 
     fn log_process_info(&self, process_info: &ProcessInfo<'_, impl Deref<Target = [u8]>>) {
@@ -366,12 +746,208 @@ impl Configurator {
         path: &Path,
         process_info: ProcessInfo<'_, impl Deref<Target = [u8]>>,
     ) -> anyhow::Result<Vec<LibraryConfig>> {
-        let stable_config = match fs::File::open(path) {
-            Ok(file) => self.parse_stable_config(&mut io::BufReader::new(file))?,
-            Err(e) if e.kind() == io::ErrorKind::NotFound => StableConfig::default(),
-            Err(e) => return Err(e).context("failed to open config file"),
+        let stable_config = self.read_stable_config_file(path)?;
+        self.get_config(
+            &stable_config,
+            &process_info,
+            LibraryConfigSource::LocalStableConfig,
+            |rule_index| (Some(path), rule_index),
+        )
+    }
+
+    /// Computes the configuration the library should apply, with the precedence
+    /// env > fleet > local: entries of the fleet stable config file override the local ones, and
+    /// `env_overrides`, e.g. the `DD_*` variables set in the environment of the process, override
+    /// both with the `EnvVar` source. Missing files yield no configuration.
+    pub fn resolve_effective(
+        &self,
+        local: &Path,
+        fleet: &Path,
+        env_overrides: &HashMap<LibraryConfigName, String>,
+        process_info: ProcessInfo<'_, impl Deref<Target = [u8]>>,
+    ) -> anyhow::Result<Vec<LibraryConfig>> {
+        let local_config = self.read_stable_config_file(local)?;
+        let fleet_config = self.read_stable_config_file(fleet)?;
+        self.merge_configs(
+            (&local_config, Some(local)),
+            (&fleet_config, Some(fleet)),
+            env_overrides,
+            &process_info,
+        )
+    }
+
+    /// Computes the configuration the library should apply like [`Configurator::resolve_effective`]
+    /// without environment overrides, for a fleet stable config received in memory, e.g. through
+    /// remote config, rather than from the file managed by the agent. A missing local file yields
+    /// no local configuration.
+    pub fn apply_remote_fleet_config(
+        &self,
+        local_file: &Path,
+        fleet_yaml: &[u8],
+        process_info: ProcessInfo<'_, impl Deref<Target = [u8]>>,
+    ) -> anyhow::Result<Vec<LibraryConfig>> {
+        let local_config = self.read_stable_config_file(local_file)?;
+        let fleet_config = self.recover_best_effort(
+            self.parse_stable_config(&mut io::Cursor::new(fleet_yaml))
+                .context("failed to parse remote fleet config"),
+        )?;
+        self.merge_configs(
+            (&local_config, Some(local_file)),
+            (&fleet_config, None),
+            &HashMap::new(),
+            &process_info,
+        )
+    }
+
+    /// Computes the configuration the library should apply like [`Configurator::resolve_effective`]
+    /// without environment overrides, with `extra_tags` known to the library, e.g. from `DD_TAGS` or
+    /// orchestrator metadata, available to the selectors and templates of both files as `tags`.
+    /// Tags defined in a file take precedence over extra tags with the same key.
+    pub fn get_config_with_tags(
+        &self,
+        local: &Path,
+        fleet: &Path,
+        extra_tags: &HashMap<String, String>,
+        process_info: ProcessInfo<'_, impl Deref<Target = [u8]>>,
+    ) -> anyhow::Result<Vec<LibraryConfig>> {
+        let mut local_config = self.read_stable_config_file(local)?;
+        let mut fleet_config = self.read_stable_config_file(fleet)?;
+        local_config.seed_tags(extra_tags);
+        fleet_config.seed_tags(extra_tags);
+        self.merge_configs(
+            (&local_config, Some(local)),
+            (&fleet_config, Some(fleet)),
+            &HashMap::new(),
+            &process_info,
+        )
+    }
+
+    /// Evaluates the local and fleet stable configs, along with the file they were read from, and
+    /// applies the precedence env > fleet > local.
+    fn merge_configs(
+        &self,
+        (local_config, local_path): (&StableConfig, Option<&Path>),
+        (fleet_config, fleet_path): (&StableConfig, Option<&Path>),
+        env_overrides: &HashMap<LibraryConfigName, String>,
+        process_info: &ProcessInfo<'_, impl Deref<Target = [u8]>>,
+    ) -> anyhow::Result<Vec<LibraryConfig>> {
+        let mut effective = self.get_config(
+            local_config,
+            process_info,
+            LibraryConfigSource::LocalStableConfig,
+            |rule_index| (local_path, rule_index),
+        )?;
+        let overrides = self
+            .get_config(
+                fleet_config,
+                process_info,
+                LibraryConfigSource::FleetStableConfig,
+                |rule_index| (fleet_path, rule_index),
+            )?
+            .into_iter()
+            .chain(env_overrides.iter().map(|(&name, value)| LibraryConfig {
+                name,
+                value: value.clone(),
+                source: LibraryConfigSource::EnvVar,
+                origin_path: None,
+                rule_index: None,
+            }));
+        for config in overrides {
+            match effective.iter_mut().find(|c| c.name == config.name) {
+                Some(existing) => *existing = config,
+                None => effective.push(config),
+            }
+        }
+        effective.sort_by_key(|c| c.name as u32);
+        if self.debug_logs {
+            eprintln!("Effective configuration:\n\t{effective:?}");
+        }
+        Ok(effective)
+    }
+
+    fn read_stable_config_file(&self, path: &Path) -> anyhow::Result<StableConfig> {
+        let result = match fs::File::open(path) {
+            Ok(file) => self.parse_stable_config(&mut io::BufReader::new(file)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(StableConfig::default()),
+            Err(e) => Err(e).context("failed to open config file"),
         };
-        self.get_config(&stable_config, process_info)
+        self.recover_best_effort(
+            result.with_context(|| format!("failed to read config file {}", path.display())),
+        )
+    }
+
+    /// In best effort mode, discards the error of a configuration source and treats it as empty
+    fn recover_best_effort(
+        &self,
+        result: anyhow::Result<StableConfig>,
+    ) -> anyhow::Result<StableConfig> {
+        match result {
+            Err(e) if self.best_effort => {
+                if self.debug_logs {
+                    eprintln!("Ignoring invalid configuration source: {e:?}");
+                }
+                Ok(StableConfig::default())
+            }
+            result => result,
+        }
+    }
+
+    /// Reads the configuration from all the `*.yaml` and `*.yml` files of a directory, in lexical
+    /// order. Later files take priority: their tags override the ones of earlier files and their
+    /// rules are evaluated first. Other files are ignored and a missing directory yields no
+    /// configuration.
+    pub fn get_config_from_dir(
+        &self,
+        dir: &Path,
+        process_info: ProcessInfo<'_, impl Deref<Target = [u8]>>,
+    ) -> anyhow::Result<Vec<LibraryConfig>> {
+        let mut paths = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()
+                .context("failed to list config directory")?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).context("failed to open config directory"),
+        };
+        paths.retain(|path| {
+            path.is_file()
+                && matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("yaml" | "yml")
+                )
+        });
+        paths.sort();
+
+        let mut stable_config = StableConfig::default();
+        // The file each rule of stable_config comes from, with its index within that file
+        let mut rule_origins = Vec::new();
+        for path in paths {
+            let fragment = self.recover_best_effort(
+                fs::File::open(&path)
+                    .with_context(|| format!("failed to open config file {}", path.display()))
+                    .and_then(|file| {
+                        self.parse_stable_config(&mut io::BufReader::new(file))
+                            .with_context(|| {
+                                format!("failed to parse config file {}", path.display())
+                            })
+                    }),
+            )?;
+            stable_config.tags.extend(fragment.tags);
+            rule_origins.splice(
+                0..0,
+                (0..fragment.rules.len()).map(|rule_index| (path.clone(), rule_index)),
+            );
+            stable_config.rules.splice(0..0, fragment.rules);
+        }
+        self.get_config(
+            &stable_config,
+            &process_info,
+            LibraryConfigSource::LocalStableConfig,
+            |rule_index| {
+                let (path, rule_index) = &rule_origins[rule_index];
+                (Some(path.as_path()), *rule_index)
+            },
+        )
     }
 
     pub fn get_config_from_bytes(
@@ -380,7 +956,33 @@ impl Configurator {
         process_info: ProcessInfo<'_, impl Deref<Target = [u8]>>,
     ) -> anyhow::Result<Vec<LibraryConfig>> {
         let stable_config: StableConfig = self.parse_stable_config(&mut io::Cursor::new(s))?;
-        self.get_config(&stable_config, process_info)
+        self.get_config(
+            &stable_config,
+            &process_info,
+            LibraryConfigSource::LocalStableConfig,
+            |rule_index| (None, rule_index),
+        )
+    }
+
+    /// Parses a stable configuration and checks the templates of its values without evaluating
+    /// them against a process, e.g. as a pre-flight check for configuration authors.
+    /// Returns an error if the configuration cannot be parsed.
+    pub fn validate_templates(&self, s: &[u8]) -> anyhow::Result<Vec<TemplateWarning>> {
+        let stable_config = self.parse_stable_config(&mut io::Cursor::new(s))?;
+        Ok(stable_config.validate_templates())
+    }
+
+    /// Parses a stable configuration and evaluates its rules against the process, explaining
+    /// which selector prevented each rule from matching, e.g. to debug why a rule was not applied.
+    /// Rules are evaluated in order up to the first matching one, like when applying them.
+    pub fn explain_config(
+        &self,
+        s: &[u8],
+        process_info: ProcessInfo<'_, impl Deref<Target = [u8]>>,
+    ) -> anyhow::Result<Vec<RuleExplanation>> {
+        let stable_config = self.parse_stable_config(&mut io::Cursor::new(s))?;
+        let matcher = Matcher::new(&process_info, &stable_config.tags);
+        Ok(matcher.explain_rules(&stable_config))
     }
 
     fn parse_stable_config<F: io::Read>(&self, f: &mut F) -> anyhow::Result<StableConfig> {
@@ -391,20 +993,24 @@ impl Configurator {
         Ok(stable_config)
     }
 
-    fn get_config(
+    /// Evaluates the stable configuration, `rule_origin` returns the file the rule at the given
+    /// index was read from, along with the index of the rule within that file.
+    fn get_config<'p>(
         &self,
         stable_config: &StableConfig,
-        process_info: ProcessInfo<'_, impl Deref<Target = [u8]>>,
+        process_info: &ProcessInfo<'_, impl Deref<Target = [u8]>>,
+        source: LibraryConfigSource,
+        rule_origin: impl FnOnce(usize) -> (Option<&'p Path>, usize),
     ) -> anyhow::Result<Vec<LibraryConfig>> {
-        self.log_process_info(&process_info);
-        let matcher = Matcher::new(&process_info, &stable_config.tags);
-        let Some(configs) = matcher.find_stable_config(stable_config) else {
+        self.log_process_info(process_info);
+        let matcher = Matcher::new(process_info, &stable_config.tags);
+        let Some((rule_index, configs)) = matcher.find_stable_config(stable_config) else {
             if self.debug_logs {
                 eprintln!("No selector matched");
             }
             return Ok(Vec::new());
         };
-        let library_config = matcher.template_configs(configs)?;
+        let library_config = matcher.template_configs(configs, source, rule_origin(rule_index))?;
         if self.debug_logs {
             eprintln!("Will apply the following configuration:\n\t{library_config:?}");
         }
@@ -416,9 +1022,13 @@ impl Configurator {
 mod tests {
     use std::{collections::HashMap, io::Write};
 
-    use super::{Configurator, ProcessInfo};
+    use super::{
+        ConfigDiff, Configurator, FailedSelector, ProcessInfo, RuleExplanation, TemplateWarning,
+        TemplateWarningKind,
+    };
     use crate::{
-        LibraryConfig, LibraryConfigName, Matcher, Operator, Origin, Rule, Selector, StableConfig,
+        LibraryConfig, LibraryConfigName, LibraryConfigSource, Matcher, Operator, Origin, Rule,
+        Selector, StableConfig,
     };
 
     macro_rules! map {
@@ -463,7 +1073,10 @@ rules:
             config,
             vec![LibraryConfig {
                 name: LibraryConfigName::DdService,
-                value: "my_service_my_cluster_my_config_java".to_string()
+                value: "my_service_my_cluster_my_config_java".to_string(),
+                source: LibraryConfigSource::LocalStableConfig,
+                origin_path: None,
+                rule_index: Some(0),
             }]
         );
     }
@@ -581,4 +1194,688 @@ rules:
             assert_eq!(matcher.selector_match(selector), *matches, "case {i}");
         }
     }
+
+    #[test]
+    fn test_typed_values() {
+        let config = |value: &str| LibraryConfig {
+            name: LibraryConfigName::DdTraceDebug,
+            value: value.to_string(),
+            source: LibraryConfigSource::LocalStableConfig,
+            origin_path: None,
+            rule_index: None,
+        };
+
+        for value in ["true", "TRUE", "True", "1", "yes", "YES", " true "] {
+            assert_eq!(config(value).as_bool(), Some(true), "{value}");
+        }
+        for value in ["false", "FALSE", "0", "no", "No", "false\n"] {
+            assert_eq!(config(value).as_bool(), Some(false), "{value}");
+        }
+        for value in ["", "2", "on", "truee", "y"] {
+            assert_eq!(config(value).as_bool(), None, "{value}");
+        }
+
+        assert_eq!(config("42").as_i64(), Some(42));
+        assert_eq!(config(" -7 ").as_i64(), Some(-7));
+        assert_eq!(config("4.2").as_i64(), None);
+        assert_eq!(config("abc").as_i64(), None);
+        assert_eq!(config("").as_i64(), None);
+
+        assert_eq!(config("4.2").as_f64(), Some(4.2));
+        assert_eq!(config("42").as_f64(), Some(42.0));
+        assert_eq!(config("-1e3").as_f64(), Some(-1000.0));
+        assert_eq!(config("abc").as_f64(), None);
+    }
+
+    #[test]
+    fn test_list_values() {
+        let config = |value: &str| LibraryConfig {
+            name: LibraryConfigName::DdService,
+            value: value.to_string(),
+            source: LibraryConfigSource::LocalStableConfig,
+            origin_path: None,
+            rule_index: None,
+        };
+        assert_eq!(config("a, b ,c").as_list(), vec!["a", "b", "c"]);
+        assert_eq!(config("single").as_list(), vec!["single"]);
+        assert_eq!(config("a,,b,").as_list(), vec!["a", "b"]);
+        assert!(config("").as_list().is_empty());
+        assert!(config(" , ").as_list().is_empty());
+
+        let process_info: ProcessInfo<'_, &[u8]> = ProcessInfo::<&[u8]> {
+            args: &[],
+            envp: &[],
+            language: b"java",
+        };
+        let configs = Configurator::new(false)
+            .get_config_from_bytes(
+                b"
+rules:
+- selectors: []
+  configuration:
+    DD_SERVICE: [\"a\", \"b\", \"c\"]
+    DD_ENV: []
+",
+                process_info,
+            )
+            .unwrap();
+        let service = configs
+            .iter()
+            .find(|c| c.name == LibraryConfigName::DdService)
+            .unwrap();
+        assert_eq!(service.value, "a,b,c");
+        assert_eq!(service.as_list(), vec!["a", "b", "c"]);
+        let env = configs
+            .iter()
+            .find(|c| c.name == LibraryConfigName::DdEnv)
+            .unwrap();
+        assert_eq!(env.value, "");
+        assert!(env.as_list().is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let config = |name, value: &str| LibraryConfig {
+            name,
+            value: value.to_string(),
+            source: LibraryConfigSource::LocalStableConfig,
+            origin_path: None,
+            rule_index: None,
+        };
+        let old = [
+            config(LibraryConfigName::DdService, "svc"),
+            config(LibraryConfigName::DdEnv, "staging"),
+            config(LibraryConfigName::DdTraceDebug, "true"),
+        ];
+        let new = [
+            config(LibraryConfigName::DdEnv, "prod"),
+            config(LibraryConfigName::DdService, "svc"),
+            config(LibraryConfigName::DdVersion, "1.0"),
+        ];
+
+        assert_eq!(
+            Configurator::diff(&old, &new),
+            ConfigDiff {
+                added: vec![config(LibraryConfigName::DdVersion, "1.0")],
+                removed: vec![LibraryConfigName::DdTraceDebug],
+                changed: vec![config(LibraryConfigName::DdEnv, "prod")],
+            }
+        );
+        assert_eq!(Configurator::diff(&old, &old), ConfigDiff::default());
+    }
+
+    #[test]
+    fn test_get_config_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("10-base.yaml"),
+            b"
+tags:
+  cluster_name: base_cluster
+  team: apm
+rules:
+- selectors:
+  - origin: language
+    matches: [\"ruby\"]
+    operator: equals
+  configuration:
+    DD_SERVICE: base_ruby
+- selectors:
+  - origin: language
+    matches: [\"java\", \"php\"]
+    operator: equals
+  configuration:
+    DD_SERVICE: \"base_{{ tags[cluster_name] }}_{{ tags[team] }}\"
+",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("20-override.yml"),
+            b"
+tags:
+  cluster_name: override_cluster
+rules:
+- selectors:
+  - origin: language
+    matches: [\"java\"]
+    operator: equals
+  configuration:
+    DD_SERVICE: \"override_{{ tags[cluster_name] }}\"
+",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("30-notes.txt"), b"not: [valid yaml").unwrap();
+
+        let configurator = Configurator::new(false);
+        let get_config = |language: &'static [u8]| {
+            configurator
+                .get_config_from_dir(
+                    dir.path(),
+                    ProcessInfo::<&[u8]> {
+                        args: &[],
+                        envp: &[],
+                        language,
+                    },
+                )
+                .unwrap()
+        };
+        let service = |value: &str, file: &str, rule_index: usize| {
+            vec![LibraryConfig {
+                name: LibraryConfigName::DdService,
+                value: value.to_string(),
+                source: LibraryConfigSource::LocalStableConfig,
+                origin_path: Some(dir.path().join(file)),
+                rule_index: Some(rule_index),
+            }]
+        };
+
+        // The later fragment's rule takes priority
+        assert_eq!(
+            get_config(b"java"),
+            service("override_override_cluster", "20-override.yml", 0)
+        );
+        // Tags are merged across fragments, rule indexes are relative to their file
+        assert_eq!(
+            get_config(b"php"),
+            service("base_override_cluster_apm", "10-base.yaml", 1)
+        );
+        assert_eq!(get_config(b"ruby"), service("base_ruby", "10-base.yaml", 0));
+        assert_eq!(get_config(b"python"), vec![]);
+
+        let missing = dir.path().join("missing");
+        let config = configurator
+            .get_config_from_dir(
+                &missing,
+                ProcessInfo::<&[u8]> {
+                    args: &[],
+                    envp: &[],
+                    language: b"java",
+                },
+            )
+            .unwrap();
+        assert_eq!(config, vec![]);
+    }
+
+    #[test]
+    fn test_resolve_effective() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("local.yaml");
+        let fleet = dir.path().join("fleet.yaml");
+        std::fs::write(
+            &local,
+            b"
+rules:
+- selectors:
+  - origin: language
+    matches: [\"java\"]
+    operator: equals
+  configuration:
+    DD_SERVICE: local_service
+    DD_ENV: local_env
+    DD_VERSION: local_version
+",
+        )
+        .unwrap();
+        std::fs::write(
+            &fleet,
+            b"
+rules:
+- selectors:
+  - origin: language
+    matches: [\"java\"]
+    operator: equals
+  configuration:
+    DD_SERVICE: fleet_service
+    DD_ENV: fleet_env
+",
+        )
+        .unwrap();
+
+        let configurator = Configurator::new(false);
+        let config = configurator
+            .resolve_effective(
+                &local,
+                &fleet,
+                &map![(LibraryConfigName::DdService, "env_service".to_string())],
+                ProcessInfo::<&[u8]> {
+                    args: &[],
+                    envp: &[],
+                    language: b"java",
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            config,
+            vec![
+                LibraryConfig {
+                    name: LibraryConfigName::DdService,
+                    value: "env_service".to_string(),
+                    source: LibraryConfigSource::EnvVar,
+                    origin_path: None,
+                    rule_index: None,
+                },
+                LibraryConfig {
+                    name: LibraryConfigName::DdEnv,
+                    value: "fleet_env".to_string(),
+                    source: LibraryConfigSource::FleetStableConfig,
+                    origin_path: Some(fleet.clone()),
+                    rule_index: Some(0),
+                },
+                LibraryConfig {
+                    name: LibraryConfigName::DdVersion,
+                    value: "local_version".to_string(),
+                    source: LibraryConfigSource::LocalStableConfig,
+                    origin_path: Some(local.clone()),
+                    rule_index: Some(0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_config_with_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("local.yaml");
+        std::fs::write(
+            &local,
+            b"
+tags:
+  team: local_team
+rules:
+- selectors:
+  - origin: tags
+    key: cluster_name
+    matches: [\"prod-\"]
+    operator: prefix_matches
+  configuration:
+    DD_SERVICE: \"{{ tags[team] }}_{{ tags[cluster_name] }}\"
+",
+        )
+        .unwrap();
+        let missing_fleet = dir.path().join("missing");
+
+        let configurator = Configurator::new(false);
+        let get_config = |cluster_name: &str| {
+            configurator
+                .get_config_with_tags(
+                    &local,
+                    &missing_fleet,
+                    &map![
+                        ("cluster_name".to_string(), cluster_name.to_string()),
+                        ("team".to_string(), "extra_team".to_string()),
+                    ],
+                    ProcessInfo::<&[u8]> {
+                        args: &[],
+                        envp: &[],
+                        language: b"java",
+                    },
+                )
+                .unwrap()
+        };
+        assert_eq!(
+            get_config("prod-eu"),
+            vec![LibraryConfig {
+                name: LibraryConfigName::DdService,
+                value: "local_team_prod-eu".to_string(),
+                source: LibraryConfigSource::LocalStableConfig,
+                origin_path: Some(local.clone()),
+                rule_index: Some(0),
+            }]
+        );
+        assert_eq!(get_config("staging"), vec![]);
+    }
+
+    #[test]
+    fn test_to_yaml_round_trip() {
+        let process_info = || ProcessInfo::<&[u8]> {
+            args: &[],
+            envp: &[],
+            language: b"java",
+        };
+        let configurator = Configurator::new(false);
+        let configs = configurator
+            .get_config_from_bytes(
+                b"
+tags:
+  cluster_name: my_cluster
+rules:
+- selectors:
+  - origin: language
+    matches: [\"java\"]
+    operator: equals
+  configuration:
+    DD_SERVICE: service_{{ tags[cluster_name] }}
+    DD_ENV: \"prod: eu\"
+    DD_PROFILING_ENABLED: \"true\"
+",
+                process_info(),
+            )
+            .unwrap();
+        assert_eq!(configs.len(), 3);
+
+        let yaml = Configurator::to_yaml(&configs);
+        let reparsed = configurator
+            .get_config_from_bytes(yaml.as_bytes(), process_info())
+            .unwrap();
+        let entries = |configs: &[LibraryConfig]| -> HashMap<LibraryConfigName, String> {
+            configs.iter().map(|c| (c.name, c.value.clone())).collect()
+        };
+        assert_eq!(entries(&configs), entries(&reparsed));
+        assert_eq!(
+            entries(&reparsed)[&LibraryConfigName::DdService],
+            "service_my_cluster"
+        );
+
+        assert_eq!(
+            configurator
+                .get_config_from_bytes(Configurator::to_yaml(&[]).as_bytes(), process_info())
+                .unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_apply_remote_fleet_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("local.yaml");
+        std::fs::write(
+            &local,
+            b"
+rules:
+- selectors:
+  - origin: language
+    matches: [\"java\"]
+    operator: equals
+  configuration:
+    DD_SERVICE: local_service
+    DD_ENV: local_env
+",
+        )
+        .unwrap();
+        let fleet_yaml = b"
+rules:
+- selectors:
+  - origin: language
+    matches: [\"java\"]
+    operator: equals
+  configuration:
+    DD_ENV: fleet_env
+    DD_VERSION: fleet_version
+";
+        let process_info = || ProcessInfo::<&[u8]> {
+            args: &[],
+            envp: &[],
+            language: b"java",
+        };
+
+        let configurator = Configurator::new(false);
+        let config = configurator
+            .apply_remote_fleet_config(&local, fleet_yaml, process_info())
+            .unwrap();
+        assert_eq!(
+            config,
+            vec![
+                LibraryConfig {
+                    name: LibraryConfigName::DdService,
+                    value: "local_service".to_string(),
+                    source: LibraryConfigSource::LocalStableConfig,
+                    origin_path: Some(local.clone()),
+                    rule_index: Some(0),
+                },
+                LibraryConfig {
+                    name: LibraryConfigName::DdEnv,
+                    value: "fleet_env".to_string(),
+                    source: LibraryConfigSource::FleetStableConfig,
+                    origin_path: None,
+                    rule_index: Some(0),
+                },
+                LibraryConfig {
+                    name: LibraryConfigName::DdVersion,
+                    value: "fleet_version".to_string(),
+                    source: LibraryConfigSource::FleetStableConfig,
+                    origin_path: None,
+                    rule_index: Some(0),
+                },
+            ]
+        );
+
+        assert!(configurator
+            .apply_remote_fleet_config(&local, b"rules: [ this is not valid", process_info())
+            .is_err());
+    }
+
+    #[test]
+    fn test_best_effort() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("local.yaml");
+        let fleet = dir.path().join("fleet.yaml");
+        std::fs::write(
+            &local,
+            b"
+rules:
+- selectors:
+  - origin: language
+    matches: [\"java\"]
+    operator: equals
+  configuration:
+    DD_SERVICE: local_service
+",
+        )
+        .unwrap();
+        std::fs::write(&fleet, b"rules: [ this is not valid").unwrap();
+        let process_info = || ProcessInfo::<&[u8]> {
+            args: &[],
+            envp: &[],
+            language: b"java",
+        };
+
+        let config = Configurator::new_best_effort(false)
+            .resolve_effective(&local, &fleet, &HashMap::new(), process_info())
+            .unwrap();
+        assert_eq!(
+            config,
+            vec![LibraryConfig {
+                name: LibraryConfigName::DdService,
+                value: "local_service".to_string(),
+                source: LibraryConfigSource::LocalStableConfig,
+                origin_path: Some(local.clone()),
+                rule_index: Some(0),
+            }]
+        );
+        assert!(Configurator::new_best_effort(false)
+            .get_config_from_file(&fleet, process_info())
+            .unwrap()
+            .is_empty());
+
+        assert!(Configurator::new(false)
+            .resolve_effective(&local, &fleet, &HashMap::new(), process_info())
+            .is_err());
+        assert!(Configurator::new(false)
+            .get_config_from_file(&fleet, process_info())
+            .is_err());
+    }
+
+    #[test]
+    fn test_get_config_from_parts() {
+        let stable_config = b"
+rules:
+- selectors:
+  - origin: language
+    matches: [\"java\"]
+    operator: equals
+  - origin: environment_variables
+    key: \"APP_NAME\"
+    operator: exists
+  configuration:
+    DD_SERVICE: \"{{ environment_variables[APP_NAME] }}\"
+";
+        let expected = vec![LibraryConfig {
+            name: LibraryConfigName::DdService,
+            value: "child".to_string(),
+            source: LibraryConfigSource::LocalStableConfig,
+            origin_path: None,
+            rule_index: Some(0),
+        }];
+        let configurator = Configurator::new(false);
+
+        let args: Vec<Vec<u8>> = vec![
+            b"/usr/bin/java".to_vec(),
+            b"-jar".to_vec(),
+            b"app.jar".to_vec(),
+        ];
+        let envp: Vec<Vec<u8>> = vec![b"ENV=VAR".to_vec(), b"APP_NAME=child".to_vec()];
+        let process_info = ProcessInfo::from_parts(&args, &envp, b"java".to_vec());
+        let config = configurator
+            .get_config_from_bytes(stable_config, process_info)
+            .unwrap();
+        assert_eq!(config, expected);
+
+        let process_info = ProcessInfo::from_str_slices(
+            &["/usr/bin/java", "-jar", "app.jar"],
+            &["ENV=VAR", "APP_NAME=child"],
+            "java",
+        );
+        let config = configurator
+            .get_config_from_bytes(stable_config, process_info)
+            .unwrap();
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn test_validate_templates() {
+        let configurator = Configurator::new(false);
+        let warnings = configurator
+            .validate_templates(
+                b"
+rules:
+- selectors:
+  - origin: language
+    matches: [\"java\"]
+    operator: equals
+  configuration:
+    DD_SERVICE: \"{{ language }}_{{ tags[cluster_name] }}\"
+- selectors: []
+  configuration:
+    DD_SERVICE: \"{{ process_name }}\"
+    DD_ENV: \"{{ environment_variables }}\"
+    DD_VERSION: \"{{ tags[version] \"
+",
+            )
+            .unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                TemplateWarning {
+                    rule_index: 1,
+                    name: LibraryConfigName::DdService,
+                    kind: TemplateWarningKind::UnknownVariable("process_name".to_string()),
+                },
+                TemplateWarning {
+                    rule_index: 1,
+                    name: LibraryConfigName::DdEnv,
+                    kind: TemplateWarningKind::Malformed("environment_variables".to_string()),
+                },
+                TemplateWarning {
+                    rule_index: 1,
+                    name: LibraryConfigName::DdVersion,
+                    kind: TemplateWarningKind::Malformed(" tags[version] ".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validated_templates_render() {
+        let process_info = ProcessInfo::<&[u8]> {
+            args: &[b"-Dkey=arg"],
+            envp: &[b"KEY=env"],
+            language: b"java",
+        };
+        let tags = HashMap::from([("key".to_string(), "tag".to_string())]);
+        let matcher = Matcher::new(&process_info, &tags);
+        let configurator = Configurator::new(false);
+
+        for (template, valid) in [
+            ("{{ language }}", true),
+            ("{{tags[key]}}", true),
+            ("{{ tags[ key ] }}", true),
+            ("{{ environment_variables[KEY] }}", true),
+            ("a_{{ process_arguments[-Dkey] }}_b", true),
+            ("{{ tags [key] }}", false),
+            ("{{ tags[key }}", false),
+            ("{{ tags[] }}", false),
+            ("{{ process_name }}", false),
+            ("{{ tags[key] ", false),
+        ] {
+            let warnings = configurator
+                .validate_templates(
+                    format!("rules:\n- selectors: []\n  configuration:\n    DD_SERVICE: \"{template}\"\n")
+                        .as_bytes(),
+                )
+                .unwrap();
+            assert_eq!(warnings.is_empty(), valid, "{template}: {warnings:?}");
+            if valid {
+                let rendered = matcher.template_config(template).unwrap();
+                assert!(
+                    !rendered.contains("UNDEFINED"),
+                    "{template} validated but rendered as {rendered}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_explain_config() {
+        let process_info: ProcessInfo<'_, &[u8]> = ProcessInfo::<&[u8]> {
+            args: &[b"-jar", b"HelloWorld.jar"],
+            envp: &[b"ENV=VAR"],
+            language: b"java",
+        };
+        let configurator = Configurator::new(false);
+        let explanations = configurator
+            .explain_config(
+                b"
+rules:
+- selectors:
+  - origin: language
+    matches: [\"java\"]
+    operator: equals
+  - origin: environment_variables
+    key: \"ENV\"
+    matches: [\"OTHER\"]
+    operator: equals
+  configuration:
+    DD_SERVICE: my-service
+- selectors:
+  - origin: process_arguments
+    matches: [\"-jar\"]
+    operator: equals
+  configuration:
+    DD_ENV: my-env
+- selectors: []
+  configuration:
+    DD_VERSION: never-evaluated
+",
+                process_info,
+            )
+            .unwrap();
+        assert_eq!(
+            explanations,
+            vec![
+                RuleExplanation {
+                    rule_index: 0,
+                    failed_selector: Some(FailedSelector {
+                        index: 1,
+                        origin: "environment_variables",
+                        key: Some("ENV".to_string()),
+                        operator: "equals",
+                    }),
+                },
+                RuleExplanation {
+                    rule_index: 1,
+                    failed_selector: None,
+                },
+            ]
+        );
+    }
 }
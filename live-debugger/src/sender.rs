@@ -67,7 +67,7 @@ impl Config {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(C)]
 pub enum DebuggerType {
     Diagnostics,
@@ -171,7 +171,7 @@ impl PayloadSender {
         );
 
         let future = Client::builder()
-            .build(Connector::default())
+            .build(Connector::for_endpoint(endpoint))
             .request(req.body(body)?);
         Ok(PayloadSender {
             future: SenderFuture::Outstanding(future),
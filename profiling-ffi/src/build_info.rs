@@ -0,0 +1,72 @@
+// Copyright 2025-Present Datadog, Inc. https://www.datadoghq.com/
+// SPDX-License-Identifier: Apache-2.0
+
+use ddcommon_ffi::CharSlice;
+use std::sync::OnceLock;
+
+fn enabled_features() -> &'static str {
+    const FEATURES: &[(&str, bool)] = &[
+        ("ddtelemetry-ffi", cfg!(feature = "ddtelemetry-ffi")),
+        ("symbolizer", cfg!(feature = "symbolizer")),
+        ("data-pipeline-ffi", cfg!(feature = "data-pipeline-ffi")),
+        ("crashtracker-ffi", cfg!(feature = "crashtracker-ffi")),
+        (
+            "crashtracker-collector",
+            cfg!(feature = "crashtracker-collector"),
+        ),
+        (
+            "crashtracker-receiver",
+            cfg!(feature = "crashtracker-receiver"),
+        ),
+        ("demangler", cfg!(feature = "demangler")),
+        (
+            "datadog-library-config-ffi",
+            cfg!(feature = "datadog-library-config-ffi"),
+        ),
+    ];
+    static ENABLED: OnceLock<String> = OnceLock::new();
+    ENABLED.get_or_init(|| {
+        join_features(
+            &ddcommon::build_info::filter_enabled(FEATURES),
+            ddcommon::build_info::enabled_features(),
+        )
+    })
+}
+
+fn join_features(own: &[&str], common: &[&str]) -> String {
+    own.iter()
+        .chain(common)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Returns the comma-separated list of features this library was compiled with, e.g.
+/// "crashtracker-ffi,data-pipeline-ffi". The returned string is static and must not be freed.
+#[no_mangle]
+#[must_use]
+pub extern "C" fn ddog_enabled_features() -> CharSlice<'static> {
+    CharSlice::from(enabled_features())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_features() {
+        assert_eq!(join_features(&[], &[]), "");
+        assert_eq!(join_features(&["a"], &[]), "a");
+        assert_eq!(join_features(&[], &["b"]), "b");
+        assert_eq!(join_features(&["a", "b"], &["c"]), "a,b,c");
+    }
+
+    #[test]
+    fn test_enabled_features_includes_ddcommon() {
+        let features = ddog_enabled_features().to_utf8_lossy().into_owned();
+        let features: Vec<_> = features.split(',').filter(|f| !f.is_empty()).collect();
+        for feature in ddcommon::build_info::enabled_features() {
+            assert!(features.contains(feature), "{feature} missing");
+        }
+    }
+}
@@ -4,6 +4,7 @@
 #[cfg(all(feature = "symbolizer", not(target_os = "windows")))]
 pub use symbolizer_ffi::*;
 
+mod build_info;
 mod exporter;
 mod profiles;
 mod string_storage;
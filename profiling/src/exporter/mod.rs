@@ -141,7 +141,7 @@ impl ProfileExporter {
         V: Into<Cow<'static, str>>,
     {
         Ok(Self {
-            exporter: Exporter::new()?,
+            exporter: Exporter::for_endpoint(&endpoint)?,
             endpoint,
             family: family.into(),
             profiling_library_name: profiling_library_name.into(),
@@ -181,34 +181,9 @@ impl ProfileExporter {
             tags_profiler.push(',');
         }
 
-        if let Some(aas_metadata) = azure_app_services::get_metadata() {
-            let aas_tags = [
-                ("aas.resource.id", aas_metadata.get_resource_id()),
-                (
-                    "aas.environment.extension_version",
-                    aas_metadata.get_extension_version(),
-                ),
-                (
-                    "aas.environment.instance_id",
-                    aas_metadata.get_instance_id(),
-                ),
-                (
-                    "aas.environment.instance_name",
-                    aas_metadata.get_instance_name(),
-                ),
-                ("aas.environment.os", aas_metadata.get_operating_system()),
-                ("aas.resource.group", aas_metadata.get_resource_group()),
-                ("aas.site.name", aas_metadata.get_site_name()),
-                ("aas.site.kind", aas_metadata.get_site_kind()),
-                ("aas.site.type", aas_metadata.get_site_type()),
-                ("aas.subscription.id", aas_metadata.get_subscription_id()),
-            ];
-            aas_tags.into_iter().for_each(|(name, value)| {
-                if let Ok(tag) = Tag::new(name, value) {
-                    tags_profiler.push_str(tag.as_ref());
-                    tags_profiler.push(',');
-                }
-            });
+        for tag in azure_app_services::aas_tags() {
+            tags_profiler.push_str(tag.as_ref());
+            tags_profiler.push(',');
         }
 
         tags_profiler.pop(); // clean up the trailing comma
@@ -306,10 +281,20 @@ impl ProfileExporter {
 impl Exporter {
     /// Creates a new Exporter, initializing the TLS stack.
     pub fn new() -> anyhow::Result<Self> {
+        Self::with_connector(connector::Connector::default())
+    }
+
+    /// Same as [`Exporter::new`], but dials the [`Endpoint::resolved_addr`] of the endpoint if
+    /// set. The exporter must then only be used to send to this endpoint.
+    pub fn for_endpoint(endpoint: &Endpoint) -> anyhow::Result<Self> {
+        Self::with_connector(connector::Connector::for_endpoint(endpoint))
+    }
+
+    fn with_connector(connector: connector::Connector) -> anyhow::Result<Self> {
         // Set idle to 0, which prevents the pipe being broken every 2nd request
         let client = hyper::Client::builder()
             .pool_max_idle_per_host(0)
-            .build(connector::Connector::default());
+            .build(connector);
         let runtime = tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()?;
@@ -43,6 +43,7 @@ async fn main() {
                 api_key: None,
                 timeout_ms: 5000, // custom timeout, defaults to 3 seconds
                 test_token: None,
+                resolved_addr: None,
             },
             products: vec![ApmTracing],
             capabilities: vec![],
@@ -314,7 +314,7 @@ impl<S: FileStorage> ConfigFetcher<S> {
         let response = tokio::time::timeout(
             Duration::from_millis(self.state.endpoint.timeout_ms),
             Client::builder()
-                .build(connector::Connector::default())
+                .build(connector::Connector::for_endpoint(&self.state.endpoint))
                 .request(req),
         )
         .await
@@ -514,12 +514,7 @@ fn get_product_endpoint(subdomain: &str, endpoint: &Endpoint) -> Endpoint {
         );
     }
     parts.path_and_query = Some(PathAndQuery::from_static("/v0.7/config"));
-    Endpoint {
-        url: hyper::Uri::from_parts(parts).unwrap(),
-        api_key: endpoint.api_key.clone(),
-        test_token: endpoint.test_token.clone(),
-        ..*endpoint
-    }
+    endpoint.with_url(hyper::Uri::from_parts(parts).unwrap())
 }
 
 #[cfg(test)]
@@ -7,7 +7,9 @@ use crate::fetch::{
 };
 use crate::file_change_tracker::{Change, ChangeTracker, FilePath, UpdatedFiles};
 use crate::{RemoteConfigPath, Target};
+use futures_util::Stream;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Simple implementation
 pub struct SingleFetcher<S: FileStorage> {
@@ -93,6 +95,31 @@ where
         })
     }
 
+    /// Turns the fetcher into a stream of changes, polling every `poll_interval`. Only non-empty
+    /// batches of changes and fetch errors are yielded, polling continues after an error. The
+    /// first poll happens immediately.
+    pub fn into_stream<R>(
+        self,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = anyhow::Result<Vec<Change<Arc<S::StoredFile>, R>>>>
+    where
+        S: UpdatedFiles<S::StoredFile, R>,
+    {
+        futures_util::stream::unfold((self, true), move |(mut fetcher, first)| async move {
+            if !first {
+                tokio::time::sleep(poll_interval).await;
+            }
+            loop {
+                match fetcher.fetch_changes().await {
+                    Ok(changes) if changes.is_empty() => {
+                        tokio::time::sleep(poll_interval).await;
+                    }
+                    result => return Some((result, (fetcher, false))),
+                }
+            }
+        })
+    }
+
     pub fn get_client_id(&self) -> &String {
         self.fetcher.get_client_id()
     }
@@ -102,3 +129,44 @@ where
         self.fetcher.set_config_state(file.path(), state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::fetcher::tests::*;
+    use crate::fetch::test_server::RemoteConfigServer;
+    use crate::file_storage::SimpleFileStorage;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_changes_stream() {
+        let server = RemoteConfigServer::spawn();
+        server.files.lock().unwrap().insert(
+            PATH_FIRST.clone(),
+            (vec![DUMMY_TARGET.clone()], 1, "v1".to_string()),
+        );
+
+        let fetcher = SingleChangesFetcher::new(
+            SimpleFileStorage::default(),
+            (**DUMMY_TARGET).clone(),
+            "3b43524b-a70c-45dc-921d-34504e50c5eb".to_string(),
+            server.dummy_invariants(),
+        );
+        let mut stream = Box::pin(fetcher.into_stream::<Vec<u8>>(Duration::from_millis(10)));
+
+        let changes = stream.next().await.unwrap().unwrap();
+        assert_eq!(changes.len(), 1);
+        let Change::Add(file) = &changes[0] else {
+            panic!("Expected the file to be added");
+        };
+        assert_eq!(file.path(), &*PATH_FIRST);
+        assert_eq!(&*file.contents(), b"v1");
+
+        // Polls without changes are not yielded
+        server.files.lock().unwrap().remove(&*PATH_FIRST);
+        let changes = stream.next().await.unwrap().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(&changes[0], Change::Remove(file) if file.path() == &*PATH_FIRST));
+    }
+}
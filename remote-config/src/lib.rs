@@ -8,7 +8,7 @@ mod parse;
 mod path;
 mod targets;
 
-use ddcommon::tag::Tag;
+use ddcommon::tag::{parse_tags, Tag};
 pub use parse::*;
 pub use path::*;
 use serde::{Deserialize, Serialize};
@@ -21,6 +21,25 @@ pub struct Target {
     pub tags: Vec<Tag>,
 }
 
+impl Target {
+    /// Builds a target from the standard `DD_SERVICE`, `DD_ENV`, `DD_VERSION` and `DD_TAGS`
+    /// environment variables. Missing variables are left empty and invalid tags are skipped.
+    pub fn from_env() -> Target {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Same as [`Target::from_env`], looking the variables up with `get_var`.
+    fn from_vars(get_var: impl Fn(&str) -> Option<String>) -> Target {
+        let var = |name| get_var(name).unwrap_or_default();
+        Target {
+            service: var("DD_SERVICE"),
+            env: var("DD_ENV"),
+            app_version: var("DD_VERSION"),
+            tags: parse_tags(&var("DD_TAGS")).0,
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -56,3 +75,45 @@ pub enum RemoteConfigCapabilities {
     ApmTracingSampleRules = 29,
     CsmActivation = 30,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_target_from_env() {
+        let vars = HashMap::from([
+            ("DD_SERVICE", "my-service"),
+            ("DD_ENV", "staging"),
+            ("DD_VERSION", "1.2.3"),
+            ("DD_TAGS", "team:apm,region:us-east-1 :invalid"),
+        ]);
+
+        let target = Target::from_vars(|name| vars.get(name).map(|v| v.to_string()));
+
+        assert_eq!(
+            target,
+            Target {
+                service: "my-service".to_string(),
+                env: "staging".to_string(),
+                app_version: "1.2.3".to_string(),
+                tags: vec![
+                    Tag::new("team", "apm").unwrap(),
+                    Tag::new("region", "us-east-1").unwrap(),
+                ],
+            }
+        );
+
+        let target = Target::from_vars(|_| None);
+        assert_eq!(
+            target,
+            Target {
+                service: String::new(),
+                env: String::new(),
+                app_version: String::new(),
+                tags: vec![],
+            }
+        );
+    }
+}
@@ -17,27 +17,33 @@ use datadog_sidecar::one_way_shared_memory::{OneWayShmReader, ReaderOpener};
 use datadog_sidecar::service::agent_info::AgentInfoReader;
 use datadog_sidecar::service::{
     blocking::{self, SidecarTransport},
-    InstanceId, QueueId, RuntimeMetadata, SerializedTracerHeaderTags, SessionConfig, SidecarAction,
+    BackpressureMode, InstanceId, QueueId, RuntimeMetadata, SerializedTracerHeaderTags,
+    SessionConfig, SidecarAction, ThroughputStats,
 };
 use datadog_sidecar::shm_remote_config::{path_for_remote_config, RemoteConfigReader};
+use datadog_sidecar::tracer;
 use ddcommon::tag::Tag;
 use ddcommon::Endpoint;
 use ddcommon_ffi as ffi;
 use ddcommon_ffi::{CharSlice, MaybeError};
 use ddtelemetry::{
     data::{self, Dependency, Integration},
-    worker::{LifecycleAction, TelemetryActions},
+    worker::{LifecycleAction, LogIdentifier, TelemetryActions},
 };
 use ddtelemetry_ffi::try_c;
 use dogstatsd_client::DogStatsDActionOwned;
 use ffi::slice::AsBytes;
 use libc::c_char;
+use std::alloc::{handle_alloc_error, Layout};
+use std::collections::hash_map::DefaultHasher;
 use std::ffi::{c_void, CStr, CString};
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 #[cfg(unix)]
 use std::os::unix::prelude::FromRawFd;
 #[cfg(windows)]
 use std::os::windows::io::{FromRawHandle, RawHandle};
+use std::ptr::NonNull;
 use std::slice;
 use std::sync::Arc;
 use std::time::Duration;
@@ -106,6 +112,12 @@ pub extern "C" fn ddog_alloc_anon_shm_handle_named(
     MaybeError::None
 }
 
+/// Returns the capacity of the shared memory handle, without mapping it.
+#[no_mangle]
+pub extern "C" fn ddog_shm_handle_size(handle: &ShmHandle) -> usize {
+    handle.size()
+}
+
 #[no_mangle]
 pub extern "C" fn ddog_map_shm(
     handle: Box<ShmHandle>,
@@ -113,7 +125,11 @@ pub extern "C" fn ddog_map_shm(
     pointer: &mut *mut c_void,
     size: &mut usize,
 ) -> MaybeError {
-    let mut memory_mapped = try_c!(handle.map());
+    let mut memory_mapped = match handle.map() {
+        Ok(mapped) => mapped,
+        // Use the Display impl, it carries the errno name, e.g. "mmap failed: ENOMEM (...)"
+        Err(e) => return MaybeError::Some(ddcommon_ffi::Error::from(e.to_string())),
+    };
     let slice = memory_mapped.as_slice_mut();
     *pointer = slice as *mut [u8] as *mut c_void;
     *size = slice.len();
@@ -208,6 +224,29 @@ pub extern "C" fn ddog_agent_remote_config_reader_drop(_: Box<AgentRemoteConfigR
 pub extern "C" fn ddog_agent_remote_config_writer_drop(_: Box<AgentRemoteConfigWriter<ShmHandle>>) {
 }
 
+unsafe fn remote_config_invariants(
+    language: &ffi::CharSlice,
+    tracer_version: &ffi::CharSlice,
+    endpoint: &Endpoint,
+    remote_config_products: *const RemoteConfigProduct,
+    remote_config_products_count: usize,
+    remote_config_capabilities: *const RemoteConfigCapabilities,
+    remote_config_capabilities_count: usize,
+) -> ConfigInvariants {
+    ConfigInvariants {
+        language: language.to_utf8_lossy().into(),
+        tracer_version: tracer_version.to_utf8_lossy().into(),
+        endpoint: endpoint.clone(),
+        products: slice::from_raw_parts(remote_config_products, remote_config_products_count)
+            .to_vec(),
+        capabilities: slice::from_raw_parts(
+            remote_config_capabilities,
+            remote_config_capabilities_count,
+        )
+        .to_vec(),
+    }
+}
+
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
 pub unsafe extern "C" fn ddog_remote_config_reader_for_endpoint<'a>(
@@ -224,18 +263,15 @@ pub unsafe extern "C" fn ddog_remote_config_reader_for_endpoint<'a>(
     remote_config_capabilities_count: usize,
 ) -> Box<RemoteConfigReader> {
     Box::new(RemoteConfigReader::new(
-        &ConfigInvariants {
-            language: language.to_utf8_lossy().into(),
-            tracer_version: tracer_version.to_utf8_lossy().into(),
-            endpoint: endpoint.clone(),
-            products: slice::from_raw_parts(remote_config_products, remote_config_products_count)
-                .to_vec(),
-            capabilities: slice::from_raw_parts(
-                remote_config_capabilities,
-                remote_config_capabilities_count,
-            )
-            .to_vec(),
-        },
+        &remote_config_invariants(
+            language,
+            tracer_version,
+            endpoint,
+            remote_config_products,
+            remote_config_products_count,
+            remote_config_capabilities,
+            remote_config_capabilities_count,
+        ),
         &Arc::new(Target {
             service: service_name.to_utf8_lossy().into(),
             env: env_name.to_utf8_lossy().into(),
@@ -245,6 +281,33 @@ pub unsafe extern "C" fn ddog_remote_config_reader_for_endpoint<'a>(
     ))
 }
 
+/// Same as ddog_remote_config_reader_for_endpoint, with the service, env, version and tags read
+/// from the DD_SERVICE, DD_ENV, DD_VERSION and DD_TAGS environment variables.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_remote_config_reader_for_endpoint_from_env<'a>(
+    language: &ffi::CharSlice<'a>,
+    tracer_version: &ffi::CharSlice<'a>,
+    endpoint: &Endpoint,
+    remote_config_products: *const RemoteConfigProduct,
+    remote_config_products_count: usize,
+    remote_config_capabilities: *const RemoteConfigCapabilities,
+    remote_config_capabilities_count: usize,
+) -> Box<RemoteConfigReader> {
+    Box::new(RemoteConfigReader::new(
+        &remote_config_invariants(
+            language,
+            tracer_version,
+            endpoint,
+            remote_config_products,
+            remote_config_products_count,
+            remote_config_capabilities,
+            remote_config_capabilities_count,
+        ),
+        &Arc::new(Target::from_env()),
+    ))
+}
+
 /// # Safety
 /// Argument should point to a valid C string.
 #[no_mangle]
@@ -280,6 +343,60 @@ pub extern "C" fn ddog_remote_config_read<'a>(
     new
 }
 
+/// Copies a string into a buffer allocated with `malloc`, to be freed by the C caller.
+fn malloc_char_slice(str: &str) -> ffi::CharSlice<'static> {
+    let size = str.len();
+    // never allocate zero bytes, malloc may return null then
+    let alloc_size = size.max(1);
+    unsafe {
+        let malloced = libc::malloc(alloc_size) as *mut u8;
+        if malloced.is_null() {
+            handle_alloc_error(Layout::array::<u8>(alloc_size).unwrap());
+        }
+        let buf = slice::from_raw_parts_mut(malloced, size);
+        buf.copy_from_slice(str.as_bytes());
+        ffi::CharSlice::from_raw_parts(malloced as *mut c_char, size)
+    }
+}
+
+/// Describes the path, invariants and target of a remote config reader as JSON, for debugging.
+///
+/// The returned string must be freed by the caller.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_remote_config_reader_describe(
+    reader: &RemoteConfigReader,
+) -> ffi::CharSlice<'static> {
+    malloc_char_slice(&reader.describe())
+}
+
+/// Returns the comma-joined ids of the configurations last read by `ddog_remote_config_read`, to
+/// be reported in the remote config client state.
+///
+/// The returned string must be freed by the caller.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_remote_config_reader_applied_ids(
+    reader: &RemoteConfigReader,
+) -> ffi::CharSlice<'static> {
+    malloc_char_slice(&reader.applied_config_ids().join(","))
+}
+
+/// Forgets the configuration files read by `ddog_remote_config_read` which are no longer present in
+/// the contents it last returned, i.e. the ones removed server-side since.
+/// Returns the number of configuration files pruned.
+#[no_mangle]
+pub extern "C" fn ddog_remote_config_reader_prune(reader: &mut RemoteConfigReader) -> usize {
+    reader.prune_stale()
+}
+
+/// Returns the number of configuration files read by `ddog_remote_config_read` and not pruned
+/// yet.
+#[no_mangle]
+pub extern "C" fn ddog_remote_config_reader_len(reader: &RemoteConfigReader) -> usize {
+    reader.len()
+}
+
 #[no_mangle]
 pub extern "C" fn ddog_remote_config_reader_drop(_: Box<RemoteConfigReader>) {}
 
@@ -298,6 +415,50 @@ pub extern "C" fn ddog_sidecar_connect(connection: &mut *mut SidecarTransport) -
     MaybeError::None
 }
 
+/// Connects to a newly spawned sidecar instead of an already running one.
+///
+/// With `kill_existing`, the sidecar currently listening for the configured ipc mode is asked to
+/// shut down first. Otherwise the new sidecar is dedicated to the current process, leaving a
+/// shared sidecar untouched, and an error is returned if one is already running for it.
+///
+/// # Safety
+/// Caller must ensure the process is safe to fork, at the time when this method is called
+#[no_mangle]
+pub extern "C" fn ddog_sidecar_connect_fresh(
+    connection: &mut *mut SidecarTransport,
+    kill_existing: bool,
+) -> MaybeError {
+    let cfg = datadog_sidecar::config::Config::get();
+
+    let stream = Box::new(try_c!(datadog_sidecar::start_fresh_sidecar(
+        cfg,
+        kill_existing
+    )));
+    *connection = Box::into_raw(stream);
+
+    MaybeError::None
+}
+
+/// Connects to the sidecar like `ddog_sidecar_connect`, but considers the transport closed once
+/// no request was sent for `idle_timeout_ms` milliseconds. Use `ddog_sidecar_reconnect` to
+/// re-establish it.
+///
+/// # Safety
+/// Caller must ensure the process is safe to fork, at the time when this method is called
+#[no_mangle]
+pub extern "C" fn ddog_sidecar_connect_with_idle_timeout(
+    connection: &mut *mut SidecarTransport,
+    idle_timeout_ms: u64,
+) -> MaybeError {
+    let cfg = datadog_sidecar::config::Config::get();
+
+    let mut stream = Box::new(try_c!(datadog_sidecar::start_or_connect_to_sidecar(cfg)));
+    stream.set_idle_timeout(Some(Duration::from_millis(idle_timeout_ms)));
+    *connection = Box::into_raw(stream);
+
+    MaybeError::None
+}
+
 #[no_mangle]
 pub extern "C" fn ddog_sidecar_ping(transport: &mut Box<SidecarTransport>) -> MaybeError {
     try_c!(blocking::ping(transport));
@@ -305,6 +466,27 @@ pub extern "C" fn ddog_sidecar_ping(transport: &mut Box<SidecarTransport>) -> Ma
     MaybeError::None
 }
 
+/// Pings the sidecar, failing with a timeout error if it didn't respond within `timeout_ms`
+/// milliseconds, e.g. so that a hung sidecar doesn't block the caller forever. After a timeout, the
+/// transport must be re-established via `ddog_sidecar_reconnect`.
+#[no_mangle]
+pub extern "C" fn ddog_sidecar_ping_timeout(
+    transport: &mut Box<SidecarTransport>,
+    timeout_ms: u64,
+) -> MaybeError {
+    if timeout_ms == 0 {
+        return MaybeError::Some(ddcommon_ffi::Error::from(
+            "the ping timeout must not be zero".to_string(),
+        ));
+    }
+    try_c!(blocking::ping_timeout(
+        transport,
+        Duration::from_millis(timeout_ms)
+    ));
+
+    MaybeError::None
+}
+
 #[no_mangle]
 pub extern "C" fn ddog_sidecar_flush_traces(transport: &mut Box<SidecarTransport>) -> MaybeError {
     try_c!(blocking::flush_traces(transport));
@@ -336,6 +518,14 @@ pub unsafe extern "C" fn ddog_sidecar_queueId_generate() -> QueueId {
     QueueId::new_unique()
 }
 
+/// Derives a `QueueId` from a stable seed: the same seed yields the same `QueueId`, e.g. to resume
+/// a logical queue after a restart.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_queueId_from_seed(seed: ffi::CharSlice) -> QueueId {
+    QueueId::from_seed(&seed.to_utf8_lossy())
+}
+
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
 pub unsafe extern "C" fn ddog_sidecar_runtimeMeta_build(
@@ -411,6 +601,25 @@ pub unsafe extern "C" fn ddog_sidecar_telemetry_addDependency(
     MaybeError::None
 }
 
+/// Clears the telemetry accumulated for an application, i.e. its dependencies, integrations,
+/// configurations and logs, without ending its session, e.g. when the runtime re-initializes.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_telemetry_reset(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+    queue_id: &QueueId,
+) -> MaybeError {
+    try_c!(blocking::enqueue_actions(
+        transport,
+        instance_id,
+        queue_id,
+        vec![SidecarAction::Telemetry(TelemetryActions::Reset)],
+    ));
+
+    MaybeError::None
+}
+
 /// Reports an integration to the telemetry.
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
@@ -443,6 +652,47 @@ pub unsafe extern "C" fn ddog_sidecar_telemetry_addIntegration(
     MaybeError::None
 }
 
+/// Reports a log to the telemetry. Logs with the same identifier are deduplicated and counted.
+/// `is_crash` flags the log as a crash report, e.g. for the crash info of the crashtracker, which
+/// the backend handles separately. An empty `stack_trace` means none.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn ddog_sidecar_telemetry_addLog(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+    queue_id: &QueueId,
+    identifier: ffi::CharSlice,
+    level: data::LogLevel,
+    message: ffi::CharSlice,
+    stack_trace: ffi::CharSlice,
+    is_crash: bool,
+) -> MaybeError {
+    let mut hasher = DefaultHasher::new();
+    identifier.as_bytes().hash(&mut hasher);
+    // A crash report must not be merged into a regular log with the same identifier
+    is_crash.hash(&mut hasher);
+
+    let mut log = data::Log::new(message.to_utf8_lossy().into_owned(), level);
+    log.stack_trace = (!stack_trace.is_empty()).then(|| stack_trace.to_utf8_lossy().into_owned());
+    log.is_crash = is_crash;
+    let log = TelemetryActions::AddLog((
+        LogIdentifier {
+            indentifier: hasher.finish(),
+        },
+        log,
+    ));
+
+    try_c!(blocking::enqueue_actions(
+        transport,
+        instance_id,
+        queue_id,
+        vec![SidecarAction::Telemetry(log)],
+    ));
+
+    MaybeError::None
+}
+
 /// Registers a service and flushes any queued actions.
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
@@ -509,16 +759,134 @@ pub unsafe extern "C" fn ddog_sidecar_telemetry_flush(
     MaybeError::None
 }
 
+/// Flushes only the telemetry metric aggregation buckets, without flushing the other telemetry
+/// data.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_telemetry_flush_metrics(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+    queue_id: &QueueId,
+) -> MaybeError {
+    try_c!(blocking::enqueue_actions(
+        transport,
+        instance_id,
+        queue_id,
+        vec![SidecarAction::Telemetry(TelemetryActions::Lifecycle(
+            LifecycleAction::FlushMetricAggr
+        ))],
+    ));
+
+    MaybeError::None
+}
+
 /// Returns whether the sidecar transport is closed or not.
 #[no_mangle]
 pub extern "C" fn ddog_sidecar_is_closed(transport: &mut Box<SidecarTransport>) -> bool {
     transport.is_closed()
 }
 
+/// Selects how the sidecar should emit its logs for a session.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LogMethodKind {
+    /// Use the method configured via the environment (`_DD_DEBUG_SIDECAR_LOG_METHOD`).
+    Default,
+    Stdout,
+    Stderr,
+    /// Log to the file given by `log_path`. Falls back to `Default` if the path is empty.
+    File,
+}
+
+impl LogMethodKind {
+    pub fn to_log_method(self, log_path: &str) -> LogMethod {
+        match self {
+            LogMethodKind::Default => config::FromEnv::log_method(),
+            LogMethodKind::Stdout => LogMethod::Stdout,
+            LogMethodKind::Stderr => LogMethod::Stderr,
+            LogMethodKind::File if log_path.is_empty() => config::FromEnv::log_method(),
+            LogMethodKind::File => LogMethod::File(log_path.into()),
+        }
+    }
+}
+
+/// Session options which are not part of [`ddog_sidecar_session_set_config`], see
+/// [`ddog_sidecar_session_set_config_v2`].
+#[repr(C)]
+pub struct SessionConfigOptions<'a> {
+    /// How the sidecar emits its logs for the session, `log_path` being the file for `File`.
+    pub log_method: LogMethodKind,
+    /// Tag every dogstatsd metric with the `runtime-id` of the instance emitting it.
+    pub dogstatsd_runtime_id_tag: bool,
+    /// Traces sent while the trace queue is full are dropped if 0, otherwise the sidecar waits
+    /// up to that timeout for the queue to be flushed.
+    pub trace_backpressure_timeout_millis: u32,
+    /// Points of the telemetry metrics named here are also sent to the dogstatsd endpoint.
+    pub telemetry_dogstatsd_mirror: ffi::Slice<'a, ffi::CharSlice<'a>>,
+}
+
+impl Default for SessionConfigOptions<'_> {
+    fn default() -> Self {
+        SessionConfigOptions {
+            log_method: LogMethodKind::File,
+            dogstatsd_runtime_id_tag: false,
+            trace_backpressure_timeout_millis: 0,
+            telemetry_dogstatsd_mirror: ffi::Slice::empty(),
+        }
+    }
+}
+
 /// Sets the configuration for a session.
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
 pub unsafe extern "C" fn ddog_sidecar_session_set_config(
+    transport: &mut Box<SidecarTransport>,
+    session_id: ffi::CharSlice,
+    agent_endpoint: &Endpoint,
+    dogstatsd_endpoint: &Endpoint,
+    language: ffi::CharSlice,
+    tracer_version: ffi::CharSlice,
+    flush_interval_milliseconds: u32,
+    remote_config_poll_interval_millis: u32,
+    telemetry_heartbeat_interval_millis: u32,
+    force_flush_size: usize,
+    force_drop_size: usize,
+    log_level: ffi::CharSlice,
+    log_path: ffi::CharSlice,
+    remote_config_notify_function: *mut c_void,
+    remote_config_products: *const RemoteConfigProduct,
+    remote_config_products_count: usize,
+    remote_config_capabilities: *const RemoteConfigCapabilities,
+    remote_config_capabilities_count: usize,
+) -> MaybeError {
+    ddog_sidecar_session_set_config_v2(
+        transport,
+        session_id,
+        agent_endpoint,
+        dogstatsd_endpoint,
+        language,
+        tracer_version,
+        flush_interval_milliseconds,
+        remote_config_poll_interval_millis,
+        telemetry_heartbeat_interval_millis,
+        force_flush_size,
+        force_drop_size,
+        log_level,
+        log_path,
+        remote_config_notify_function,
+        remote_config_products,
+        remote_config_products_count,
+        remote_config_capabilities,
+        remote_config_capabilities_count,
+        &SessionConfigOptions::default(),
+    )
+}
+
+/// Sets the configuration for a session, like [`ddog_sidecar_session_set_config`], with the
+/// additional `options`.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_session_set_config_v2(
     transport: &mut Box<SidecarTransport>,
     session_id: ffi::CharSlice,
     agent_endpoint: &Endpoint,
@@ -538,6 +906,7 @@ pub unsafe extern "C" fn ddog_sidecar_session_set_config(
     remote_config_products_count: usize,
     remote_config_capabilities: *const RemoteConfigCapabilities,
     remote_config_capabilities_count: usize,
+    options: &SessionConfigOptions,
 ) -> MaybeError {
     #[cfg(unix)]
     let remote_config_notify_target = libc::getpid();
@@ -546,12 +915,12 @@ pub unsafe extern "C" fn ddog_sidecar_session_set_config(
     try_c!(blocking::set_session_config(
         transport,
         remote_config_notify_target,
-        session_id.to_utf8_lossy().into(),
+        ffi::char_slice_to_string_logged(session_id, "session id"),
         &SessionConfig {
             endpoint: agent_endpoint.clone(),
             dogstatsd_endpoint: dogstatsd_endpoint.clone(),
-            language: language.to_utf8_lossy().into(),
-            tracer_version: tracer_version.to_utf8_lossy().into(),
+            language: ffi::char_slice_to_string_logged(language, "language"),
+            tracer_version: ffi::char_slice_to_string_logged(tracer_version, "tracer version"),
             flush_interval: Duration::from_millis(flush_interval_milliseconds as u64),
             remote_config_poll_interval: Duration::from_millis(
                 remote_config_poll_interval_millis as u64
@@ -562,11 +931,7 @@ pub unsafe extern "C" fn ddog_sidecar_session_set_config(
             force_flush_size,
             force_drop_size,
             log_level: log_level.to_utf8_lossy().into(),
-            log_file: if log_path.is_empty() {
-                config::FromEnv::log_method()
-            } else {
-                LogMethod::File(String::from(log_path.to_utf8_lossy()).into())
-            },
+            log_file: options.log_method.to_log_method(&log_path.to_utf8_lossy()),
             remote_config_products: ffi::Slice::from_raw_parts(
                 remote_config_products,
                 remote_config_products_count
@@ -579,12 +944,128 @@ pub unsafe extern "C" fn ddog_sidecar_session_set_config(
             )
             .as_slice()
             .to_vec(),
+            dogstatsd_runtime_id_tag: options.dogstatsd_runtime_id_tag,
+            trace_backpressure_mode: match options.trace_backpressure_timeout_millis {
+                0 => BackpressureMode::Drop,
+                timeout => BackpressureMode::Block(Duration::from_millis(timeout as u64)),
+            },
+            telemetry_dogstatsd_mirror: options
+                .telemetry_dogstatsd_mirror
+                .as_slice()
+                .iter()
+                .map(|name| name.to_utf8_lossy().into_owned())
+                .collect(),
         },
     ));
 
     MaybeError::None
 }
 
+/// Updates the remote config products and capabilities of an existing session, without touching
+/// the rest of its configuration. The remote config subscriptions of the session are restarted.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_session_set_remote_config_products(
+    transport: &mut Box<SidecarTransport>,
+    session_id: ffi::CharSlice,
+    remote_config_products: *const RemoteConfigProduct,
+    remote_config_products_count: usize,
+    remote_config_capabilities: *const RemoteConfigCapabilities,
+    remote_config_capabilities_count: usize,
+) -> MaybeError {
+    try_c!(blocking::set_remote_config_products(
+        transport,
+        session_id.to_utf8_lossy().into(),
+        ffi::Slice::from_raw_parts(remote_config_products, remote_config_products_count)
+            .as_slice()
+            .to_vec(),
+        ffi::Slice::from_raw_parts(remote_config_capabilities, remote_config_capabilities_count)
+            .as_slice()
+            .to_vec(),
+    ));
+
+    MaybeError::None
+}
+
+/// Updates the queue sizes above which the traces of an existing session are flushed and dropped,
+/// without touching the rest of its configuration. Fails if `force_drop_size` is below
+/// `force_flush_size`.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_session_set_flush_thresholds(
+    transport: &mut Box<SidecarTransport>,
+    session_id: ffi::CharSlice,
+    force_flush_size: usize,
+    force_drop_size: usize,
+) -> MaybeError {
+    try_c!(blocking::set_session_flush_thresholds(
+        transport,
+        session_id.to_utf8_lossy().into(),
+        force_flush_size,
+        force_drop_size,
+    ));
+
+    MaybeError::None
+}
+
+/// Retrieves the configuration currently stored for a session, serialized as JSON into `config`.
+///
+/// Api keys are redacted. `config` is set to an empty string if no configuration was set for this
+/// session. On success, `config` must be freed by the caller. On error, it is left untouched.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_session_get_config(
+    transport: &mut Box<SidecarTransport>,
+    session_id: ffi::CharSlice,
+    config: &mut ffi::CharSlice<'static>,
+) -> MaybeError {
+    let session_config = try_c!(blocking::get_session_config(
+        transport,
+        session_id.to_utf8_lossy().into()
+    ));
+    let str = match session_config {
+        Some(session_config) => try_c!(serde_json::to_string(&session_config.redacted())),
+        None => String::new(),
+    };
+    *config = malloc_char_slice(&str);
+
+    MaybeError::None
+}
+
+/// Retrieves the log level the session is currently configured with, e.g. `debug`, into
+/// `log_level`.
+///
+/// `log_level` is set to an empty string if no configuration was set for this session. On success,
+/// `log_level` must be freed by the caller. On error, it is left untouched.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_get_log_level(
+    transport: &mut Box<SidecarTransport>,
+    session_id: ffi::CharSlice,
+    log_level: &mut ffi::CharSlice<'static>,
+) -> MaybeError {
+    let session_config = try_c!(blocking::get_session_config(
+        transport,
+        session_id.to_utf8_lossy().into()
+    ));
+    let str = session_config.map_or_else(String::new, |config| config.log_level);
+    *log_level = malloc_char_slice(&str);
+
+    MaybeError::None
+}
+
+/// Retrieves the cumulative amount of trace data of a session queued for sending to the agent.
+///
+/// Returns all zeroes if the session does not exist or the sidecar could not be reached.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_session_throughput(
+    transport: &mut Box<SidecarTransport>,
+    session_id: ffi::CharSlice,
+) -> ThroughputStats {
+    blocking::session_throughput(transport, session_id.to_utf8_lossy().into()).unwrap_or_default()
+}
+
 #[repr(C)]
 pub struct TracerHeaderTags<'a> {
     pub lang: ffi::CharSlice<'a>,
@@ -640,6 +1121,34 @@ pub unsafe extern "C" fn ddog_sidecar_send_trace_v04_shm(
         *shm_handle,
         len,
         tracer_header_tags,
+        None,
+    ));
+
+    MaybeError::None
+}
+
+/// Sends a trace to the sidecar via shared memory, to be submitted to the given agent instead of
+/// the one of the session.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_send_trace_v04_shm_to_endpoint(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+    shm_handle: Box<ShmHandle>,
+    len: usize,
+    tracer_header_tags: &TracerHeaderTags,
+    endpoint: &Endpoint,
+) -> MaybeError {
+    let tracer_header_tags = try_c!(tracer_header_tags.try_into());
+    try_c!(tracer::Config::default().endpoint_for(endpoint.clone()));
+
+    try_c!(blocking::send_trace_v04_shm(
+        transport,
+        instance_id,
+        *shm_handle,
+        len,
+        tracer_header_tags,
+        Some(endpoint.clone()),
     ));
 
     MaybeError::None
@@ -661,6 +1170,32 @@ pub unsafe extern "C" fn ddog_sidecar_send_trace_v04_bytes(
         instance_id,
         data.as_bytes().to_vec(),
         tracer_header_tags,
+        None,
+    ));
+
+    MaybeError::None
+}
+
+/// Sends a trace as bytes to the sidecar, to be submitted to the given agent instead of the one of
+/// the session.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_send_trace_v04_bytes_to_endpoint(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+    data: ffi::CharSlice,
+    tracer_header_tags: &TracerHeaderTags,
+    endpoint: &Endpoint,
+) -> MaybeError {
+    let tracer_header_tags = try_c!(tracer_header_tags.try_into());
+    try_c!(tracer::Config::default().endpoint_for(endpoint.clone()));
+
+    try_c!(blocking::send_trace_v04_bytes(
+        transport,
+        instance_id,
+        data.as_bytes().to_vec(),
+        tracer_header_tags,
+        Some(endpoint.clone()),
     ));
 
     MaybeError::None
@@ -689,6 +1224,34 @@ pub unsafe extern "C" fn ddog_sidecar_send_debugger_data(
     MaybeError::None
 }
 
+/// Sends the debugger payloads split into chunks of at most `max_chunk_size` bytes each, which the
+/// sidecar reassembles before forwarding. Prefer this over `ddog_sidecar_send_debugger_data` for
+/// large snapshots, to avoid a single giant shared memory allocation.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+#[allow(improper_ctypes_definitions)] // DebuggerPayload is just a pointer, we hide its internals
+pub unsafe extern "C" fn ddog_sidecar_send_debugger_data_chunked(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+    queue_id: QueueId,
+    payloads: Vec<DebuggerPayload>,
+    max_chunk_size: usize,
+) -> MaybeError {
+    if payloads.is_empty() {
+        return MaybeError::None;
+    }
+
+    try_c!(blocking::send_debugger_data_shm_vec_chunked(
+        transport,
+        instance_id,
+        queue_id,
+        payloads,
+        max_chunk_size,
+    ));
+
+    MaybeError::None
+}
+
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
 #[allow(improper_ctypes_definitions)] // DebuggerPayload is just a pointer, we hide its internals
@@ -754,11 +1317,7 @@ pub unsafe extern "C" fn ddog_sidecar_dump(
         Ok(dump) => dump,
         Err(e) => format!("{:?}", e),
     };
-    let size = str.len();
-    let malloced = libc::malloc(size) as *mut u8;
-    let buf = slice::from_raw_parts_mut(malloced, size);
-    buf.copy_from_slice(str.as_bytes());
-    ffi::CharSlice::from_raw_parts(malloced as *mut c_char, size)
+    malloc_char_slice(&str)
 }
 
 /// Retrieves the current statistics of the sidecar.
@@ -771,11 +1330,48 @@ pub unsafe extern "C" fn ddog_sidecar_stats(
         Ok(stats) => stats,
         Err(e) => format!("{:?}", e),
     };
-    let size = str.len();
-    let malloced = libc::malloc(size) as *mut u8;
-    let buf = slice::from_raw_parts_mut(malloced, size);
-    buf.copy_from_slice(str.as_bytes());
-    ffi::CharSlice::from_raw_parts(malloced as *mut c_char, size)
+    malloc_char_slice(&str)
+}
+
+/// Retrieves for how many milliseconds the sidecar has been running into `uptime_ms`.
+/// On error, `uptime_ms` is left untouched.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_uptime_ms(
+    transport: &mut Box<SidecarTransport>,
+    uptime_ms: &mut u64,
+) -> MaybeError {
+    *uptime_ms = try_c!(blocking::uptime(transport)).as_millis() as u64;
+
+    MaybeError::None
+}
+
+/// Retrieves how many milliseconds ago the telemetry of the instance last emitted a heartbeat.
+/// Returns -1 if no heartbeat was emitted yet or the sidecar could not be reached.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_telemetry_last_heartbeat_ms(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+) -> i64 {
+    match blocking::telemetry_last_heartbeat(transport, instance_id) {
+        Ok(Some(elapsed)) => elapsed.as_millis() as i64,
+        Ok(None) | Err(_) => -1,
+    }
+}
+
+/// Retrieves the HTTP status of the last response of the agent the traces of the instance are sent
+/// to. Returns 0 if no response was received yet or the sidecar could not be reached.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_last_agent_status(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+) -> i32 {
+    match blocking::last_agent_status(transport, instance_id) {
+        Ok(Some(status)) => status as i32,
+        Ok(None) | Err(_) => 0,
+    }
 }
 
 /// Send a DogStatsD "count" metric.
@@ -826,6 +1422,35 @@ pub unsafe extern "C" fn ddog_sidecar_dogstatsd_distribution(
     MaybeError::None
 }
 
+/// Send a DogStatsD "distribution" metric with a sample rate.
+///
+/// `sample_rate` is the fraction of the values the caller sends, e.g. 0.1 when sending one value
+/// in ten. Any rate outside of (0, 1) sends the metric unsampled.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_dogstatsd_distribution_sampled(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+    metric: ffi::CharSlice,
+    value: f64,
+    sample_rate: f64,
+    tags: Option<&ddcommon_ffi::Vec<Tag>>,
+) -> MaybeError {
+    try_c!(blocking::send_dogstatsd_actions(
+        transport,
+        instance_id,
+        vec![DogStatsDActionOwned::SampledDistribution(
+            metric.to_utf8_lossy().into_owned(),
+            value,
+            sample_rate,
+            tags.map(|tags| tags.iter().cloned().collect())
+                .unwrap_or_default()
+        ),],
+    ));
+
+    MaybeError::None
+}
+
 /// Send a DogStatsD "gauge" metric.
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
@@ -874,6 +1499,35 @@ pub unsafe extern "C" fn ddog_sidecar_dogstatsd_histogram(
     MaybeError::None
 }
 
+/// Send a DogStatsD "histogram" metric with a sample rate.
+///
+/// `sample_rate` is the fraction of the values the caller sends, e.g. 0.1 when sending one value
+/// in ten. Any rate outside of (0, 1) sends the metric unsampled.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_dogstatsd_histogram_sampled(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+    metric: ffi::CharSlice,
+    value: f64,
+    sample_rate: f64,
+    tags: Option<&ddcommon_ffi::Vec<Tag>>,
+) -> MaybeError {
+    try_c!(blocking::send_dogstatsd_actions(
+        transport,
+        instance_id,
+        vec![DogStatsDActionOwned::SampledHistogram(
+            metric.to_utf8_lossy().into_owned(),
+            value,
+            sample_rate,
+            tags.map(|tags| tags.iter().cloned().collect())
+                .unwrap_or_default()
+        ),],
+    ));
+
+    MaybeError::None
+}
+
 /// Send a DogStatsD "set" metric.
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
@@ -898,6 +1552,43 @@ pub unsafe extern "C" fn ddog_sidecar_dogstatsd_set(
     MaybeError::None
 }
 
+/// Send a DogStatsD "set" metric with a string value, e.g. to count unique user ids.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_dogstatsd_set_string(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+    metric: ffi::CharSlice,
+    value: ffi::CharSlice,
+    tags: Option<&ddcommon_ffi::Vec<Tag>>,
+) -> MaybeError {
+    try_c!(blocking::send_dogstatsd_actions(
+        transport,
+        instance_id,
+        vec![DogStatsDActionOwned::SetString(
+            metric.to_utf8_lossy().into_owned(),
+            value.to_utf8_lossy().into_owned(),
+            tags.map(|tags| tags.iter().cloned().collect())
+                .unwrap_or_default()
+        ),],
+    ));
+
+    MaybeError::None
+}
+
+/// Waits until all the DogStatsD metrics sent so far for this instance have been handed to the
+/// dogstatsd endpoint, e.g. before the process exits.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_dogstatsd_flush(
+    transport: &mut Box<SidecarTransport>,
+    instance_id: &InstanceId,
+) -> MaybeError {
+    try_c!(blocking::flush_dogstatsd(transport, instance_id));
+
+    MaybeError::None
+}
+
 /// Sets x-datadog-test-session-token on all requests for the given session.
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
@@ -931,19 +1622,51 @@ pub extern "C" fn ddog_sidecar_reconnect(
     transport.reconnect(|| unsafe { factory() });
 }
 
+/// Sets a callback invoked when the transport is found closed, by a failing request or when
+/// checking whether it must be reconnected, so that it can be proactively re-established with
+/// `ddog_sidecar_reconnect`. It is invoked once per connection, never while holding the lock of the
+/// transport.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `callback` - A C function invoked upon disconnection.
+#[no_mangle]
+pub extern "C" fn ddog_sidecar_set_disconnect_callback(
+    transport: &mut Box<SidecarTransport>,
+    callback: extern "C" fn(),
+) {
+    transport.set_disconnect_callback(Some(Box::new(move || callback())));
+}
+
 /// Return the path of the crashtracker unix domain socket.
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
 pub unsafe extern "C" fn ddog_sidecar_get_crashtracker_unix_socket_path() -> ffi::CharSlice<'static>
 {
     let socket_path = crashtracker_unix_socket_path();
-    let str = socket_path.to_str().unwrap_or_default();
+    malloc_char_slice(socket_path.to_str().unwrap_or_default())
+}
 
-    let size = str.len();
-    let malloced = libc::malloc(size) as *mut u8;
-    let buf = slice::from_raw_parts_mut(malloced, size);
-    buf.copy_from_slice(str.as_bytes());
-    ffi::CharSlice::from_raw_parts(malloced as *mut c_char, size)
+/// Writes the path of the crashtracker unix domain socket to `out`, to be freed by the caller with
+/// `ddog_StringWrapper_drop`.
+///
+/// Returns false, leaving `out` untouched, if the path is not valid UTF-8.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_sidecar_crashtracker_socket_path(
+    out: NonNull<ffi::StringWrapper>,
+) -> bool {
+    match crashtracker_unix_socket_path()
+        .into_os_string()
+        .into_string()
+    {
+        Ok(path) => {
+            out.as_ptr().write(path.into());
+            true
+        }
+        Err(_) => false,
+    }
 }
 
 /// Gets an agent info reader.
@@ -973,6 +1696,51 @@ pub unsafe extern "C" fn ddog_get_agent_info_env<'a>(
         .unwrap_or(ffi::CharSlice::empty())
 }
 
+/// An owned copy of the agent info as of a read, see `ddog_get_agent_info_snapshot`.
+#[repr(C)]
+pub struct AgentInfoSnapshot {
+    /// Whether the agent info changed since the previous read of the reader
+    pub changed: bool,
+    /// Hash of the agent info, changing whenever the info does. Empty if no info is available yet.
+    pub state_hash: ffi::StringWrapper,
+    /// Version of the agent, empty if unknown
+    pub version: ffi::StringWrapper,
+    /// The default environment of the agent, empty if not set
+    pub default_env: ffi::StringWrapper,
+    /// The maximum size of a request accepted by the agent, -1 if unknown
+    pub max_request_bytes: i64,
+}
+
+/// Reads the current agent info into a self-contained snapshot, which stays valid regardless of
+/// later reads. The snapshot must be freed with `ddog_drop_agent_info_snapshot`.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_get_agent_info_snapshot(
+    reader: &mut AgentInfoReader,
+) -> Box<AgentInfoSnapshot> {
+    let (changed, info) = reader.read();
+    let info = info.as_ref();
+    let config = info.and_then(|info| info.config.as_ref());
+    Box::new(AgentInfoSnapshot {
+        changed,
+        version: info
+            .and_then(|info| info.version.as_deref())
+            .unwrap_or_default()
+            .into(),
+        default_env: config
+            .and_then(|c| c.default_env.as_deref())
+            .unwrap_or_default()
+            .into(),
+        max_request_bytes: info.and_then(|info| info.max_request_bytes()).unwrap_or(-1),
+        state_hash: reader.state_hash().unwrap_or_default().into(),
+    })
+}
+
+/// Drops an agent info snapshot.
+#[no_mangle]
+#[allow(clippy::missing_safety_doc)]
+pub unsafe extern "C" fn ddog_drop_agent_info_snapshot(_: Box<AgentInfoSnapshot>) {}
+
 /// Drops the agent info reader.
 #[no_mangle]
 #[allow(clippy::missing_safety_doc)]
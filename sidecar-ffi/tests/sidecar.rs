@@ -12,6 +12,7 @@ macro_rules! assert_maybe_no_error {
 }
 
 use ddcommon::Endpoint;
+use ddcommon_ffi::slice::AsBytes;
 use std::ptr::{null, null_mut};
 use std::time::Duration;
 #[cfg(unix)]
@@ -67,6 +68,35 @@ fn test_ddog_sidecar_connection() {
     ddog_sidecar_transport_drop(transport);
 }
 
+#[test]
+#[cfg(unix)]
+// shuts down the per-process sidecar other tests may be connected to, run it separately with:
+// cargo test --package datadog-sidecar-ffi -- --ignored test_ddog_sidecar_connect_fresh
+#[ignore]
+fn test_ddog_sidecar_connect_fresh() {
+    set_sidecar_per_process();
+
+    let mut transport = std::ptr::null_mut();
+    assert_maybe_no_error!(ddog_sidecar_connect(&mut transport));
+    let mut transport = unsafe { Box::from_raw(transport) };
+    let old_pid = datadog_sidecar::service::blocking::process_id(&mut transport).unwrap();
+    ddog_sidecar_transport_drop(transport);
+
+    // The running sidecar is left alone without kill_existing
+    let mut transport = std::ptr::null_mut();
+    assert!(ddog_sidecar_connect_fresh(&mut transport, false)
+        .to_std()
+        .is_some());
+
+    let mut transport = std::ptr::null_mut();
+    assert_maybe_no_error!(ddog_sidecar_connect_fresh(&mut transport, true));
+    let mut transport = unsafe { Box::from_raw(transport) };
+    assert_maybe_no_error!(ddog_sidecar_ping(&mut transport));
+    let new_pid = datadog_sidecar::service::blocking::process_id(&mut transport).unwrap();
+    assert_ne!(old_pid, new_pid);
+    ddog_sidecar_transport_drop(transport);
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn test_ddog_sidecar_register_app() {
@@ -173,3 +203,440 @@ fn test_ddog_sidecar_register_app() {
 
     ddog_sidecar_transport_drop(transport);
 }
+
+#[test]
+fn test_log_method_kind_mapping() {
+    use datadog_sidecar::config::{FromEnv, LogMethod};
+
+    assert_eq!(
+        LogMethodKind::Default.to_log_method(""),
+        FromEnv::log_method()
+    );
+    assert_eq!(LogMethodKind::Stdout.to_log_method(""), LogMethod::Stdout);
+    assert_eq!(LogMethodKind::Stderr.to_log_method(""), LogMethod::Stderr);
+    assert_eq!(
+        LogMethodKind::File.to_log_method("/tmp/sidecar.log"),
+        LogMethod::File("/tmp/sidecar.log".into())
+    );
+    assert_eq!(LogMethodKind::File.to_log_method(""), FromEnv::log_method());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_ddog_sidecar_session_get_config() {
+    set_sidecar_per_process();
+
+    let mut transport = std::ptr::null_mut();
+    assert_maybe_no_error!(ddog_sidecar_connect(&mut transport));
+    let mut transport = unsafe { Box::from_raw(transport) };
+    transport
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    transport
+        .set_write_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+
+    unsafe {
+        ddog_sidecar_session_set_config_v2(
+            &mut transport,
+            "get_config_session".into(),
+            &Endpoint {
+                url: hyper::Uri::from_static("http://localhost:8082/"),
+                api_key: Some("secret-api-key".into()),
+                ..Default::default()
+            },
+            &Endpoint::default(),
+            "php".into(),
+            "1.0.0".into(),
+            1234,
+            1000000,
+            1,
+            10000000,
+            10000000,
+            "".into(),
+            "".into(),
+            null_mut(),
+            null(),
+            0,
+            null(),
+            0,
+            &SessionConfigOptions {
+                dogstatsd_runtime_id_tag: true,
+                trace_backpressure_timeout_millis: 500,
+                ..Default::default()
+            },
+        )
+        .unwrap_none();
+
+        let read_config = |transport, session_id: &str| {
+            let mut json = ddcommon_ffi::CharSlice::empty();
+            ddog_sidecar_session_get_config(transport, session_id.into(), &mut json).unwrap_none();
+            let str = json.to_utf8_lossy().into_owned();
+            libc::free(json.as_ptr() as *mut libc::c_void);
+            str
+        };
+        assert_eq!(read_config(&mut transport, "unknown_session"), "");
+
+        let str = read_config(&mut transport, "get_config_session");
+
+        let config: serde_json::Value = serde_json::from_str(&str).unwrap();
+        assert_eq!(config["language"], "php");
+        assert_eq!(config["flush_interval"]["secs"], 1);
+        assert_eq!(config["flush_interval"]["nanos"], 234_000_000);
+        assert_eq!(config["endpoint"]["api_key"], "***");
+        assert_eq!(config["dogstatsd_runtime_id_tag"], true);
+        assert_eq!(
+            config["trace_backpressure_mode"]["Block"]["nanos"],
+            500_000_000
+        );
+        assert!(!str.contains("secret-api-key"));
+    };
+
+    ddog_sidecar_transport_drop(transport);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_ddog_sidecar_get_log_level() {
+    set_sidecar_per_process();
+
+    let mut transport = std::ptr::null_mut();
+    assert_maybe_no_error!(ddog_sidecar_connect(&mut transport));
+    let mut transport = unsafe { Box::from_raw(transport) };
+    transport
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    transport
+        .set_write_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+
+    unsafe {
+        let read_log_level = |transport| {
+            let mut level = ddcommon_ffi::CharSlice::empty();
+            ddog_sidecar_get_log_level(transport, "log_level_session".into(), &mut level)
+                .unwrap_none();
+            let str = level.to_utf8_lossy().into_owned();
+            libc::free(level.as_ptr() as *mut libc::c_void);
+            str
+        };
+        assert_eq!(read_log_level(&mut transport), "");
+
+        ddog_sidecar_session_set_config(
+            &mut transport,
+            "log_level_session".into(),
+            &Endpoint::default(),
+            &Endpoint::default(),
+            "php".into(),
+            "1.0.0".into(),
+            1000,
+            1000000,
+            1,
+            10000000,
+            10000000,
+            "debug".into(),
+            "".into(),
+            null_mut(),
+            null(),
+            0,
+            null(),
+            0,
+        )
+        .unwrap_none();
+
+        assert_eq!(read_log_level(&mut transport), "debug");
+    };
+
+    ddog_sidecar_transport_drop(transport);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_ddog_sidecar_crashtracker_socket_path() {
+    unsafe {
+        let mut path = std::mem::MaybeUninit::<ddcommon_ffi::StringWrapper>::uninit();
+        assert!(ddog_sidecar_crashtracker_socket_path(
+            std::ptr::NonNull::new(path.as_mut_ptr()).unwrap()
+        ));
+        let mut path = path.assume_init();
+
+        let legacy_path = ddog_sidecar_get_crashtracker_unix_socket_path();
+        assert_eq!(
+            ddcommon_ffi::ddog_StringWrapper_message(Some(&path)).to_utf8_lossy(),
+            legacy_path.to_utf8_lossy()
+        );
+        assert!(path.as_ref().contains("libdatadog.ct."));
+        libc::free(legacy_path.as_ptr() as *mut libc::c_void);
+
+        // Dropped in place, must not be dropped again
+        ddcommon_ffi::ddog_StringWrapper_drop(Some(&mut path));
+        std::mem::forget(path);
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+#[cfg_attr(miri, ignore)]
+fn test_ddog_alloc_anon_shm_handle_error_is_descriptive() {
+    let mut handle = null_mut();
+    match ddog_alloc_anon_shm_handle(usize::MAX, &mut handle) {
+        ddcommon_ffi::Option::Some(err) => {
+            let message = err.to_string();
+            assert!(
+                message.contains("failed to resize anonymous shm"),
+                "{message}"
+            );
+            assert!(message.contains("EINVAL"), "{message}");
+        }
+        ddcommon_ffi::Option::None => panic!("allocating usize::MAX bytes of shm must fail"),
+    }
+    assert!(handle.is_null());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_ddog_get_agent_info_snapshot() {
+    use std::io::{Read, Write};
+
+    set_sidecar_per_process();
+
+    // A minimal agent answering every request with its info
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = Endpoint::from_slice(&format!("http://{}/", listener.local_addr().unwrap()));
+    std::thread::spawn(move || {
+        let body = r#"{"version":"7.99.0","config":{"default_env":"snapshot_env","max_request_bytes":1234}}"#;
+        for mut stream in listener.incoming().flatten() {
+            let _ = stream.read(&mut [0; 4096]);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                 datadog-agent-state: snapshot_state\r\ncontent-length: {}\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    });
+
+    let mut transport = std::ptr::null_mut();
+    assert_maybe_no_error!(ddog_sidecar_connect(&mut transport));
+    let mut transport = unsafe { Box::from_raw(transport) };
+    transport
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    transport
+        .set_write_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+
+    unsafe {
+        // The sidecar starts fetching the agent info of the session endpoint
+        ddog_sidecar_session_set_config(
+            &mut transport,
+            "agent_info_snapshot_session".into(),
+            &endpoint,
+            &Endpoint::default(),
+            "php".into(),
+            "1.0.0".into(),
+            1000,
+            1000000,
+            1,
+            10000000,
+            10000000,
+            "".into(),
+            "".into(),
+            null_mut(),
+            null(),
+            0,
+            null(),
+            0,
+        )
+        .unwrap_none();
+
+        let mut reader = ddog_get_agent_info_reader(&endpoint);
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        let snapshot = loop {
+            let snapshot = ddog_get_agent_info_snapshot(&mut reader);
+            if snapshot.changed {
+                break snapshot;
+            }
+            assert!(
+                std::time::Instant::now() < deadline,
+                "no agent info was shared"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(snapshot.state_hash.as_ref(), "snapshot_state");
+        assert_eq!(snapshot.version.as_ref(), "7.99.0");
+        assert_eq!(snapshot.default_env.as_ref(), "snapshot_env");
+        assert_eq!(snapshot.max_request_bytes, 1234);
+        ddog_drop_agent_info_snapshot(snapshot);
+
+        // The snapshot stays the same until the info changes
+        let snapshot = ddog_get_agent_info_snapshot(&mut reader);
+        assert!(!snapshot.changed);
+        assert_eq!(snapshot.state_hash.as_ref(), "snapshot_state");
+        ddog_drop_agent_info_snapshot(snapshot);
+        ddog_drop_agent_info_reader(reader);
+    }
+
+    ddog_sidecar_transport_drop(transport);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_ddog_sidecar_uptime_ms() {
+    set_sidecar_per_process();
+
+    let mut transport = std::ptr::null_mut();
+    assert_maybe_no_error!(ddog_sidecar_connect(&mut transport));
+    let mut transport = unsafe { Box::from_raw(transport) };
+    transport
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    transport
+        .set_write_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+
+    let (mut first, mut second) = (0, 0);
+    assert_maybe_no_error!(unsafe { ddog_sidecar_uptime_ms(&mut transport, &mut first) });
+    std::thread::sleep(Duration::from_millis(50));
+    assert_maybe_no_error!(unsafe { ddog_sidecar_uptime_ms(&mut transport, &mut second) });
+    assert!(second >= first + 50, "{first} -> {second}");
+
+    ddog_sidecar_transport_drop(transport);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_ddog_sidecar_telemetry_flush_metrics() {
+    use datadog_sidecar::service::{blocking, SidecarAction};
+    use ddtelemetry::data::metrics::{MetricNamespace, MetricType};
+    use ddtelemetry::metrics::MetricContext;
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    set_sidecar_per_process();
+
+    // A minimal agent forwarding the bodies of the telemetry requests it receives
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = Endpoint::from_slice(&format!("http://{}/", listener.local_addr().unwrap()));
+    let (telemetry_tx, telemetry_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            let mut content_length = 0;
+            let _ = reader.read_line(&mut request_line);
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).unwrap_or(0) == 0 || header == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            let _ = reader.read_exact(&mut body);
+            if request_line.contains("/telemetry/") {
+                let _ = telemetry_tx.send(String::from_utf8_lossy(&body).into_owned());
+            }
+            let _ = (&stream).write_all(
+                b"HTTP/1.1 202 Accepted\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}",
+            );
+        }
+    });
+
+    let mut transport = std::ptr::null_mut();
+    assert_maybe_no_error!(ddog_sidecar_connect(&mut transport));
+    let mut transport = unsafe { Box::from_raw(transport) };
+    transport
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    transport
+        .set_write_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+
+    unsafe {
+        ddog_sidecar_session_set_config(
+            &mut transport,
+            "flush_metrics_session".into(),
+            &endpoint,
+            &Endpoint::default(),
+            "php".into(),
+            "1.0.0".into(),
+            1000,
+            1000000,
+            // Telemetry data is sent every 100ms, metrics are aggregated every 10s on their own
+            100,
+            10000000,
+            10000000,
+            "".into(),
+            "".into(),
+            null_mut(),
+            null(),
+            0,
+            null(),
+            0,
+        )
+        .unwrap_none();
+
+        let meta = ddog_sidecar_runtimeMeta_build("php".into(), "8.3".into(), "1.0.0".into());
+        let instance_id =
+            ddog_sidecar_instanceId_build("flush_metrics_session".into(), "runtime_id".into());
+        let queue_id = ddog_sidecar_queueId_generate();
+
+        assert_maybe_no_error!(ddog_sidecar_telemetry_flushServiceData(
+            &mut transport,
+            &instance_id,
+            &queue_id,
+            &meta,
+            "flush_metrics_service".into(),
+            "flush_metrics_env".into()
+        ));
+        blocking::enqueue_actions(
+            &mut transport,
+            &instance_id,
+            &queue_id,
+            vec![
+                SidecarAction::RegisterTelemetryMetric(MetricContext {
+                    namespace: MetricNamespace::Tracers,
+                    name: "flush_metrics_test".to_string(),
+                    tags: vec![],
+                    metric_type: MetricType::Count,
+                    common: true,
+                }),
+                SidecarAction::AddTelemetryMetricPoint((
+                    "flush_metrics_test".to_string(),
+                    1.0,
+                    vec![],
+                )),
+            ],
+        )
+        .unwrap();
+
+        // Actions are processed asynchronously, flush until the aggregated point is sent, well
+        // before the periodic aggregation would have happened
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "the metric did not reach the telemetry intake"
+            );
+            assert_maybe_no_error!(ddog_sidecar_telemetry_flush_metrics(
+                &mut transport,
+                &instance_id,
+                &queue_id,
+            ));
+            std::thread::sleep(Duration::from_millis(200));
+            if telemetry_rx.try_iter().any(|body| {
+                body.contains("generate-metrics") && body.contains("flush_metrics_test")
+            }) {
+                break;
+            }
+        }
+
+        ddog_sidecar_instanceId_drop(instance_id);
+        ddog_sidecar_runtimeMeta_drop(meta);
+    }
+
+    ddog_sidecar_transport_drop(transport);
+}
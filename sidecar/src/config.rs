@@ -239,7 +239,7 @@ impl FromEnv {
 }
 
 pub fn get_product_endpoint(subdomain: &str, endpoint: &Endpoint) -> Endpoint {
-    if let Some(ref api_key) = endpoint.api_key {
+    if endpoint.api_key.is_some() {
         let mut parts = endpoint.url.clone().into_parts();
         if parts.scheme.is_none() {
             parts.scheme = Some(Scheme::HTTPS);
@@ -250,12 +250,7 @@ pub fn get_product_endpoint(subdomain: &str, endpoint: &Endpoint) -> Endpoint {
             );
         }
         parts.path_and_query = Some(PathAndQuery::from_static("/"));
-        Endpoint {
-            url: hyper::Uri::from_parts(parts).unwrap(),
-            api_key: Some(api_key.clone()),
-            test_token: endpoint.test_token.clone(),
-            ..*endpoint
-        }
+        endpoint.with_url(hyper::Uri::from_parts(parts).unwrap())
     } else {
         endpoint.clone()
     }
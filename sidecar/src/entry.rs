@@ -19,7 +19,8 @@ use tokio::sync::mpsc;
 
 #[cfg(unix)]
 use crate::crashtracker::crashtracker_unix_socket_path;
-use crate::service::blocking::SidecarTransport;
+use crate::service::blocking::{self, SidecarTransport};
+use crate::service::sidecar_server::record_start_time;
 use crate::service::SidecarServer;
 use datadog_ipc::platform::AsyncChannel;
 
@@ -62,6 +63,18 @@ where
         }
     });
 
+    let server = SidecarServer::default();
+
+    tokio::spawn({
+        let cancel = cancel.clone();
+        let shutdown_requested = server.shutdown_requested.clone();
+        async move {
+            shutdown_requested.notified().await;
+            tracing::info!("Shutdown requested by a client, shutting down");
+            cancel();
+        }
+    });
+
     tokio::spawn(async move {
         if let Err(err) = tokio::signal::ctrl_c().await {
             tracing::error!("Error setting up signal handler {}", err);
@@ -82,8 +95,8 @@ where
 
     // Init. Early, before we start listening.
     drop(SHM_LIMITER.lock());
+    record_start_time();
 
-    let server = SidecarServer::default();
     let (shutdown_complete_tx, shutdown_complete_rx) = mpsc::channel::<()>(1);
 
     let watchdog_handle = Watchdog::from_receiver(shutdown_complete_rx).spawn_watchdog();
@@ -200,11 +213,15 @@ pub fn daemonize(listener: IpcServer, mut cfg: Config) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub fn start_or_connect_to_sidecar(cfg: Config) -> anyhow::Result<SidecarTransport> {
-    let liaison = match cfg.ipc_mode {
+fn liaison_for(ipc_mode: config::IpcMode) -> setup::DefaultLiason {
+    match ipc_mode {
         config::IpcMode::Shared => setup::DefaultLiason::ipc_shared(),
         config::IpcMode::InstancePerProcess => setup::DefaultLiason::ipc_per_process(),
-    };
+    }
+}
+
+pub fn start_or_connect_to_sidecar(cfg: Config) -> anyhow::Result<SidecarTransport> {
+    let liaison = liaison_for(cfg.ipc_mode);
 
     let err = match liaison.attempt_listen() {
         Ok(Some(listener)) => {
@@ -220,3 +237,41 @@ pub fn start_or_connect_to_sidecar(cfg: Config) -> anyhow::Result<SidecarTranspo
         .map_err(|e| err.unwrap_or(e.into()))?
         .into())
 }
+
+/// Spawns a new sidecar and connects to it, instead of connecting to an already running one.
+///
+/// With `kill_existing`, the sidecar currently listening for the configured ipc mode is asked to
+/// shut down and is replaced; it keeps serving its connected clients until they disconnect.
+/// Otherwise the new sidecar is dedicated to the current process, and an error is returned if one
+/// is already running for it.
+pub fn start_fresh_sidecar(
+    mut cfg: Config,
+    kill_existing: bool,
+) -> anyhow::Result<SidecarTransport> {
+    if !kill_existing {
+        cfg.ipc_mode = config::IpcMode::InstancePerProcess;
+    }
+    let liaison = liaison_for(cfg.ipc_mode);
+
+    if let Ok(channel) = liaison.connect_to_server() {
+        if !kill_existing {
+            anyhow::bail!("A sidecar is already running for the current process");
+        }
+        let mut transport = SidecarTransport::from(channel);
+        blocking::shutdown(&mut transport).context("Could not shut down the running sidecar")?;
+    }
+
+    // The listener of the running sidecar is released asynchronously
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let listener = loop {
+        match liaison.attempt_listen() {
+            Ok(Some(listener)) => break listener,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(10)),
+            Ok(None) => anyhow::bail!("The running sidecar did not release its listener in time"),
+            Err(e) => return Err(e).context("Error starting sidecar"),
+        }
+    };
+    daemonize(listener, cfg)?;
+
+    Ok(liaison.connect_to_server()?.into())
+}
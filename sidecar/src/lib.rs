@@ -1,5 +1,10 @@
 // Copyright 2021-Present Datadog, Inc. https://www.datadoghq.com/
 // SPDX-License-Identifier: Apache-2.0
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::hash::{Hash, Hasher};
+use zwohash::ZwoHasher;
+
 pub mod agent_remote_config;
 pub mod config;
 pub mod crashtracker;
@@ -35,3 +40,65 @@ macro_rules! sidecar_version {
     };
 }
 pub(crate) use sidecar_version;
+
+/// Qualifies the identifier `id` of a sidecar with the namespace configured via
+/// [`datadog_ipc::platform::ENV_SHM_NAMESPACE`]: `id` itself in the default, empty, namespace, a
+/// short hash of both otherwise. Sockets and shm segments are named after it, keeping sidecars of
+/// distinct namespaces apart without lengthening the shm names, limited to 31 characters on macOS.
+pub(crate) fn namespaced_identifier(namespace: &str, id: &str) -> String {
+    if namespace.is_empty() {
+        return id.to_string();
+    }
+    // We need a stable hash so that the outcome is independent of the process
+    let mut hasher = ZwoHasher::default();
+    namespace.hash(&mut hasher);
+    id.hash(&mut hasher);
+    let mut hash = BASE64_URL_SAFE_NO_PAD.encode(hasher.finish().to_ne_bytes());
+    hash.truncate(10); // as long as the decimal representation of an uid at most
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::namespaced_identifier;
+    use datadog_ipc::platform::{FileBackedHandle, NamedShmHandle};
+    use std::ffi::CString;
+
+    #[test]
+    fn test_namespaced_identifier() {
+        assert_eq!(namespaced_identifier("", "1000"), "1000");
+
+        let a = namespaced_identifier("a", "1000");
+        let b = namespaced_identifier("b", "1000");
+        assert_ne!(a, b);
+        assert_ne!(a, namespaced_identifier("a", "1001"));
+        assert_eq!(a, namespaced_identifier("a", "1000"));
+        assert_eq!(a.len(), 10);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_namespaced_shm_segments() {
+        let id = format!("ddnstest{}", std::process::id());
+        let path = |namespace| {
+            CString::new(format!("/{}", namespaced_identifier(namespace, &id))).unwrap()
+        };
+        let (path_a, path_b) = (path("a"), path("b"));
+
+        let mut a = NamedShmHandle::create(path_a.clone(), 5)
+            .unwrap()
+            .map()
+            .unwrap();
+        let mut b = NamedShmHandle::create(path_b.clone(), 5)
+            .unwrap()
+            .map()
+            .unwrap();
+        assert!(a.write_slice(0, b"aaaaa"));
+        assert!(b.write_slice(0, b"bbbbb"));
+
+        let other_a = NamedShmHandle::open(&path_a).unwrap().map().unwrap();
+        let other_b = NamedShmHandle::open(&path_b).unwrap().map().unwrap();
+        assert_eq!(other_a.read_slice(0, 5), Some(&b"aaaaa"[..]));
+        assert_eq!(other_b.read_slice(0, 5), Some(&b"bbbbb"[..]));
+    }
+}
@@ -81,6 +81,17 @@ impl<T: FileBackedHandle + From<MappedMem<T>>, D> OneWayShmReader<T, D> {
             extra,
         }
     }
+
+    /// The contents returned by the last successful read, without checking for updates.
+    pub fn last_read(&self) -> &[u8] {
+        match &self.current_data {
+            Some(cur_mem) => {
+                let cur_data: &RawData = cur_mem.as_slice().into();
+                skip_last_byte(cur_data.as_slice())
+            }
+            None => b"",
+        }
+    }
 }
 
 impl<T: FileBackedHandle + From<MappedMem<T>>> OneWayShmWriter<T> {
@@ -101,7 +112,7 @@ where
 }
 
 pub fn open_named_shm(path: &CStr) -> io::Result<MappedMem<NamedShmHandle>> {
-    NamedShmHandle::open(path)?.map()
+    Ok(NamedShmHandle::open(path)?.map()?)
 }
 
 fn skip_last_byte(slice: &[u8]) -> &[u8] {
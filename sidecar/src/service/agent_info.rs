@@ -5,8 +5,8 @@
 //! It will keep one fetcher per Endpoint. The SidecarServer is expected to keep the AgentInfoGuard
 //! alive for the lifetime of the session.
 //! The fetcher will remain alive for a short while after all guards have been dropped.
-//! It writes the raw agent response to shared memory at a fixed per-endpoint location, to be
-//! consumed be tracers.
+//! It writes the agent response to shared memory at a fixed per-endpoint location, to be consumed
+//! be tracers. The state hash of the agent is stored as an additional key of the response.
 
 use crate::one_way_shared_memory::{open_named_shm, OneWayShmReader, OneWayShmWriter};
 use crate::primary_sidecar_identifier;
@@ -20,6 +20,8 @@ use futures::future::Shared;
 use futures::FutureExt;
 use http::uri::PathAndQuery;
 use manual_future::ManualFuture;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::ffi::CString;
 use std::hash::{Hash, Hasher};
 use std::sync::{Arc, Mutex};
@@ -120,7 +122,11 @@ impl AgentInfoFetcher {
                                 };
                             }
                             if let Some(ref writer) = writer {
-                                writer.write(&serde_json::to_vec(&status.info).unwrap())
+                                let shared = SharedAgentInfo {
+                                    state_hash: state.as_deref().map(Cow::Borrowed),
+                                    info: Cow::Borrowed(&status.info),
+                                };
+                                writer.write(&serde_json::to_vec(&shared).unwrap())
                             }
                             if let Some(completer) = completer {
                                 complete_fut = Some(completer.complete(status.info));
@@ -168,9 +174,24 @@ fn info_path(endpoint: &Endpoint) -> CString {
     CString::new(path).unwrap()
 }
 
+/// The layout of the agent info segment: the raw agent response, which is all that readers
+/// predating the state hash expect, with the state hash as an additional key.
+#[derive(Serialize, Deserialize)]
+struct SharedAgentInfo<'a> {
+    #[serde(
+        rename = "datadog_agent_state",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    state_hash: Option<Cow<'a, str>>,
+    #[serde(flatten)]
+    info: Cow<'a, AgentInfoStruct>,
+}
+
 pub struct AgentInfoReader {
     reader: OneWayShmReader<NamedShmHandle, CString>,
     info: Option<AgentInfoStruct>,
+    state_hash: Option<String>,
 }
 
 impl AgentInfoReader {
@@ -179,19 +200,28 @@ impl AgentInfoReader {
         AgentInfoReader {
             reader: OneWayShmReader::new(open_named_shm(&path).ok(), path),
             info: None,
+            state_hash: None,
         }
     }
 
     pub fn read(&mut self) -> (bool, &Option<AgentInfoStruct>) {
         let (updated, data) = self.reader.read();
         if updated {
-            match serde_json::from_slice(data) {
-                Ok(info) => self.info = Some(info),
+            match serde_json::from_slice::<SharedAgentInfo>(data) {
+                Ok(shared) => {
+                    self.info = Some(shared.info.into_owned());
+                    self.state_hash = shared.state_hash.map(Cow::into_owned);
+                }
                 Err(e) => error!("Failed deserializing the agent info: {e:?}"),
             }
         }
         (updated, &self.info)
     }
+
+    /// The state hash of the agent info as of the last read, changing whenever the info does.
+    pub fn state_hash(&self) -> Option<&str> {
+        self.state_hash.as_deref()
+    }
 }
 
 #[cfg(test)]
@@ -240,4 +270,51 @@ mod tests {
             Some("testenv".to_string())
         );
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_read_state_hash() {
+        let endpoint = Endpoint::from_slice("http://127.0.0.1:1/state_hash_test");
+        let writer = OneWayShmWriter::<NamedShmHandle>::new(info_path(&endpoint)).unwrap();
+        let mut reader = AgentInfoReader::new(&endpoint);
+        assert_eq!(reader.state_hash(), None);
+
+        for (state_hash, env) in [("state1", "env1"), ("state2", "env2")] {
+            let info: AgentInfoStruct =
+                serde_json::from_str(&format!(r#"{{"config":{{"default_env":"{env}"}}}}"#))
+                    .unwrap();
+            let shared = SharedAgentInfo {
+                state_hash: Some(state_hash.into()),
+                info: Cow::Borrowed(&info),
+            };
+            let data = serde_json::to_vec(&shared).unwrap();
+            // Readers predating the state hash still find the raw agent response
+            assert_eq!(
+                serde_json::from_slice::<AgentInfoStruct>(&data).unwrap(),
+                info
+            );
+            writer.write(&data);
+
+            let (updated, read_info) = reader.read();
+            assert!(updated);
+            assert_eq!(read_info.as_ref(), Some(&info));
+            assert_eq!(reader.state_hash(), Some(state_hash));
+        }
+
+        // A segment written without state hash is still read
+        writer.write(TEST_INFO.as_bytes());
+        let (updated, read_info) = reader.read();
+        assert!(updated);
+        assert_eq!(
+            read_info
+                .as_ref()
+                .unwrap()
+                .config
+                .as_ref()
+                .unwrap()
+                .default_env,
+            Some("testenv".to_string())
+        );
+        assert_eq!(reader.state_hash(), None);
+    }
 }
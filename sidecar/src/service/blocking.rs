@@ -3,19 +3,23 @@
 
 use super::{
     InstanceId, QueueId, RuntimeMetadata, SerializedTracerHeaderTags, SessionConfig, SidecarAction,
-    SidecarInterfaceRequest, SidecarInterfaceResponse,
+    SidecarInterfaceRequest, SidecarInterfaceResponse, ThroughputStats,
 };
 use datadog_ipc::platform::{Channel, FileBackedHandle, ShmHandle};
 use datadog_ipc::transport::blocking::BlockingTransport;
 use datadog_live_debugger::debugger_defs::DebuggerPayload;
 use datadog_live_debugger::sender::DebuggerType;
+use datadog_remote_config::{RemoteConfigCapabilities, RemoteConfigProduct};
 use ddcommon::tag::Tag;
+use ddcommon::Endpoint;
 use dogstatsd_client::DogStatsDActionOwned;
 use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 use std::{
     borrow::Cow,
     io,
+    ops::Range,
     time::{Duration, Instant},
 };
 use tracing::info;
@@ -29,6 +33,14 @@ use tracing::info;
 /// complete.
 pub struct SidecarTransport {
     pub inner: Mutex<BlockingTransport<SidecarInterfaceResponse, SidecarInterfaceRequest>>,
+    idle_timeout: Option<Duration>,
+    last_activity: Mutex<Instant>,
+    on_disconnect: Option<Box<dyn Fn() + Send + Sync>>,
+    /// Whether `on_disconnect` was invoked since the transport was last (re)connected.
+    disconnect_notified: AtomicBool,
+    /// Whether a call timed out since the transport was last (re)connected. Its response may still
+    /// arrive, and pending data is indistinguishable from the sidecar closing the connection.
+    timed_out: AtomicBool,
 }
 
 impl SidecarTransport {
@@ -36,11 +48,12 @@ impl SidecarTransport {
     where
         F: FnOnce() -> Option<Box<SidecarTransport>>,
     {
+        let needs_reconnect = self.needs_reconnect();
         let mut transport = match self.inner.lock() {
             Ok(t) => t,
             Err(_) => return,
         };
-        if transport.is_closed() {
+        if needs_reconnect || transport.is_closed() {
             info!("The sidecar transport is closed. Reconnecting...");
             let new = match factory() {
                 None => return,
@@ -50,6 +63,51 @@ impl SidecarTransport {
                 return;
             }
             *transport = new.unwrap();
+            drop(transport);
+            self.touch();
+            self.timed_out.store(false, Ordering::Release);
+            self.disconnect_notified.store(false, Ordering::Release);
+        }
+    }
+
+    /// Sets a callback invoked when the transport is found closed, by a failing request or by
+    /// [`SidecarTransport::is_closed`], so that the connection can be proactively re-established.
+    /// It is invoked once per connection, i.e. again only after a [`SidecarTransport::reconnect`],
+    /// and never while holding the lock of the transport.
+    pub fn set_disconnect_callback(&mut self, callback: Option<Box<dyn Fn() + Send + Sync>>) {
+        self.on_disconnect = callback;
+    }
+
+    fn notify_disconnect(&self) {
+        if let Some(on_disconnect) = &self.on_disconnect {
+            if !self.disconnect_notified.swap(true, Ordering::AcqRel) {
+                on_disconnect();
+            }
+        }
+    }
+
+    /// Sets a duration after which the transport is considered closed if no request was sent.
+    /// A closed transport must be re-established via [`SidecarTransport::reconnect`].
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+        self.touch();
+    }
+
+    fn is_idle(&self) -> bool {
+        match (self.idle_timeout, self.last_activity.lock()) {
+            (Some(timeout), Ok(last_activity)) => last_activity.elapsed() >= timeout,
+            _ => false,
+        }
+    }
+
+    /// Whether the transport must be re-established, regardless of the state of the connection.
+    fn needs_reconnect(&self) -> bool {
+        self.is_idle() || self.timed_out.load(Ordering::Acquire)
+    }
+
+    fn touch(&self) {
+        if let Ok(mut last_activity) = self.last_activity.lock() {
+            *last_activity = Instant::now();
         }
     }
 
@@ -68,26 +126,99 @@ impl SidecarTransport {
     }
 
     pub fn is_closed(&self) -> bool {
-        match self.inner.lock() {
-            Ok(t) => t.is_closed(),
-            // Should happen only during the "reconnection" phase. During this phase the transport
-            // is always closed.
-            Err(_) => true,
+        let closed = self.needs_reconnect()
+            || match self.inner.lock() {
+                Ok(t) => t.is_closed(),
+                // Should happen only during the "reconnection" phase. During this phase the
+                // transport is always closed.
+                Err(_) => true,
+            };
+        if closed {
+            self.notify_disconnect();
         }
+        closed
     }
 
     pub fn send(&mut self, item: SidecarInterfaceRequest) -> io::Result<()> {
-        match self.inner.lock() {
-            Ok(mut t) => t.send(item),
-            Err(e) => Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
+        self.check_usable()?;
+        let (result, closed) = match self.inner.lock() {
+            Ok(mut t) => {
+                let result = t.send(item);
+                let closed = result.is_err() && t.is_closed();
+                (result, closed)
+            }
+            Err(e) => (
+                Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
+                true,
+            ),
+        };
+        self.touch();
+        if closed {
+            self.notify_disconnect();
         }
+        result
     }
 
     pub fn call(&mut self, item: SidecarInterfaceRequest) -> io::Result<SidecarInterfaceResponse> {
-        match self.inner.lock() {
-            Ok(mut t) => t.call(item),
-            Err(e) => Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
+        self.check_usable()?;
+        let (result, closed) = match self.inner.lock() {
+            Ok(mut t) => {
+                let result = t.call(item);
+                let closed = result.is_err() && t.is_closed();
+                (result, closed)
+            }
+            Err(e) => (
+                Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
+                true,
+            ),
+        };
+        self.touch();
+        if closed {
+            self.notify_disconnect();
         }
+        result
+    }
+
+    /// Same as [`SidecarTransport::call`], failing with an `io::ErrorKind::TimedOut` error if no
+    /// response was received within `timeout`, which must not be zero. The read timeout of the
+    /// transport is restored afterwards. After a timeout, the transport is considered closed and
+    /// must be re-established via [`SidecarTransport::reconnect`], as the response may still
+    /// arrive.
+    pub fn call_timeout(
+        &mut self,
+        item: SidecarInterfaceRequest,
+        timeout: Duration,
+    ) -> io::Result<SidecarInterfaceResponse> {
+        let previous_timeout = match self.inner.lock() {
+            Ok(t) => t.read_timeout()?,
+            Err(e) => return Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
+        };
+        self.set_read_timeout(Some(timeout))?;
+        let result = self.call(item);
+        self.set_read_timeout(previous_timeout)?;
+        result.map_err(|e| match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                self.timed_out.store(true, Ordering::Release);
+                self.notify_disconnect();
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("no response from the sidecar within {timeout:?}"),
+                )
+            }
+            _ => e,
+        })
+    }
+
+    fn check_usable(&self) -> io::Result<()> {
+        let reason = if self.is_idle() {
+            "The sidecar transport was closed after being idle"
+        } else if self.timed_out.load(Ordering::Acquire) {
+            "The sidecar transport was closed after a request timed out"
+        } else {
+            return Ok(());
+        };
+        self.notify_disconnect();
+        Err(io::Error::new(io::ErrorKind::NotConnected, reason))
     }
 }
 
@@ -95,6 +226,11 @@ impl From<Channel> for SidecarTransport {
     fn from(c: Channel) -> Self {
         SidecarTransport {
             inner: Mutex::new(c.into()),
+            idle_timeout: None,
+            last_activity: Mutex::new(Instant::now()),
+            on_disconnect: None,
+            disconnect_notified: AtomicBool::new(false),
+            timed_out: AtomicBool::new(false),
         }
     }
 }
@@ -222,6 +358,109 @@ pub fn set_session_config(
     })
 }
 
+/// Updates the queue sizes above which the traces of a session are flushed and dropped.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `session_id` - The ID of the session.
+/// * `force_flush_size` - The queue size in bytes above which the traces are flushed.
+/// * `force_drop_size` - The queue size in bytes above which new traces are dropped.
+///
+/// # Returns
+///
+/// An `io::Result<()>` indicating the result of the operation, an `InvalidInput` error if
+/// `force_drop_size` is below `force_flush_size`.
+pub fn set_session_flush_thresholds(
+    transport: &mut SidecarTransport,
+    session_id: String,
+    force_flush_size: usize,
+    force_drop_size: usize,
+) -> io::Result<()> {
+    if force_drop_size < force_flush_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "force_drop_size {force_drop_size} is below force_flush_size {force_flush_size}"
+            ),
+        ));
+    }
+    transport.send(SidecarInterfaceRequest::SetSessionFlushThresholds {
+        session_id,
+        force_flush_size,
+        force_drop_size,
+    })
+}
+
+/// Updates the remote config products and capabilities of a session.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `session_id` - The ID of the session.
+/// * `products` - The remote config products to subscribe to.
+/// * `capabilities` - The remote config capabilities to announce.
+///
+/// # Returns
+///
+/// An `io::Result<()>` indicating the result of the operation.
+pub fn set_remote_config_products(
+    transport: &mut SidecarTransport,
+    session_id: String,
+    products: Vec<RemoteConfigProduct>,
+    capabilities: Vec<RemoteConfigCapabilities>,
+) -> io::Result<()> {
+    transport.send(SidecarInterfaceRequest::SetRemoteConfigProducts {
+        session_id,
+        products,
+        capabilities,
+    })
+}
+
+/// Retrieves the configuration currently stored for a session.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `session_id` - The ID of the session.
+///
+/// # Returns
+///
+/// An `io::Result<Option<SessionConfig>>` holding the stored configuration, if any.
+pub fn get_session_config(
+    transport: &mut SidecarTransport,
+    session_id: String,
+) -> io::Result<Option<SessionConfig>> {
+    let res = transport.call(SidecarInterfaceRequest::GetSessionConfig { session_id })?;
+    if let SidecarInterfaceResponse::GetSessionConfig(config) = res {
+        Ok(config)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Retrieves the cumulative amount of trace data of a session queued for sending to the agent.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `session_id` - The ID of the session.
+///
+/// # Returns
+///
+/// An `io::Result<ThroughputStats>` with the throughput of the session.
+pub fn session_throughput(
+    transport: &mut SidecarTransport,
+    session_id: String,
+) -> io::Result<ThroughputStats> {
+    let res = transport.call(SidecarInterfaceRequest::SessionThroughput { session_id })?;
+    if let SidecarInterfaceResponse::SessionThroughput(throughput) = res {
+        Ok(throughput)
+    } else {
+        Ok(ThroughputStats::default())
+    }
+}
+
 /// Sends a trace as bytes.
 ///
 /// # Arguments
@@ -230,6 +469,7 @@ pub fn set_session_config(
 /// * `instance_id` - The ID of the instance.
 /// * `data` - The trace data serialized as bytes.
 /// * `headers` - The serialized headers from the tracer.
+/// * `endpoint_override` - The agent to send this trace to instead of the session's one.
 ///
 /// # Returns
 ///
@@ -239,11 +479,13 @@ pub fn send_trace_v04_bytes(
     instance_id: &InstanceId,
     data: Vec<u8>,
     headers: SerializedTracerHeaderTags,
+    endpoint_override: Option<Endpoint>,
 ) -> io::Result<()> {
     transport.send(SidecarInterfaceRequest::SendTraceV04Bytes {
         instance_id: instance_id.clone(),
         data,
         headers,
+        endpoint_override,
     })
 }
 
@@ -256,6 +498,7 @@ pub fn send_trace_v04_bytes(
 /// * `handle` - The handle to the shared memory.
 /// * `len` - The size of the shared memory data.
 /// * `headers` - The serialized headers from the tracer.
+/// * `endpoint_override` - The agent to send this trace to instead of the session's one.
 ///
 /// # Returns
 ///
@@ -266,12 +509,14 @@ pub fn send_trace_v04_shm(
     handle: ShmHandle,
     len: usize,
     headers: SerializedTracerHeaderTags,
+    endpoint_override: Option<Endpoint>,
 ) -> io::Result<()> {
     transport.send(SidecarInterfaceRequest::SendTraceV04Shm {
         instance_id: instance_id.clone(),
         handle,
         len,
         headers,
+        endpoint_override,
     })
 }
 
@@ -326,6 +571,16 @@ pub fn send_debugger_data_shm_vec(
     }
     let debugger_type = DebuggerType::of_payload(&payloads[0]);
 
+    Ok(send_debugger_data_shm(
+        transport,
+        instance_id,
+        queue_id,
+        serialize_to_shm(&payloads)?,
+        debugger_type,
+    )?)
+}
+
+fn serialized_size<T: Serialize + ?Sized>(value: &T) -> usize {
     struct SizeCount(usize);
 
     impl io::Write for SizeCount {
@@ -338,19 +593,113 @@ pub fn send_debugger_data_shm_vec(
         }
     }
     let mut size_serializer = serde_json::Serializer::new(SizeCount(0));
-    payloads.serialize(&mut size_serializer).unwrap();
+    value.serialize(&mut size_serializer).unwrap();
+    size_serializer.into_inner().0
+}
 
-    let mut mapped = ShmHandle::new(size_serializer.into_inner().0)?.map()?;
+pub(crate) fn serialize_to_shm<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<ShmHandle> {
+    let mut mapped = ShmHandle::new(serialized_size(value))?.map()?;
     let mut serializer = serde_json::Serializer::new(mapped.as_slice_mut());
-    payloads.serialize(&mut serializer).unwrap();
+    value.serialize(&mut serializer).unwrap();
+    Ok(mapped.into())
+}
 
-    Ok(send_debugger_data_shm(
-        transport,
-        instance_id,
+/// Splits `items` into consecutive ranges whose serialized JSON array does not exceed
+/// `max_chunk_size` bytes. A single item larger than `max_chunk_size` gets a range of its own.
+pub(crate) fn chunk_ranges<T: Serialize>(items: &[T], max_chunk_size: usize) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = 0;
+    // The surrounding []
+    let mut chunk_size = 2;
+    for (i, item) in items.iter().enumerate() {
+        // The item and its separating comma
+        let item_size = serialized_size(item) + 1;
+        if i > start && chunk_size + item_size > max_chunk_size {
+            ranges.push(start..i);
+            start = i;
+            chunk_size = 2;
+        }
+        chunk_size += item_size;
+    }
+    if start < items.len() {
+        ranges.push(start..items.len());
+    }
+    ranges
+}
+
+/// Sends one chunk of debugger payloads from shared memory to the debugger endpoint.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `instance_id` - The ID of the instance.
+/// * `queue_id` - The unique identifier for the trace context.
+/// * `handle` - The handle to the shared memory.
+/// * `debugger_type` - Whether it's log or diagnostic data.
+/// * `more` - Whether further chunks follow this one.
+///
+/// # Returns
+///
+/// An `io::Result<()>` indicating the result of the operation.
+pub fn send_debugger_data_shm_chunk(
+    transport: &mut SidecarTransport,
+    instance_id: &InstanceId,
+    queue_id: QueueId,
+    handle: ShmHandle,
+    debugger_type: DebuggerType,
+    more: bool,
+) -> io::Result<()> {
+    transport.send(SidecarInterfaceRequest::SendDebuggerDataShmChunk {
+        instance_id: instance_id.clone(),
         queue_id,
-        mapped.into(),
+        handle,
         debugger_type,
-    )?)
+        more,
+    })
+}
+
+/// Sends a collection of debugger payloads to the debugger endpoint, split into chunks.
+///
+/// Each chunk is serialized into its own shared memory of at most `max_chunk_size` bytes (unless a
+/// single payload exceeds it), avoiding one giant allocation for large snapshots. The sidecar
+/// reassembles the chunks before forwarding them.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `instance_id` - The ID of the instance.
+/// * `queue_id` - The unique identifier for the trace context.
+/// * `payloads` - The payloads to be sent
+/// * `max_chunk_size` - The maximum size in bytes of a single chunk.
+///
+/// # Returns
+///
+/// An `anyhow::Result<()>` indicating the result of the operation.
+pub fn send_debugger_data_shm_vec_chunked(
+    transport: &mut SidecarTransport,
+    instance_id: &InstanceId,
+    queue_id: QueueId,
+    payloads: Vec<DebuggerPayload>,
+    max_chunk_size: usize,
+) -> anyhow::Result<()> {
+    if payloads.is_empty() {
+        return Ok(());
+    }
+    let debugger_type = DebuggerType::of_payload(&payloads[0]);
+
+    let ranges = chunk_ranges(&payloads, max_chunk_size);
+    let last = ranges.len() - 1;
+    for (i, range) in ranges.into_iter().enumerate() {
+        send_debugger_data_shm_chunk(
+            transport,
+            instance_id,
+            queue_id,
+            serialize_to_shm(&payloads[range])?,
+            debugger_type,
+            i != last,
+        )?;
+    }
+    Ok(())
 }
 
 /// Submits debugger diagnostics.
@@ -452,6 +801,26 @@ pub fn send_dogstatsd_actions(
     })
 }
 
+/// Waits until all the DogStatsD metrics sent so far have been handed to the dogstatsd endpoint.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `instance_id` - The ID of the instance.
+///
+/// # Returns
+///
+/// An `io::Result<()>` indicating the result of the operation.
+pub fn flush_dogstatsd(
+    transport: &mut SidecarTransport,
+    instance_id: &InstanceId,
+) -> io::Result<()> {
+    transport.call(SidecarInterfaceRequest::FlushDogstatsd {
+        instance_id: instance_id.clone(),
+    })?;
+    Ok(())
+}
+
 /// Sets x-datadog-test-session-token on all requests for the given session.
 ///
 /// # Arguments
@@ -505,6 +874,110 @@ pub fn stats(transport: &mut SidecarTransport) -> io::Result<String> {
     }
 }
 
+/// Retrieves for how long the sidecar has been running.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+///
+/// # Returns
+///
+/// An `io::Result<Duration>` representing the time elapsed since the sidecar started.
+pub fn uptime(transport: &mut SidecarTransport) -> io::Result<Duration> {
+    let res = transport.call(SidecarInterfaceRequest::Uptime {})?;
+    if let SidecarInterfaceResponse::Uptime(uptime) = res {
+        Ok(uptime)
+    } else {
+        Ok(Duration::default())
+    }
+}
+
+/// Retrieves the process id of the sidecar.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+///
+/// # Returns
+///
+/// An `io::Result<u32>` with the process id of the sidecar.
+pub fn process_id(transport: &mut SidecarTransport) -> io::Result<u32> {
+    let res = transport.call(SidecarInterfaceRequest::ProcessId {})?;
+    if let SidecarInterfaceResponse::ProcessId(pid) = res {
+        Ok(pid)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected response to a process id request",
+        ))
+    }
+}
+
+/// Asks the sidecar to stop listening for new connections and to shut down once all the connected
+/// clients are gone.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+///
+/// # Returns
+///
+/// An `io::Result<()>` indicating whether the sidecar acknowledged the request.
+pub fn shutdown(transport: &mut SidecarTransport) -> io::Result<()> {
+    transport.call(SidecarInterfaceRequest::Shutdown {})?;
+    Ok(())
+}
+
+/// Retrieves how long ago the telemetry of an instance last emitted a heartbeat.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `instance_id` - The ID of the instance.
+///
+/// # Returns
+///
+/// An `io::Result<Option<Duration>>` with the time elapsed since the last heartbeat, `None` if no
+/// heartbeat was emitted yet.
+pub fn telemetry_last_heartbeat(
+    transport: &mut SidecarTransport,
+    instance_id: &InstanceId,
+) -> io::Result<Option<Duration>> {
+    let res = transport.call(SidecarInterfaceRequest::TelemetryLastHeartbeat {
+        instance_id: instance_id.clone(),
+    })?;
+    if let SidecarInterfaceResponse::TelemetryLastHeartbeat(elapsed) = res {
+        Ok(elapsed)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Retrieves the HTTP status of the last response of the agent the traces of an instance are sent
+/// to.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `instance_id` - The ID of the instance.
+///
+/// # Returns
+///
+/// An `io::Result<Option<u16>>` with the last HTTP status, `None` if no response was received yet.
+pub fn last_agent_status(
+    transport: &mut SidecarTransport,
+    instance_id: &InstanceId,
+) -> io::Result<Option<u16>> {
+    let res = transport.call(SidecarInterfaceRequest::LastAgentStatus {
+        instance_id: instance_id.clone(),
+    })?;
+    if let SidecarInterfaceResponse::LastAgentStatus(status) = res {
+        Ok(status)
+    } else {
+        Ok(None)
+    }
+}
+
 /// Flushes the outstanding traces.
 ///
 /// # Arguments
@@ -535,10 +1008,29 @@ pub fn ping(transport: &mut SidecarTransport) -> io::Result<Duration> {
     Ok(start.elapsed())
 }
 
+/// Same as [`ping`], failing with an `io::ErrorKind::TimedOut` error if the sidecar didn't respond
+/// within `timeout`.
+///
+/// # Arguments
+///
+/// * `transport` - The transport used for communication.
+/// * `timeout` - The maximum time to wait for the response, must not be zero.
+///
+/// # Returns
+///
+/// An `io::Result<Duration>` representing the round-trip time of the ping.
+pub fn ping_timeout(transport: &mut SidecarTransport, timeout: Duration) -> io::Result<Duration> {
+    let start = Instant::now();
+    transport.call_timeout(SidecarInterfaceRequest::Ping {}, timeout)?;
+
+    Ok(start.elapsed())
+}
+
 #[cfg(test)]
 #[cfg(unix)]
 mod tests {
     use crate::service::blocking::SidecarTransport;
+    use crate::service::SidecarInterfaceRequest;
     use datadog_ipc::platform::Channel;
     use std::net::Shutdown;
     use std::os::unix::net::{UnixListener, UnixStream};
@@ -569,6 +1061,176 @@ mod tests {
         let _ = std::fs::remove_file(bind_addr);
     }
 
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_idle_timeout() {
+        let bind_addr = "/tmp/test_idle_timeout.sock";
+        let _ = std::fs::remove_file(bind_addr);
+
+        let listener = UnixListener::bind(bind_addr).expect("Cannot bind");
+        let sock = UnixStream::connect_addr(&listener.local_addr().unwrap()).unwrap();
+
+        let mut transport = SidecarTransport::from(Channel::from(sock));
+        transport.set_idle_timeout(Some(Duration::from_millis(200)));
+        assert!(!transport.is_closed());
+
+        // activity resets the idle timer
+        std::thread::sleep(Duration::from_millis(120));
+        transport
+            .send(SidecarInterfaceRequest::Ping {})
+            .expect("send function failed");
+        std::thread::sleep(Duration::from_millis(120));
+        assert!(!transport.is_closed());
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(transport.is_closed());
+        transport
+            .send(SidecarInterfaceRequest::Ping {})
+            .expect_err("sending on an idle transport must fail");
+
+        transport.reconnect(|| {
+            let new_sock = UnixStream::connect_addr(&listener.local_addr().unwrap()).unwrap();
+            Some(Box::new(SidecarTransport::from(Channel::from(new_sock))))
+        });
+        assert!(!transport.is_closed());
+        transport
+            .send(SidecarInterfaceRequest::Ping {})
+            .expect("send function failed");
+
+        let _ = std::fs::remove_file(bind_addr);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_disconnect_callback() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let bind_addr = "/tmp/test_disconnect_callback.sock";
+        let _ = std::fs::remove_file(bind_addr);
+
+        let listener = UnixListener::bind(bind_addr).expect("Cannot bind");
+        let sock = UnixStream::connect_addr(&listener.local_addr().unwrap()).unwrap();
+
+        let mut transport = SidecarTransport::from(Channel::from(sock.try_clone().unwrap()));
+        let disconnects = Arc::new(AtomicUsize::new(0));
+        transport.set_disconnect_callback(Some(Box::new({
+            let disconnects = disconnects.clone();
+            move || {
+                disconnects.fetch_add(1, Ordering::SeqCst);
+            }
+        })));
+        assert!(!transport.is_closed());
+        assert_eq!(disconnects.load(Ordering::SeqCst), 0);
+
+        sock.shutdown(Shutdown::Both)
+            .expect("shutdown function failed");
+        assert!(transport.is_closed());
+        assert_eq!(disconnects.load(Ordering::SeqCst), 1);
+
+        // Invoked once per connection
+        assert!(transport.is_closed());
+        transport
+            .call(SidecarInterfaceRequest::Ping {})
+            .expect_err("calling on a closed transport must fail");
+        assert_eq!(disconnects.load(Ordering::SeqCst), 1);
+
+        let new_sock = UnixStream::connect_addr(&listener.local_addr().unwrap()).unwrap();
+        transport.reconnect(|| {
+            Some(Box::new(SidecarTransport::from(Channel::from(
+                new_sock.try_clone().unwrap(),
+            ))))
+        });
+        assert!(!transport.is_closed());
+
+        new_sock
+            .shutdown(Shutdown::Both)
+            .expect("shutdown function failed");
+        transport
+            .call(SidecarInterfaceRequest::Ping {})
+            .expect_err("calling on a closed transport must fail");
+        assert_eq!(disconnects.load(Ordering::SeqCst), 2);
+
+        let _ = std::fs::remove_file(bind_addr);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_ping_timeout() {
+        let bind_addr = "/tmp/test_ping_timeout.sock";
+        let _ = std::fs::remove_file(bind_addr);
+
+        // Nobody ever reads from the listener, the sidecar is unresponsive
+        let listener = UnixListener::bind(bind_addr).expect("Cannot bind");
+        let sock = UnixStream::connect_addr(&listener.local_addr().unwrap()).unwrap();
+
+        let mut transport = SidecarTransport::from(Channel::from(sock.try_clone().unwrap()));
+        transport
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .expect("set_read_timeout function failed");
+
+        let start = std::time::Instant::now();
+        let err = super::ping_timeout(&mut transport, Duration::from_millis(100))
+            .expect_err("ping of an unresponsive sidecar must time out");
+        assert_eq!(std::io::ErrorKind::TimedOut, err.kind());
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // The previous read timeout is restored
+        assert_eq!(Some(Duration::from_secs(10)), sock.read_timeout().unwrap());
+
+        let _ = std::fs::remove_file(bind_addr);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_late_response_after_timeout() {
+        use std::io::Write;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let bind_addr = "/tmp/test_late_response_after_timeout.sock";
+        let _ = std::fs::remove_file(bind_addr);
+
+        let listener = UnixListener::bind(bind_addr).expect("Cannot bind");
+        let sock = UnixStream::connect_addr(&listener.local_addr().unwrap()).unwrap();
+        let (mut server_sock, _) = listener.accept().unwrap();
+
+        let mut transport = SidecarTransport::from(Channel::from(sock));
+        let disconnects = Arc::new(AtomicUsize::new(0));
+        transport.set_disconnect_callback(Some(Box::new({
+            let disconnects = disconnects.clone();
+            move || {
+                disconnects.fetch_add(1, Ordering::SeqCst);
+            }
+        })));
+
+        let err = super::ping_timeout(&mut transport, Duration::from_millis(100))
+            .expect_err("ping of an unresponsive sidecar must time out");
+        assert_eq!(std::io::ErrorKind::TimedOut, err.kind());
+        assert_eq!(disconnects.load(Ordering::SeqCst), 1);
+
+        // The response arrives late, the transport must be reconnected rather than read from
+        server_sock.write_all(b"late response").unwrap();
+        assert!(transport.is_closed());
+        let err = transport
+            .call(SidecarInterfaceRequest::Ping {})
+            .expect_err("calling after a timeout must fail");
+        assert_eq!(std::io::ErrorKind::NotConnected, err.kind());
+        assert_eq!(disconnects.load(Ordering::SeqCst), 1);
+
+        transport.reconnect(|| {
+            let new_sock = UnixStream::connect_addr(&listener.local_addr().unwrap()).unwrap();
+            Some(Box::new(SidecarTransport::from(Channel::from(new_sock))))
+        });
+        assert!(!transport.is_closed());
+        transport
+            .send(SidecarInterfaceRequest::Ping {})
+            .expect("send function failed");
+        assert_eq!(disconnects.load(Ordering::SeqCst), 1);
+
+        let _ = std::fs::remove_file(bind_addr);
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn test_set_timeout() {
@@ -606,4 +1268,30 @@ mod tests {
 
         let _ = std::fs::remove_file(bind_addr);
     }
+
+    #[test]
+    fn test_chunk_ranges() {
+        use super::chunk_ranges;
+
+        let payloads: Vec<String> = (0..100).map(|i| format!("payload number {i}")).collect();
+        let max_chunk_size = 200;
+        assert!(serde_json::to_vec(&payloads).unwrap().len() > max_chunk_size);
+
+        let ranges = chunk_ranges(&payloads, max_chunk_size);
+        assert!(ranges.len() > 1);
+        // The chunks cover all the payloads, in order
+        assert_eq!(ranges.first().unwrap().start, 0);
+        assert_eq!(ranges.last().unwrap().end, payloads.len());
+        for (range, next) in ranges.iter().zip(ranges.iter().skip(1)) {
+            assert_eq!(range.end, next.start);
+        }
+        for range in ranges {
+            let chunk = serde_json::to_vec(&payloads[range]).unwrap();
+            assert!(chunk.len() <= max_chunk_size);
+        }
+
+        // An oversized payload gets a chunk of its own
+        let payloads = vec!["a".repeat(300), "b".to_string()];
+        assert_eq!(chunk_ranges(&payloads, max_chunk_size), vec![0..1, 1..2]);
+    }
 }
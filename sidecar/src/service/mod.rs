@@ -59,6 +59,59 @@ pub struct SessionConfig {
     pub log_file: config::LogMethod,
     pub remote_config_products: Vec<RemoteConfigProduct>,
     pub remote_config_capabilities: Vec<RemoteConfigCapabilities>,
+    /// Tag every dogstatsd metric with the `runtime-id` of the instance emitting it
+    #[serde(default)]
+    pub dogstatsd_runtime_id_tag: bool,
+    /// What to do with traces sent while the trace queue is full
+    #[serde(default)]
+    pub trace_backpressure_mode: BackpressureMode,
+    /// Names of the telemetry metrics also sent to the dogstatsd endpoint, e.g. for init failures
+    /// to be reported when the telemetry intake is not reachable. Empty, i.e. disabled, by default
+    #[serde(default)]
+    pub telemetry_dogstatsd_mirror: Vec<String>,
+}
+
+/// Cumulative amount of trace data a session handed over to the sidecar, which was queued for
+/// sending to the agent.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThroughputStats {
+    /// The approximate size of the trace payloads in bytes
+    pub bytes: u64,
+    /// The number of trace chunks
+    pub traces: u64,
+    /// The number of trace payloads queued. Payloads are merged before being sent to the agent, so
+    /// this is not the number of requests made to the agent.
+    pub payloads: u64,
+}
+
+/// How traces are handled when the trace queue of the sidecar is full, i.e. holds more than the
+/// force drop size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackpressureMode {
+    /// Drop the traces right away
+    #[default]
+    Drop,
+    /// Wait up to the given timeout for the queue to be flushed, then drop the traces if it is
+    /// still full
+    Block(Duration),
+}
+
+impl SessionConfig {
+    /// Returns a copy of this config with all api keys masked, suitable for exposing to callers.
+    pub fn redacted(&self) -> SessionConfig {
+        fn redact(endpoint: &Endpoint) -> Endpoint {
+            Endpoint {
+                api_key: endpoint.api_key.as_ref().map(|_| "***".into()),
+                ..endpoint.clone()
+            }
+        }
+        SessionConfig {
+            endpoint: redact(&self.endpoint),
+            dogstatsd_endpoint: redact(&self.dogstatsd_endpoint),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -3,6 +3,7 @@
 
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 /// `QueueId` is a struct that represents a unique identifier for a queue.
 /// It contains a single field, `inner`, which is a 64-bit unsigned integer.
@@ -30,6 +31,25 @@ impl QueueId {
             inner: rand::thread_rng().gen_range(1u64..u64::MAX),
         }
     }
+
+    /// Derives a `QueueId` from the given seed, e.g. to resume the same logical queue after a
+    /// tracer restart. The same seed always yields the same `QueueId`, across processes and
+    /// versions, within the same range as `new_unique`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datadog_sidecar::service::QueueId;
+    ///
+    /// assert_eq!(QueueId::from_seed("worker-1"), QueueId::from_seed("worker-1"));
+    /// ```
+    pub fn from_seed(seed: &str) -> Self {
+        let digest = Sha256::digest(seed.as_bytes());
+        let hash = u64::from_le_bytes(digest[..8].try_into().unwrap_or_default());
+        Self {
+            inner: hash % (u64::MAX - 1) + 1,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -53,4 +73,16 @@ mod tests {
             "Generated QueueId should be within the defined range bounds"
         );
     }
+
+    #[test]
+    fn test_from_seed() {
+        assert_eq!(QueueId::from_seed("seed"), QueueId::from_seed("seed"));
+        assert_ne!(QueueId::from_seed("seed"), QueueId::from_seed("other"));
+
+        let queue_id = QueueId::from_seed("");
+        assert!(
+            queue_id.inner >= 1 && queue_id.inner < u64::MAX,
+            "Derived QueueId should be within the defined range bounds"
+        );
+    }
 }
@@ -6,8 +6,8 @@ use crate::service::{
     telemetry::{AppInstance, AppOrQueue},
     InstanceId, QueueId,
 };
-use datadog_live_debugger::sender::{generate_tags, PayloadSender};
-use ddcommon::tag::Tag;
+use datadog_live_debugger::sender::{generate_tags, DebuggerType, PayloadSender};
+use ddcommon::tag::{unified_service_tags, Tag};
 use futures::{
     future::{self, join_all, Shared},
     FutureExt,
@@ -17,7 +17,12 @@ use simd_json::prelude::ArrayTrait;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::sync::{Arc, Mutex, MutexGuard};
-use tracing::{debug, info};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// A debugger payload partially received in chunks is discarded if its next chunk does not arrive
+/// within this delay, e.g. because the tracer failed sending it.
+const DEBUGGER_CHUNK_TIMEOUT: Duration = Duration::from_secs(10);
 
 type AppMap = HashMap<(String, String), Shared<ManualFuture<Option<AppInstance>>>>;
 
@@ -49,12 +54,15 @@ pub(crate) struct RuntimeInfo {
 pub(crate) struct ActiveApplication {
     pub app_or_actions: AppOrQueue,
     pub remote_config_guard: Option<RemoteConfigsGuard>,
+    pub service_name: Option<String>,
     pub env: Option<String>,
     pub app_version: Option<String>,
     pub global_tags: Vec<Tag>,
     pub live_debugger_tag_cache: Option<Arc<String>>,
     pub debugger_logs_payload_sender: Arc<tokio::sync::Mutex<Option<PayloadSender>>>,
     pub debugger_diagnostics_payload_sender: Arc<tokio::sync::Mutex<Option<PayloadSender>>>,
+    /// Debugger payloads being reassembled from chunks, with the time their last chunk arrived.
+    pub debugger_chunks: HashMap<DebuggerType, (Vec<u8>, Instant)>,
 }
 
 impl RuntimeInfo {
@@ -157,6 +165,16 @@ impl ActiveApplication {
         self.live_debugger_tag_cache = None;
     }
 
+    /// Returns the `service`, `env` and `version` tags of the application, omitting the ones which
+    /// are not known yet.
+    pub fn service_tags(&self) -> Vec<Tag> {
+        unified_service_tags(
+            self.service_name.as_deref().unwrap_or_default(),
+            self.env.as_deref().unwrap_or_default(),
+            self.app_version.as_deref().unwrap_or_default(),
+        )
+    }
+
     /// Sets the cached debugger tags if not set and returns them.
     ///
     /// # Arguments
@@ -194,6 +212,43 @@ impl ActiveApplication {
         self.live_debugger_tag_cache = Some(tags.clone());
         (tags, true)
     }
+
+    /// Buffers one chunk of serialized debugger payloads (a JSON array).
+    ///
+    /// Chunks are concatenated in the order they arrive. Once the last chunk (`more` being false)
+    /// is received, the reassembled JSON array is returned and the buffer is cleared.
+    pub(crate) fn append_debugger_chunk(
+        &mut self,
+        debugger_type: DebuggerType,
+        chunk: &[u8],
+        more: bool,
+    ) -> Option<Vec<u8>> {
+        let now = Instant::now();
+        let (buffer, last_chunk) = self
+            .debugger_chunks
+            .entry(debugger_type)
+            .or_insert_with(|| (Vec::new(), now));
+        if now.duration_since(*last_chunk) > DEBUGGER_CHUNK_TIMEOUT {
+            warn!("Discarding an incomplete chunked live debugger {debugger_type:?} payload");
+            buffer.clear();
+        }
+        *last_chunk = now;
+        if buffer.is_empty() {
+            buffer.extend_from_slice(chunk);
+        } else if chunk.len() > 2 {
+            // Merge "[a,b]" and "[c]" into "[a,b,c]"
+            buffer.pop();
+            buffer.push(b',');
+            buffer.extend_from_slice(&chunk[1..]);
+        }
+        if more {
+            None
+        } else {
+            self.debugger_chunks
+                .remove(&debugger_type)
+                .map(|(buffer, _)| buffer)
+        }
+    }
 }
 
 // TODO: APM-1079 - Add unit tests for RuntimeInfo
@@ -12,6 +12,10 @@ use futures::future;
 
 use datadog_live_debugger::sender::{DebuggerType, PayloadSender};
 use datadog_remote_config::fetch::ConfigInvariants;
+use ddcommon::tag::Tag;
+use ddtelemetry::data::metrics::MetricType;
+use dogstatsd_client::DogStatsDActionOwned;
+use tokio::task::JoinHandle;
 use tracing::log::warn;
 use tracing::{debug, error, info, trace};
 
@@ -19,7 +23,9 @@ use crate::log::{MultiEnvFilterGuard, MultiWriterGuard};
 use crate::{spawn_map_err, tracer};
 
 use crate::service::agent_info::AgentInfoGuard;
-use crate::service::{InstanceId, QueueId, RuntimeInfo};
+use crate::service::{
+    InstanceId, QueueId, RuntimeInfo, SessionConfig, SidecarAction, ThroughputStats,
+};
 
 /// `SessionInfo` holds information about a session.
 ///
@@ -29,11 +35,14 @@ use crate::service::{InstanceId, QueueId, RuntimeInfo};
 pub(crate) struct SessionInfo {
     runtimes: Arc<Mutex<HashMap<String, RuntimeInfo>>>,
     pub(crate) session_config: Arc<Mutex<Option<ddtelemetry::config::Config>>>,
+    pub(crate) applied_config: Arc<Mutex<Option<SessionConfig>>>,
     debugger_config: Arc<Mutex<datadog_live_debugger::sender::Config>>,
     tracer_config: Arc<Mutex<tracer::Config>>,
     dogstatsd: Arc<Mutex<Option<dogstatsd_client::Client>>>,
     remote_config_invariants: Arc<Mutex<Option<ConfigInvariants>>>,
     pub(crate) agent_infos: Arc<Mutex<Option<AgentInfoGuard>>>,
+    /// Applies the agent info to the trace flusher once fetched, aborted with the session
+    pub(crate) agent_info_task: Arc<Mutex<Option<JoinHandle<()>>>>,
     pub(crate) remote_config_interval: Arc<Mutex<Duration>>,
     #[cfg(windows)]
     pub(crate) remote_config_notify_function:
@@ -42,6 +51,9 @@ pub(crate) struct SessionInfo {
         Arc<Mutex<Option<(MultiEnvFilterGuard<'static>, MultiWriterGuard<'static>)>>>,
     pub(crate) session_id: String,
     pub(crate) pid: Arc<AtomicI32>,
+    pub(crate) throughput: Arc<Mutex<ThroughputStats>>,
+    /// The type and tags of the telemetry metrics registered in the session, by name
+    telemetry_metrics: Arc<Mutex<HashMap<String, (MetricType, Vec<Tag>)>>>,
 }
 
 impl Clone for SessionInfo {
@@ -49,17 +61,21 @@ impl Clone for SessionInfo {
         SessionInfo {
             runtimes: self.runtimes.clone(),
             session_config: self.session_config.clone(),
+            applied_config: self.applied_config.clone(),
             debugger_config: self.debugger_config.clone(),
             tracer_config: self.tracer_config.clone(),
             dogstatsd: self.dogstatsd.clone(),
             remote_config_invariants: self.remote_config_invariants.clone(),
             agent_infos: self.agent_infos.clone(),
+            agent_info_task: self.agent_info_task.clone(),
             remote_config_interval: self.remote_config_interval.clone(),
             #[cfg(windows)]
             remote_config_notify_function: self.remote_config_notify_function.clone(),
             log_guard: self.log_guard.clone(),
             session_id: self.session_id.clone(),
             pid: self.pid.clone(),
+            throughput: self.throughput.clone(),
+            telemetry_metrics: self.telemetry_metrics.clone(),
         }
     }
 }
@@ -94,8 +110,87 @@ impl SessionInfo {
         }
     }
 
+    /// Converts the points of the telemetry metrics listed in the `telemetry_dogstatsd_mirror`
+    /// setting of the session to dogstatsd metrics, e.g. to report init failures when the
+    /// telemetry intake is not reachable.
+    ///
+    /// # Arguments
+    ///
+    /// * `actions` - The actions enqueued for the telemetry of an application.
+    /// * `service_tags` - The unified service tags of the application, added to every metric.
+    ///
+    /// # Returns
+    ///
+    /// * The dogstatsd metrics to send, empty if mirroring is disabled.
+    pub(crate) fn telemetry_to_dogstatsd(
+        &self,
+        actions: &[SidecarAction],
+        service_tags: &[Tag],
+    ) -> Vec<DogStatsDActionOwned> {
+        let mirrored = self
+            .applied_config
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|config| config.telemetry_dogstatsd_mirror.clone())
+            .unwrap_or_default();
+        let mut metrics = self.telemetry_metrics.lock().unwrap();
+        let mut dogstatsd_actions = vec![];
+        for action in actions {
+            match action {
+                // Always remembered, metrics may be registered before the session is configured
+                SidecarAction::RegisterTelemetryMetric(metric) => {
+                    metrics.insert(
+                        metric.name.clone(),
+                        (metric.metric_type, metric.tags.clone()),
+                    );
+                }
+                SidecarAction::AddTelemetryMetricPoint((name, value, point_tags)) => {
+                    if !mirrored.contains(name) {
+                        continue;
+                    }
+                    let Some((metric_type, tags)) = metrics.get(name) else {
+                        continue;
+                    };
+                    let tags = tags
+                        .iter()
+                        .chain(point_tags)
+                        .chain(service_tags)
+                        .cloned()
+                        .collect();
+                    dogstatsd_actions.push(match metric_type {
+                        MetricType::Count => {
+                            DogStatsDActionOwned::Count(name.clone(), *value as i64, tags)
+                        }
+                        MetricType::Gauge => {
+                            DogStatsDActionOwned::Gauge(name.clone(), *value, tags)
+                        }
+                        MetricType::Distribution => {
+                            DogStatsDActionOwned::Distribution(name.clone(), *value, tags)
+                        }
+                    });
+                }
+                _ => {}
+            }
+        }
+        dogstatsd_actions
+    }
+
+    /// Accounts for a trace payload of `bytes` bytes holding `traces` trace chunks, which was
+    /// queued for sending to the agent.
+    pub(crate) fn record_throughput(&self, bytes: usize, traces: usize) {
+        let mut throughput = self.throughput.lock().unwrap();
+        throughput.bytes += bytes as u64;
+        throughput.traces += traces as u64;
+        throughput.payloads += 1;
+    }
+
     /// Shuts down all runtimes in the session.
     pub(crate) async fn shutdown(&self) {
+        if let Some(task) = self.agent_info_task.lock().unwrap().take() {
+            task.abort();
+        }
+
         let runtimes: Vec<RuntimeInfo> = self
             .lock_runtimes()
             .drain()
@@ -206,6 +301,33 @@ impl SessionInfo {
         self.remote_config_invariants.lock().unwrap()
     }
 
+    /// Buffers one chunk of serialized debugger payloads (a JSON array) for the given queue, see
+    /// `ActiveApplication::append_debugger_chunk`. The buffer lives with the application of the
+    /// queue, chunks of an unknown application are dropped.
+    pub(crate) fn append_debugger_chunk(
+        &self,
+        debugger_type: DebuggerType,
+        runtime_id: &str,
+        queue_id: QueueId,
+        chunk: &[u8],
+        more: bool,
+    ) -> Option<Vec<u8>> {
+        let runtimes = self.lock_runtimes();
+        let Some(runtime) = runtimes.get(runtime_id) else {
+            warn!(
+                "Did not find runtime {runtime_id} for session id {} - skipping live debugger data",
+                self.session_id
+            );
+            return None;
+        };
+        let mut applications = runtime.lock_applications();
+        let Some(app) = applications.get_mut(&queue_id) else {
+            warn!("Did not find queue_id {queue_id:?} for runtime id {runtime_id} of session id {} - skipping live debugger data", self.session_id);
+            return None;
+        };
+        app.append_debugger_chunk(debugger_type, chunk, more)
+    }
+
     pub fn send_debugger_data<R: AsRef<[u8]> + Sync + Send + 'static>(
         &self,
         debugger_type: DebuggerType,
@@ -306,6 +428,7 @@ impl SessionInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::service::runtime_info::ActiveApplication;
 
     #[tokio::test]
     #[cfg_attr(all(miri, target_os = "macos"), ignore)]
@@ -365,4 +488,69 @@ mod tests {
             .unwrap()
             .contains_key(&runtime_id2));
     }
+
+    #[test]
+    fn test_append_debugger_chunk() {
+        let session_info = SessionInfo::default();
+        let queue_id = QueueId::new_unique();
+        for runtime_id in ["runtime1", "runtime2"] {
+            session_info
+                .get_runtime(&runtime_id.to_string())
+                .lock_applications()
+                .insert(queue_id, ActiveApplication::default());
+        }
+
+        assert!(session_info
+            .append_debugger_chunk(DebuggerType::Logs, "runtime1", queue_id, b"[1,2]", true)
+            .is_none());
+        // Chunks of other queues are kept apart
+        assert_eq!(
+            session_info
+                .append_debugger_chunk(DebuggerType::Logs, "runtime2", queue_id, b"[9]", false)
+                .unwrap(),
+            b"[9]"
+        );
+        assert!(session_info
+            .append_debugger_chunk(DebuggerType::Logs, "runtime1", queue_id, b"[3]", true)
+            .is_none());
+        assert_eq!(
+            session_info
+                .append_debugger_chunk(DebuggerType::Logs, "runtime1", queue_id, b"[4,5]", false)
+                .unwrap(),
+            b"[1,2,3,4,5]"
+        );
+        let runtime = session_info.get_runtime(&"runtime1".to_string());
+        assert!(runtime.lock_applications()[&queue_id]
+            .debugger_chunks
+            .is_empty());
+
+        // Chunks of unknown applications are not buffered
+        let unknown_queue_id = QueueId::new_unique();
+        assert!(session_info
+            .append_debugger_chunk(
+                DebuggerType::Logs,
+                "runtime1",
+                unknown_queue_id,
+                b"[1]",
+                true
+            )
+            .is_none());
+        assert!(!runtime.lock_applications().contains_key(&unknown_queue_id));
+    }
+
+    #[test]
+    fn test_append_debugger_chunk_discards_stale_chunks() {
+        let mut app = ActiveApplication::default();
+        assert!(app
+            .append_debugger_chunk(DebuggerType::Logs, b"[1,2]", true)
+            .is_none());
+        // The last chunk of the payload never arrived
+        app.debugger_chunks.get_mut(&DebuggerType::Logs).unwrap().1 -= Duration::from_secs(60);
+        assert_eq!(
+            app.append_debugger_chunk(DebuggerType::Logs, b"[3]", false)
+                .unwrap(),
+            b"[3]"
+        );
+        assert!(app.debugger_chunks.is_empty());
+    }
 }
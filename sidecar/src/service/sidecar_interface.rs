@@ -5,13 +5,15 @@
 
 use crate::service::{
     InstanceId, QueueId, RequestIdentification, RequestIdentifier, RuntimeMetadata,
-    SerializedTracerHeaderTags, SessionConfig, SidecarAction,
+    SerializedTracerHeaderTags, SessionConfig, SidecarAction, ThroughputStats,
 };
 use anyhow::Result;
 use datadog_ipc::platform::ShmHandle;
 use datadog_ipc::tarpc;
 use datadog_live_debugger::sender::DebuggerType;
+use datadog_remote_config::{RemoteConfigCapabilities, RemoteConfigProduct};
 use ddcommon::tag::Tag;
+use ddcommon::Endpoint;
 use dogstatsd_client::DogStatsDActionOwned;
 use std::time::Duration;
 
@@ -74,6 +76,56 @@ pub trait SidecarInterface {
         config: SessionConfig,
     );
 
+    /// Retrieves the configuration currently stored for a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session.
+    ///
+    /// # Returns
+    ///
+    /// The last configuration set for the session, or `None` if none was set.
+    async fn get_session_config(session_id: String) -> Option<SessionConfig>;
+
+    /// Retrieves the cumulative amount of trace data of a session queued for sending to the agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session.
+    ///
+    /// # Returns
+    ///
+    /// The throughput of the session, all zeroes if the session does not exist.
+    async fn session_throughput(session_id: String) -> ThroughputStats;
+
+    /// Updates the remote config products and capabilities of a session, restarting the remote
+    /// config subscriptions of its applications.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session.
+    /// * `products` - The remote config products to subscribe to.
+    /// * `capabilities` - The remote config capabilities to announce.
+    async fn set_remote_config_products(
+        session_id: String,
+        products: Vec<RemoteConfigProduct>,
+        capabilities: Vec<RemoteConfigCapabilities>,
+    );
+
+    /// Updates the queue sizes above which the traces are flushed and dropped, without touching the
+    /// rest of the session configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The ID of the session.
+    /// * `force_flush_size` - The queue size in bytes above which the traces are flushed.
+    /// * `force_drop_size` - The queue size in bytes above which new traces are dropped.
+    async fn set_session_flush_thresholds(
+        session_id: String,
+        force_flush_size: usize,
+        force_drop_size: usize,
+    );
+
     /// Shuts down a runtime.
     ///
     /// # Arguments
@@ -96,11 +148,13 @@ pub trait SidecarInterface {
     /// * `handle` - The handle to the shared memory.
     /// * `len` - The size of the shared memory data.
     /// * `headers` - The serialized headers from the tracer.
+    /// * `endpoint_override` - The agent to send this trace to instead of the session's one.
     async fn send_trace_v04_shm(
         instance_id: InstanceId,
         #[SerializedHandle] handle: ShmHandle,
         len: usize,
         headers: SerializedTracerHeaderTags,
+        endpoint_override: Option<Endpoint>,
     );
 
     /// Sends a trace as bytes.
@@ -110,10 +164,12 @@ pub trait SidecarInterface {
     /// * `instance_id` - The ID of the instance.
     /// * `data` - The trace data serialized as bytes.
     /// * `headers` - The serialized headers from the tracer.
+    /// * `endpoint_override` - The agent to send this trace to instead of the session's one.
     async fn send_trace_v04_bytes(
         instance_id: InstanceId,
         data: Vec<u8>,
         headers: SerializedTracerHeaderTags,
+        endpoint_override: Option<Endpoint>,
     );
 
     /// Transfers raw data to a live-debugger endpoint.
@@ -130,6 +186,25 @@ pub trait SidecarInterface {
         debugger_type: DebuggerType,
     );
 
+    /// Transfers one chunk of a list of debugger payloads to a live-debugger endpoint.
+    ///
+    /// The sidecar buffers all chunks of a queue until the last one is received and then forwards
+    /// the reassembled payloads at once.
+    ///
+    /// # Arguments
+    /// * `instance_id` - The ID of the instance.
+    /// * `queue_id` - The unique identifier for the trace context.
+    /// * `handle` - The chunk to send, a serialized JSON array of payloads.
+    /// * `debugger_type` - Whether it's log or diagnostic data.
+    /// * `more` - Whether further chunks follow this one.
+    async fn send_debugger_data_shm_chunk(
+        instance_id: InstanceId,
+        queue_id: QueueId,
+        #[SerializedHandle] handle: ShmHandle,
+        debugger_type: DebuggerType,
+        more: bool,
+    );
+
     /// Submits debugger diagnostics.
     /// They are small and bounded in size, hence it's fine to send them without shm.
     /// Also, the sidecar server deserializes them to inspect and filter and avoid sending redundant
@@ -179,6 +254,14 @@ pub trait SidecarInterface {
     /// * `actions` - The DogStatsD actions to send.
     async fn send_dogstatsd_actions(instance_id: InstanceId, actions: Vec<DogStatsDActionOwned>);
 
+    /// Waits until all the DogStatsD metrics sent so far by the session have been handed to the
+    /// dogstatsd endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `instance_id` - The ID of the instance.
+    async fn flush_dogstatsd(instance_id: InstanceId);
+
     /// Flushes any outstanding traces queued for sending.
     async fn flush_traces();
 
@@ -206,4 +289,46 @@ pub trait SidecarInterface {
     ///
     /// A string representation of the current statistics of the service.
     async fn stats() -> String;
+
+    /// Retrieves for how long the sidecar has been running.
+    ///
+    /// # Returns
+    ///
+    /// The time elapsed since the sidecar started.
+    async fn uptime() -> Duration;
+
+    /// Retrieves the process id of the sidecar.
+    ///
+    /// # Returns
+    ///
+    /// The process id of the sidecar.
+    async fn process_id() -> u32;
+
+    /// Asks the sidecar to stop accepting connections and shut down once the connected clients
+    /// are gone.
+    async fn shutdown();
+
+    /// Retrieves how long ago the telemetry of an instance last emitted a heartbeat.
+    ///
+    /// # Arguments
+    ///
+    /// * `instance_id` - The ID of the instance.
+    ///
+    /// # Returns
+    ///
+    /// The time elapsed since the most recent heartbeat of any application of the instance, or
+    /// `None` if none was emitted yet.
+    async fn telemetry_last_heartbeat(instance_id: InstanceId) -> Option<Duration>;
+
+    /// Retrieves the HTTP status of the last response received from the agent the traces of an
+    /// instance are sent to.
+    ///
+    /// # Arguments
+    ///
+    /// * `instance_id` - The ID of the instance.
+    ///
+    /// # Returns
+    ///
+    /// The last HTTP status, or `None` if no response was received yet.
+    async fn last_agent_status(instance_id: InstanceId) -> Option<u16>;
 }
@@ -7,9 +7,10 @@ use crate::service::{
     sidecar_interface::ServeSidecarInterface,
     telemetry::{AppInstance, AppOrQueue},
     tracing::TraceFlusher,
-    EnqueuedTelemetryData, InstanceId, QueueId, RequestIdentification, RequestIdentifier,
-    RuntimeInfo, RuntimeMetadata, SerializedTracerHeaderTags, SessionConfig, SessionInfo,
-    SidecarAction, SidecarInterface, SidecarInterfaceRequest, SidecarInterfaceResponse,
+    BackpressureMode, EnqueuedTelemetryData, InstanceId, QueueId, RequestIdentification,
+    RequestIdentifier, RuntimeInfo, RuntimeMetadata, SerializedTracerHeaderTags, SessionConfig,
+    SessionInfo, SidecarAction, SidecarInterface, SidecarInterfaceRequest,
+    SidecarInterfaceResponse, ThroughputStats,
 };
 use datadog_ipc::platform::{AsyncChannel, ShmHandle};
 use datadog_ipc::tarpc;
@@ -30,12 +31,13 @@ use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::pin::Pin;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex, MutexGuard};
-use std::time::Duration;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
 use futures::FutureExt;
 use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
 use tokio::task::{JoinError, JoinHandle};
 
 use crate::config::get_product_endpoint;
@@ -52,6 +54,7 @@ use datadog_ipc::platform::FileBackedHandle;
 use datadog_ipc::tarpc::server::{Channel, InFlightRequest};
 use datadog_live_debugger::sender::DebuggerType;
 use datadog_remote_config::fetch::{ConfigInvariants, MultiTargetStats};
+use datadog_remote_config::{RemoteConfigCapabilities, RemoteConfigProduct};
 use datadog_trace_utils::tracer_header_tags::TracerHeaderTags;
 use ddcommon::tag::Tag;
 use dogstatsd_client::{new_flusher, DogStatsDActionOwned};
@@ -63,6 +66,33 @@ fn no_response() -> NoResponse {
     future::ready(())
 }
 
+/// Sends the metrics with the dogstatsd client of the session, tagging them with the runtime id if
+/// configured.
+fn send_dogstatsd(
+    session: &SessionInfo,
+    instance_id: &InstanceId,
+    mut actions: Vec<DogStatsDActionOwned>,
+) {
+    let tag_runtime_id = session
+        .applied_config
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|config| config.dogstatsd_runtime_id_tag);
+    if tag_runtime_id {
+        match Tag::new("runtime-id", &instance_id.runtime_id) {
+            Ok(tag) => actions
+                .iter_mut()
+                .for_each(|action| action.tags_mut().push(tag.clone())),
+            Err(e) => warn!("Cannot tag metrics with runtime-id: {e}"),
+        }
+    }
+    session
+        .get_dogstatsd()
+        .as_ref()
+        .inspect(|f| f.send_owned(actions));
+}
+
 #[derive(Serialize, Deserialize)]
 struct SidecarStats {
     trace_flusher: TraceFlusherStats,
@@ -92,6 +122,19 @@ unsafe impl Send for ProcessHandle {}
 #[cfg(windows)]
 unsafe impl Sync for ProcessHandle {}
 
+/// The moment the sidecar started, used to compute its uptime.
+static STARTED_AT: OnceLock<Instant> = OnceLock::new();
+
+/// Records the start of the sidecar. Only the first call has an effect.
+pub(crate) fn record_start_time() {
+    STARTED_AT.get_or_init(Instant::now);
+}
+
+/// Returns how long the sidecar has been running.
+pub(crate) fn uptime() -> Duration {
+    STARTED_AT.get_or_init(Instant::now).elapsed()
+}
+
 /// The `SidecarServer` struct represents a server that handles sidecar operations.
 ///
 /// It maintains a list of active sessions and a counter for each session.
@@ -116,6 +159,8 @@ pub struct SidecarServer {
     remote_configs: RemoteConfigs,
     /// Diagnostics bookkeeper
     debugger_diagnostics_bookkeeper: Arc<DebuggerDiagnosticsBookkeeper>,
+    /// Notified when a client requests the sidecar to shut down
+    pub(crate) shutdown_requested: Arc<Notify>,
     /// The ProcessHandle tied to the connection
     #[cfg(windows)]
     process_handle: Option<ProcessHandle>,
@@ -238,6 +283,25 @@ impl SidecarServer {
         }
     }
 
+    fn remote_config_notify_target(
+        &self,
+        session: &SessionInfo,
+    ) -> Option<RemoteConfigNotifyTarget> {
+        #[cfg(windows)]
+        {
+            self.process_handle.map(|handle| RemoteConfigNotifyTarget {
+                process_handle: handle,
+                notify_function: *session.remote_config_notify_function.lock().unwrap(),
+            })
+        }
+        #[cfg(unix)]
+        {
+            Some(RemoteConfigNotifyTarget {
+                pid: session.pid.load(Ordering::Relaxed),
+            })
+        }
+    }
+
     fn get_runtime(&self, instance_id: &InstanceId) -> RuntimeInfo {
         let session = self.get_session(&instance_id.session_id);
         session.get_runtime(&instance_id.runtime_id)
@@ -260,11 +324,82 @@ impl SidecarServer {
             .expect("Unable to acquire lock on sessions")
     }
 
-    fn send_trace_v04(
+    /// Returns the endpoint traces of the instance are sent to: the traces endpoint of the session,
+    /// or that of the given agent if overridden, with the other settings of the session.
+    fn get_trace_endpoint(
+        &self,
+        instance_id: &InstanceId,
+        endpoint_override: Option<Endpoint>,
+    ) -> anyhow::Result<Option<Endpoint>> {
+        let session = self.get_session(&instance_id.session_id);
+        let config = session.get_trace_config();
+        match endpoint_override {
+            None => Ok(config.endpoint.clone()),
+            Some(agent) => config.endpoint_for(agent).map(Some),
+        }
+    }
+
+    /// Processes a v0.4 trace of the instance. This happens in the background, unless the session
+    /// blocks on a full trace queue: then it happens while handling the request, so that the
+    /// following requests of the connection, which are processed in sequence, wait as well.
+    fn handle_trace_v04<F>(
+        self,
+        instance_id: InstanceId,
+        headers: SerializedTracerHeaderTags,
+        endpoint_override: Option<Endpoint>,
+        data: F,
+    ) -> Pin<Box<dyn Send + futures::Future<Output = ()>>>
+    where
+        F: FnOnce() -> Option<tinybytes::Bytes> + Send + 'static,
+    {
+        let endpoint = match self.get_trace_endpoint(&instance_id, endpoint_override) {
+            Ok(Some(endpoint)) => endpoint,
+            Ok(None) => return Box::pin(no_response()),
+            Err(e) => {
+                error!("Dropping trace sent to an invalid endpoint: {e:?}");
+                return Box::pin(no_response());
+            }
+        };
+        let backpressure = self
+            .get_session(&instance_id.session_id)
+            .applied_config
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(BackpressureMode::Drop, |config| {
+                config.trace_backpressure_mode
+            });
+        let send = async move {
+            if let Some(bytes) = data() {
+                self.send_trace_v04(&instance_id, &headers, bytes, &endpoint, backpressure)
+                    .await;
+            }
+        };
+        match backpressure {
+            BackpressureMode::Drop => {
+                tokio::spawn(send);
+                Box::pin(no_response())
+            }
+            BackpressureMode::Block(_) => Box::pin(send),
+        }
+    }
+
+    /// Returns the HTTP status of the last response of the agent the traces of the session of the
+    /// instance are sent to. Traces sent to an overridden endpoint are not taken into account.
+    fn get_last_agent_status(&self, instance_id: &InstanceId) -> Option<u16> {
+        // Avoid self.get_session(), it would create a new one.
+        let session = self.lock_sessions().get(&instance_id.session_id).cloned()?;
+        let endpoint = session.get_trace_config().endpoint.clone()?;
+        self.trace_flusher.last_status(&endpoint)
+    }
+
+    async fn send_trace_v04(
         &self,
+        instance_id: &InstanceId,
         headers: &SerializedTracerHeaderTags,
         data: tinybytes::Bytes,
         target: &Endpoint,
+        backpressure: BackpressureMode,
     ) {
         let headers: TracerHeaderTags = match headers.try_into() {
             Ok(headers) => headers,
@@ -286,8 +421,18 @@ impl SidecarServer {
         payload_params.measure_size(&mut size);
         match payload_params.try_into() {
             Ok(payload) => {
+                let traces = payload.size();
                 let data = SendData::new(size, payload, headers, target);
-                self.trace_flusher.enqueue(data);
+                if self
+                    .trace_flusher
+                    .enqueue_with_backpressure(data, backpressure)
+                    .await
+                {
+                    // Avoid self.get_session(), it would create a new one.
+                    if let Some(session) = self.lock_sessions().get(&instance_id.session_id) {
+                        session.record_throughput(size, traces);
+                    }
+                }
             }
             Err(e) => {
                 error!(
@@ -450,7 +595,18 @@ impl SidecarInterface for SidecarServer {
                 )
         }
 
+        let session = self.get_session(&instance_id.session_id);
         let rt_info = self.get_runtime(&instance_id);
+        let service_tags = rt_info
+            .lock_applications()
+            .get(&queue_id)
+            .map(ActiveApplication::service_tags)
+            .unwrap_or_default();
+        let mirrored = session.telemetry_to_dogstatsd(&actions, &service_tags);
+        if !mirrored.is_empty() {
+            send_dogstatsd(&session, &instance_id, mirrored);
+        }
+
         let mut applications = rt_info.lock_applications();
         match applications.entry(queue_id) {
             Entry::Occupied(mut entry) => {
@@ -674,6 +830,7 @@ impl SidecarInterface for SidecarServer {
         debug!("Set session config for {session_id} to {config:?}");
 
         let session = self.get_session(&session_id);
+        *session.applied_config.lock().unwrap() = Some(config.clone());
         #[cfg(unix)]
         {
             session.pid.store(pid, Ordering::Relaxed);
@@ -713,8 +870,21 @@ impl SidecarInterface for SidecarServer {
         });
         if config.endpoint.api_key.is_none() {
             // no agent info if agentless
-            *session.agent_infos.lock().unwrap() =
-                Some(self.agent_infos.query_for(config.endpoint.clone()));
+            let agent_infos = self.agent_infos.query_for(config.endpoint.clone());
+            let agent_info = agent_infos.get();
+            let trace_flusher = self.trace_flusher.clone();
+            let trace_endpoint = session.get_trace_config().endpoint.clone();
+            let task = tokio::spawn(async move {
+                if let (Some(max_request_bytes), Some(trace_endpoint)) =
+                    (agent_info.await.max_request_bytes(), trace_endpoint)
+                {
+                    trace_flusher.set_max_request_bytes(trace_endpoint, max_request_bytes);
+                }
+            });
+            if let Some(previous) = session.agent_info_task.lock().unwrap().replace(task) {
+                previous.abort();
+            }
+            *session.agent_infos.lock().unwrap() = Some(agent_infos);
         }
         session.set_remote_config_invariants(ConfigInvariants {
             language: config.language,
@@ -728,11 +898,7 @@ impl SidecarInterface for SidecarServer {
             .interval_ms
             .store(config.flush_interval.as_millis() as u64, Ordering::Relaxed);
         self.trace_flusher
-            .min_force_flush_size_bytes
-            .store(config.force_flush_size as u32, Ordering::Relaxed);
-        self.trace_flusher
-            .min_force_drop_size_bytes
-            .store(config.force_drop_size as u32, Ordering::Relaxed);
+            .set_force_sizes(config.force_flush_size, config.force_drop_size);
 
         session
             .log_guard
@@ -767,6 +933,122 @@ impl SidecarInterface for SidecarServer {
         })
     }
 
+    type GetSessionConfigFut = Ready<Option<SessionConfig>>;
+
+    fn get_session_config(self, _: Context, session_id: String) -> Self::GetSessionConfigFut {
+        // Avoid self.get_session(), it would create a new one.
+        let config = self
+            .lock_sessions()
+            .get(&session_id)
+            .and_then(|session| session.applied_config.lock().unwrap().clone());
+        future::ready(config)
+    }
+
+    type SessionThroughputFut = Ready<ThroughputStats>;
+
+    fn session_throughput(self, _: Context, session_id: String) -> Self::SessionThroughputFut {
+        // Avoid self.get_session(), it would create a new one.
+        let throughput = self
+            .lock_sessions()
+            .get(&session_id)
+            .map(|session| *session.throughput.lock().unwrap())
+            .unwrap_or_default();
+        future::ready(throughput)
+    }
+
+    type SetSessionFlushThresholdsFut = NoResponse;
+
+    fn set_session_flush_thresholds(
+        self,
+        _: Context,
+        session_id: String,
+        force_flush_size: usize,
+        force_drop_size: usize,
+    ) -> Self::SetSessionFlushThresholdsFut {
+        debug!("Set flush thresholds for {session_id} to {force_flush_size} (flush) and {force_drop_size} (drop)");
+
+        if force_drop_size < force_flush_size {
+            warn!("Ignoring flush thresholds of session {session_id}: the drop size {force_drop_size} is below the flush size {force_flush_size}");
+            return no_response();
+        }
+        let session = self.get_session(&session_id);
+        match session.applied_config.lock().unwrap().as_mut() {
+            Some(config) => {
+                config.force_flush_size = force_flush_size;
+                config.force_drop_size = force_drop_size;
+            }
+            None => {
+                warn!(
+                    "Cannot set flush thresholds of session {session_id} before its config is set"
+                );
+                return no_response();
+            }
+        }
+        self.trace_flusher
+            .set_force_sizes(force_flush_size, force_drop_size);
+
+        no_response()
+    }
+
+    type SetRemoteConfigProductsFut = NoResponse;
+
+    fn set_remote_config_products(
+        self,
+        _: Context,
+        session_id: String,
+        products: Vec<RemoteConfigProduct>,
+        capabilities: Vec<RemoteConfigCapabilities>,
+    ) -> Self::SetRemoteConfigProductsFut {
+        debug!("Set remote config products for {session_id} to {products:?} with capabilities {capabilities:?}");
+
+        let session = self.get_session(&session_id);
+        let invariants = match session.get_remote_config_invariants().as_mut() {
+            Some(invariants) => {
+                invariants.products.clone_from(&products);
+                invariants.capabilities.clone_from(&capabilities);
+                invariants.clone()
+            }
+            None => {
+                warn!("Cannot set remote config products of session {session_id} before its config is set");
+                return no_response();
+            }
+        };
+        if let Some(config) = session.applied_config.lock().unwrap().as_mut() {
+            config.remote_config_products = products;
+            config.remote_config_capabilities = capabilities;
+        }
+
+        let Some(notify_target) = self.remote_config_notify_target(&session) else {
+            return no_response();
+        };
+        let poll_interval = *session.remote_config_interval.lock().unwrap();
+        for (runtime_id, runtime_info) in session.lock_runtimes().iter() {
+            for app in runtime_info.lock_applications().values_mut() {
+                if app.remote_config_guard.is_none() {
+                    continue;
+                }
+                let (Some(service), Some(env), Some(app_version)) =
+                    (&app.service_name, &app.env, &app.app_version)
+                else {
+                    continue;
+                };
+                // Replacing the guard drops the subscription with the previous products
+                app.remote_config_guard = Some(self.remote_configs.add_runtime(
+                    invariants.clone(),
+                    poll_interval,
+                    runtime_id.clone(),
+                    notify_target.clone(),
+                    env.clone(),
+                    service.clone(),
+                    app_version.clone(),
+                    app.global_tags.clone(),
+                ));
+            }
+        }
+
+        no_response()
+    }
+
     type ShutdownRuntimeFut = NoResponse;
 
     fn shutdown_runtime(self, _: Context, instance_id: InstanceId) -> Self::ShutdownRuntimeFut {
@@ -783,7 +1065,7 @@ impl SidecarInterface for SidecarServer {
         no_response()
     }
 
-    type SendTraceV04ShmFut = NoResponse;
+    type SendTraceV04ShmFut = Pin<Box<dyn Send + futures::Future<Output = ()>>>;
 
     fn send_trace_v04_shm(
         self,
@@ -792,28 +1074,23 @@ impl SidecarInterface for SidecarServer {
         handle: ShmHandle,
         _len: usize,
         headers: SerializedTracerHeaderTags,
+        endpoint_override: Option<Endpoint>,
     ) -> Self::SendTraceV04ShmFut {
-        if let Some(endpoint) = self
-            .get_session(&instance_id.session_id)
-            .get_trace_config()
-            .endpoint
-            .clone()
-        {
-            tokio::spawn(async move {
-                match handle.map() {
-                    Ok(mapped) => {
-                        let bytes = tinybytes::Bytes::from(mapped);
-                        self.send_trace_v04(&headers, bytes, &endpoint);
-                    }
-                    Err(e) => error!("Failed mapping shared trace data memory: {}", e),
+        self.handle_trace_v04(
+            instance_id,
+            headers,
+            endpoint_override,
+            move || match handle.map() {
+                Ok(mapped) => Some(tinybytes::Bytes::from(mapped)),
+                Err(e) => {
+                    error!("Failed mapping shared trace data memory: {}", e);
+                    None
                 }
-            });
-        }
-
-        no_response()
+            },
+        )
     }
 
-    type SendTraceV04BytesFut = NoResponse;
+    type SendTraceV04BytesFut = Pin<Box<dyn Send + futures::Future<Output = ()>>>;
 
     fn send_trace_v04_bytes(
         self,
@@ -821,20 +1098,11 @@ impl SidecarInterface for SidecarServer {
         instance_id: InstanceId,
         data: Vec<u8>,
         headers: SerializedTracerHeaderTags,
+        endpoint_override: Option<Endpoint>,
     ) -> Self::SendTraceV04BytesFut {
-        if let Some(endpoint) = self
-            .get_session(&instance_id.session_id)
-            .get_trace_config()
-            .endpoint
-            .clone()
-        {
-            tokio::spawn(async move {
-                let bytes = tinybytes::Bytes::from(data);
-                self.send_trace_v04(&headers, bytes, &endpoint);
-            });
-        }
-
-        no_response()
+        self.handle_trace_v04(instance_id, headers, endpoint_override, move || {
+            Some(tinybytes::Bytes::from(data))
+        })
     }
 
     type SendDebuggerDataShmFut = NoResponse;
@@ -863,6 +1131,41 @@ impl SidecarInterface for SidecarServer {
         no_response()
     }
 
+    type SendDebuggerDataShmChunkFut = NoResponse;
+
+    fn send_debugger_data_shm_chunk(
+        self,
+        _: Context,
+        instance_id: InstanceId,
+        queue_id: QueueId,
+        handle: ShmHandle,
+        debugger_type: DebuggerType,
+        more: bool,
+    ) -> Self::SendDebuggerDataShmChunkFut {
+        let session = self.get_session(&instance_id.session_id);
+        match handle.map() {
+            Ok(mapped) => {
+                if let Some(payload) = session.append_debugger_chunk(
+                    debugger_type,
+                    &instance_id.runtime_id,
+                    queue_id,
+                    mapped.as_slice(),
+                    more,
+                ) {
+                    session.send_debugger_data(
+                        debugger_type,
+                        &instance_id.runtime_id,
+                        queue_id,
+                        payload,
+                    );
+                }
+            }
+            Err(e) => error!("Failed mapping shared debugger data memory: {}", e),
+        }
+
+        no_response()
+    }
+
     type SendDebuggerDiagnosticsFut = NoResponse;
 
     fn send_debugger_diagnostics(
@@ -919,19 +1222,9 @@ impl SidecarInterface for SidecarServer {
         debug!("Registered remote config metadata: instance {instance_id:?}, queue_id: {queue_id:?}, service: {service_name}, env: {env_name}, version: {app_version}");
 
         let session = self.get_session(&instance_id.session_id);
-        #[cfg(windows)]
-        let notify_target = if let Some(handle) = self.process_handle {
-            RemoteConfigNotifyTarget {
-                process_handle: handle,
-                notify_function: *session.remote_config_notify_function.lock().unwrap(),
-            }
-        } else {
+        let Some(notify_target) = self.remote_config_notify_target(&session) else {
             return no_response();
         };
-        #[cfg(unix)]
-        let notify_target = RemoteConfigNotifyTarget {
-            pid: session.pid.load(Ordering::Relaxed),
-        };
         let runtime_info = session.get_runtime(&instance_id.runtime_id);
         let mut applications = runtime_info.lock_applications();
         let app = applications.entry(queue_id).or_default();
@@ -946,11 +1239,12 @@ impl SidecarInterface for SidecarServer {
                 instance_id.runtime_id,
                 notify_target,
                 env_name.clone(),
-                service_name,
+                service_name.clone(),
                 app_version.clone(),
                 global_tags.clone(),
             ),
         );
+        app.service_name = Some(service_name);
         app.set_metadata(env_name, app_version, global_tags);
 
         no_response()
@@ -964,16 +1258,31 @@ impl SidecarInterface for SidecarServer {
         instance_id: InstanceId,
         actions: Vec<DogStatsDActionOwned>,
     ) -> Self::SendDogstatsdActionsFut {
-        tokio::spawn(async move {
-            self.get_session(&instance_id.session_id)
-                .get_dogstatsd()
-                .as_ref()
-                .inspect(|f| f.send_owned(actions));
-        });
+        // Sent inline rather than from a spawned task, so that a subsequent flush_dogstatsd
+        // request is guaranteed to see these metrics queued.
+        let session = self.get_session(&instance_id.session_id);
+        send_dogstatsd(&session, &instance_id, actions);
 
         no_response()
     }
 
+    type FlushDogstatsdFut = future::Map<JoinHandle<()>, fn(Result<(), JoinError>)>;
+
+    fn flush_dogstatsd(self, _: Context, instance_id: InstanceId) -> Self::FlushDogstatsdFut {
+        let session = self.get_session(&instance_id.session_id);
+        fn report_result(result: Result<(), JoinError>) {
+            if let Err(e) = result {
+                error!("Failed flushing dogstatsd metrics: {e:?}");
+            }
+        }
+        tokio::task::spawn_blocking(move || {
+            if let Some(Err(e)) = session.get_dogstatsd().as_ref().map(|f| f.flush()) {
+                error!("Failed flushing dogstatsd metrics: {e:?}");
+            }
+        })
+        .map(report_result)
+    }
+
     type FlushTracesFut = future::Map<JoinHandle<()>, fn(Result<(), JoinError>)>;
 
     fn flush_traces(self, _: Context) -> Self::FlushTracesFut {
@@ -1033,7 +1342,63 @@ impl SidecarInterface for SidecarServer {
     type DumpFut = Pin<Box<dyn Send + futures::Future<Output = String>>>;
 
     fn dump(self, _: Context) -> Self::DumpFut {
-        Box::pin(crate::dump::dump())
+        Box::pin(async move {
+            format!(
+                "Sidecar uptime: {}ms\n{}",
+                uptime().as_millis(),
+                crate::dump::dump().await
+            )
+        })
+    }
+
+    type UptimeFut = Ready<Duration>;
+
+    fn uptime(self, _: Context) -> Self::UptimeFut {
+        future::ready(uptime())
+    }
+
+    type ProcessIdFut = Ready<u32>;
+
+    fn process_id(self, _: Context) -> Self::ProcessIdFut {
+        future::ready(std::process::id())
+    }
+
+    type ShutdownFut = Ready<()>;
+
+    fn shutdown(self, _: Context) -> Self::ShutdownFut {
+        self.shutdown_requested.notify_one();
+        future::ready(())
+    }
+
+    type TelemetryLastHeartbeatFut = Ready<Option<Duration>>;
+
+    fn telemetry_last_heartbeat(
+        self,
+        _: Context,
+        instance_id: InstanceId,
+    ) -> Self::TelemetryLastHeartbeatFut {
+        // Avoid self.get_session(), it would create a new one.
+        let session = self.lock_sessions().get(&instance_id.session_id).cloned();
+        let runtime = session.and_then(|session| {
+            session
+                .lock_runtimes()
+                .get(&instance_id.runtime_id)
+                .cloned()
+        });
+        let last_heartbeat = runtime.and_then(|runtime| {
+            runtime
+                .lock_apps()
+                .values()
+                .filter_map(|app| app.peek()?.as_ref()?.telemetry.last_heartbeat())
+                .max()
+        });
+        future::ready(last_heartbeat.map(|instant| instant.elapsed()))
+    }
+
+    type LastAgentStatusFut = Ready<Option<u16>>;
+
+    fn last_agent_status(self, _: Context, instance_id: InstanceId) -> Self::LastAgentStatusFut {
+        future::ready(self.get_last_agent_status(&instance_id))
     }
 
     type StatsFut = Pin<Box<dyn Send + futures::Future<Output = String>>>;
@@ -1098,3 +1463,501 @@ async fn session_interceptor(
 }
 
 // TODO: APMSP-1079 - Unit tests are sparse for the sidecar server. We should add more.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datadog_remote_config::fetch::test_server::RemoteConfigServer;
+    use datadog_trace_utils::test_utils::{create_test_span, poll_for_mock_hit};
+    use httpmock::MockServer;
+
+    async fn wait_for_products(server: &RemoteConfigServer, expected: &[&str]) {
+        for _ in 0..500 {
+            if let Some(req) = server.last_request.lock().unwrap().as_ref() {
+                if req.client.as_ref().unwrap().products == expected {
+                    return;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Remote config products never became {expected:?}");
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_set_remote_config_products_restarts_subscription() {
+        let rc_server = RemoteConfigServer::spawn();
+        let server = SidecarServer::default();
+        let session_id = "session".to_string();
+
+        let session = server.get_session(&session_id);
+        let mut invariants = rc_server.dummy_invariants();
+        invariants.products = vec![RemoteConfigProduct::ApmTracing];
+        let capabilities = invariants.capabilities.clone();
+        session.set_remote_config_invariants(invariants);
+        *session.remote_config_interval.lock().unwrap() = Duration::from_millis(10);
+
+        server.clone().set_remote_config_data(
+            tarpc::context::current(),
+            InstanceId::new("session", "2b5e1aa6-ac16-4d84-8f3b-7dbf2aa3a9d4"),
+            QueueId::new_unique(),
+            "service".to_string(),
+            "env".to_string(),
+            "1.0".to_string(),
+            vec![],
+        );
+        wait_for_products(&rc_server, &["APM_TRACING"]).await;
+
+        server.clone().set_remote_config_products(
+            tarpc::context::current(),
+            session_id.clone(),
+            vec![
+                RemoteConfigProduct::ApmTracing,
+                RemoteConfigProduct::LiveDebugger,
+            ],
+            capabilities,
+        );
+        wait_for_products(&rc_server, &["APM_TRACING", "LIVE_DEBUGGING"]).await;
+
+        server.remote_configs.shutdown();
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_dogstatsd_runtime_id_tag() {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let server = SidecarServer::default();
+        server
+            .clone()
+            .set_session_config(
+                tarpc::context::current(),
+                "session".to_string(),
+                0,
+                SessionConfig {
+                    endpoint: Endpoint::from_slice("http://127.0.0.1:1/"),
+                    dogstatsd_endpoint: Endpoint::from_slice(
+                        &socket.local_addr().unwrap().to_string(),
+                    ),
+                    language: "php".to_string(),
+                    tracer_version: "1.0.0".to_string(),
+                    flush_interval: Duration::from_secs(1),
+                    remote_config_poll_interval: Duration::from_secs(1),
+                    telemetry_heartbeat_interval: Duration::from_secs(1),
+                    force_flush_size: 1000,
+                    force_drop_size: 1000,
+                    log_level: String::new(),
+                    log_file: crate::config::LogMethod::Disabled,
+                    remote_config_products: vec![],
+                    remote_config_capabilities: vec![],
+                    dogstatsd_runtime_id_tag: true,
+                    trace_backpressure_mode: BackpressureMode::Drop,
+                    telemetry_dogstatsd_mirror: vec![],
+                },
+            )
+            .await;
+
+        let runtime_id = "2b5e1aa6-ac16-4d84-8f3b-7dbf2aa3a9d4";
+        server.clone().send_dogstatsd_actions(
+            tarpc::context::current(),
+            InstanceId::new("session", runtime_id),
+            vec![
+                DogStatsDActionOwned::Count("test_count".to_string(), 1, vec![]),
+                DogStatsDActionOwned::SampledDistribution(
+                    "test_distribution".to_string(),
+                    2.5,
+                    0.1,
+                    vec![],
+                ),
+            ],
+        );
+
+        let datagrams = tokio::task::spawn_blocking(move || {
+            let mut buf = [0; 200];
+            (0..2)
+                .map(|_| {
+                    let len = socket.recv(&mut buf).unwrap();
+                    String::from_utf8_lossy(&buf[..len]).to_string()
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            datagrams,
+            [
+                format!("test_count:1|c|#runtime-id:{runtime_id}"),
+                format!("test_distribution:2.5|d|@0.1|#runtime-id:{runtime_id}"),
+            ]
+        );
+
+        server.remote_configs.shutdown();
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    async fn test_telemetry_dogstatsd_mirror() {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let server = SidecarServer::default();
+        server
+            .clone()
+            .set_session_config(
+                tarpc::context::current(),
+                "session".to_string(),
+                0,
+                SessionConfig {
+                    endpoint: Endpoint::from_slice("http://127.0.0.1:1/"),
+                    dogstatsd_endpoint: Endpoint::from_slice(
+                        &socket.local_addr().unwrap().to_string(),
+                    ),
+                    language: "php".to_string(),
+                    tracer_version: "1.0.0".to_string(),
+                    flush_interval: Duration::from_secs(1),
+                    remote_config_poll_interval: Duration::from_secs(1),
+                    telemetry_heartbeat_interval: Duration::from_secs(1),
+                    force_flush_size: 1000,
+                    force_drop_size: 1000,
+                    log_level: String::new(),
+                    log_file: crate::config::LogMethod::Disabled,
+                    remote_config_products: vec![],
+                    remote_config_capabilities: vec![],
+                    dogstatsd_runtime_id_tag: false,
+                    trace_backpressure_mode: BackpressureMode::Drop,
+                    telemetry_dogstatsd_mirror: vec!["init_failed".to_string()],
+                },
+            )
+            .await;
+
+        let metric = |name: &str| {
+            SidecarAction::RegisterTelemetryMetric(ddtelemetry::metrics::MetricContext {
+                namespace: ddtelemetry::data::metrics::MetricNamespace::Tracers,
+                name: name.to_string(),
+                tags: vec![ddcommon::tag!("reason", "timeout")],
+                metric_type: ddtelemetry::data::metrics::MetricType::Count,
+                common: true,
+            })
+        };
+        // The unified service tags of the application are added, the unknown version is omitted
+        let instance_id = InstanceId::new("session", "2b5e1aa6-ac16-4d84-8f3b-7dbf2aa3a9d4");
+        let queue_id = QueueId::new_unique();
+        server.get_runtime(&instance_id).lock_applications().insert(
+            queue_id,
+            ActiveApplication {
+                service_name: Some("web".to_string()),
+                env: Some("prod".to_string()),
+                ..Default::default()
+            },
+        );
+        server.clone().enqueue_actions(
+            tarpc::context::current(),
+            instance_id,
+            queue_id,
+            vec![
+                metric("spans_created"),
+                metric("init_failed"),
+                SidecarAction::AddTelemetryMetricPoint(("spans_created".to_string(), 5.0, vec![])),
+                SidecarAction::AddTelemetryMetricPoint(("init_failed".to_string(), 1.0, vec![])),
+            ],
+        );
+
+        let datagram = tokio::task::spawn_blocking(move || {
+            let mut buf = [0; 200];
+            let len = socket.recv(&mut buf).unwrap();
+            String::from_utf8_lossy(&buf[..len]).to_string()
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            datagram,
+            "init_failed:1|c|#reason:timeout,service:web,env:prod"
+        );
+
+        server.remote_configs.shutdown();
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_send_trace_endpoint_override() {
+        let default_agent = MockServer::start();
+        let override_agent = MockServer::start();
+        let mut mocks = vec![];
+        for agent in [&default_agent, &override_agent] {
+            mocks.push(
+                agent
+                    .mock_async(|when, then| {
+                        when.path("/v0.4/traces")
+                            .header("x-datadog-test-session-token", "token");
+                        then.status(200)
+                            .header("content-type", "application/json")
+                            .body(r#"{"rate_by_service":{}}"#);
+                    })
+                    .await,
+            );
+        }
+
+        let server = SidecarServer::default();
+        let instance_id = InstanceId::new("session", "2b5e1aa6-ac16-4d84-8f3b-7dbf2aa3a9d4");
+        server
+            .get_session(&instance_id.session_id)
+            .modify_trace_config(|cfg| {
+                cfg.set_endpoint(Endpoint::from_slice(&default_agent.url("")))
+                    .unwrap();
+            });
+        // The override keeps the settings of the session
+        server
+            .clone()
+            .set_test_session_token(
+                tarpc::context::current(),
+                instance_id.session_id.clone(),
+                "token".to_string(),
+            )
+            .await;
+
+        let data = rmp_serde::to_vec_named(&[vec![create_test_span(1, 2, 0, 1, true)]]).unwrap();
+        for endpoint_override in [None, Some(Endpoint::from_slice(&override_agent.url("")))] {
+            let headers: SerializedTracerHeaderTags = TracerHeaderTags {
+                lang: "php",
+                ..Default::default()
+            }
+            .try_into()
+            .unwrap();
+            server
+                .clone()
+                .send_trace_v04_bytes(
+                    tarpc::context::current(),
+                    instance_id.clone(),
+                    data.clone(),
+                    headers,
+                    endpoint_override,
+                )
+                .await;
+        }
+
+        // The traces are enqueued asynchronously, keep flushing until they are received
+        for mock in mocks {
+            let mut hit = false;
+            for _ in 0..100 {
+                server.trace_flusher.flush().await;
+                if mock.hits_async().await == 1 {
+                    hit = true;
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            assert!(hit);
+        }
+
+        server.remote_configs.shutdown();
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_last_agent_status() {
+        let agent = MockServer::start();
+        let mut mock = agent
+            .mock_async(|when, then| {
+                when.path("/v0.4/traces");
+                then.status(413);
+            })
+            .await;
+
+        let server = SidecarServer::default();
+        let instance_id = InstanceId::new("session", "2b5e1aa6-ac16-4d84-8f3b-7dbf2aa3a9d4");
+        server
+            .get_session(&instance_id.session_id)
+            .modify_trace_config(|cfg| {
+                cfg.set_endpoint(Endpoint::from_slice(&agent.url("")))
+                    .unwrap();
+            });
+        assert_eq!(server.get_last_agent_status(&instance_id), None);
+
+        let headers: SerializedTracerHeaderTags = TracerHeaderTags {
+            lang: "php",
+            ..Default::default()
+        }
+        .try_into()
+        .unwrap();
+        let data = rmp_serde::to_vec_named(&[vec![create_test_span(1, 2, 0, 1, true)]]).unwrap();
+        let endpoint = server
+            .get_trace_endpoint(&instance_id, None)
+            .unwrap()
+            .unwrap();
+        server
+            .send_trace_v04(
+                &instance_id,
+                &headers,
+                tinybytes::Bytes::from(data),
+                &endpoint,
+                BackpressureMode::Drop,
+            )
+            .await;
+        server.trace_flusher.flush().await;
+
+        assert!(poll_for_mock_hit(&mut mock, 10, 100, 1, true).await);
+        assert_eq!(server.get_last_agent_status(&instance_id), Some(413));
+
+        // Querying an unknown session does not create it
+        let unknown = InstanceId::new("unknown", "2b5e1aa6-ac16-4d84-8f3b-7dbf2aa3a9d4");
+        assert_eq!(server.get_last_agent_status(&unknown), None);
+        assert!(server.lock_sessions().get("unknown").is_none());
+
+        server.remote_configs.shutdown();
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_telemetry_last_heartbeat_unknown_instance() {
+        let server = SidecarServer::default();
+        let instance_id = InstanceId::new("unknown", "2b5e1aa6-ac16-4d84-8f3b-7dbf2aa3a9d4");
+        let last_heartbeat = server
+            .clone()
+            .telemetry_last_heartbeat(tarpc::context::current(), instance_id)
+            .await;
+        assert_eq!(last_heartbeat, None);
+        // Querying does not create the session
+        assert!(server.lock_sessions().get("unknown").is_none());
+
+        server.remote_configs.shutdown();
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_session_throughput() {
+        let agent = MockServer::start();
+        let _mock = agent
+            .mock_async(|when, then| {
+                when.path("/v0.4/traces");
+                then.status(200)
+                    .header("content-type", "application/json")
+                    .body(r#"{"rate_by_service":{}}"#);
+            })
+            .await;
+
+        let server = SidecarServer::default();
+        let instance_id = InstanceId::new("session", "2b5e1aa6-ac16-4d84-8f3b-7dbf2aa3a9d4");
+        server
+            .get_session(&instance_id.session_id)
+            .modify_trace_config(|cfg| {
+                cfg.set_endpoint(Endpoint::from_slice(&agent.url("")))
+                    .unwrap();
+            });
+
+        let headers: SerializedTracerHeaderTags = TracerHeaderTags {
+            lang: "php",
+            ..Default::default()
+        }
+        .try_into()
+        .unwrap();
+        let endpoint = server
+            .get_trace_endpoint(&instance_id, None)
+            .unwrap()
+            .unwrap();
+
+        let mut previous = ThroughputStats::default();
+        for batch in 1..=3 {
+            let traces: Vec<_> = (0..batch)
+                .map(|i| vec![create_test_span(i + 1, 2, 0, 1, true)])
+                .collect();
+            let data = rmp_serde::to_vec_named(&traces).unwrap();
+            server
+                .send_trace_v04(
+                    &instance_id,
+                    &headers,
+                    tinybytes::Bytes::from(data),
+                    &endpoint,
+                    BackpressureMode::Drop,
+                )
+                .await;
+
+            let throughput = server
+                .clone()
+                .session_throughput(tarpc::context::current(), "session".to_string())
+                .await;
+            assert!(throughput.bytes > previous.bytes);
+            assert_eq!(throughput.traces, previous.traces + batch);
+            assert_eq!(throughput.payloads, previous.payloads + 1);
+            previous = throughput;
+        }
+
+        // Sending traces of an unknown session does not create it
+        let unknown_instance_id =
+            InstanceId::new("unknown", "2b5e1aa6-ac16-4d84-8f3b-7dbf2aa3a9d4");
+        let data = rmp_serde::to_vec_named(&[vec![create_test_span(1, 2, 0, 1, true)]]).unwrap();
+        server
+            .send_trace_v04(
+                &unknown_instance_id,
+                &headers,
+                tinybytes::Bytes::from(data),
+                &endpoint,
+                BackpressureMode::Drop,
+            )
+            .await;
+        assert!(!server.lock_sessions().contains_key("unknown"));
+        let unknown = server
+            .clone()
+            .session_throughput(tarpc::context::current(), "unknown".to_string())
+            .await;
+        assert_eq!(unknown, ThroughputStats::default());
+
+        server.trace_flusher.flush().await;
+        server.remote_configs.shutdown();
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_send_debugger_data_shm_chunk() {
+        use crate::service::blocking::{chunk_ranges, serialize_to_shm};
+
+        let payloads: Vec<String> = (0..100).map(|i| format!("payload number {i}")).collect();
+        let agent = MockServer::start();
+        let mut mock = agent
+            .mock_async(|when, then| {
+                when.path("/debugger/v1/input")
+                    .body(serde_json::to_string(&payloads).unwrap());
+                then.status(200);
+            })
+            .await;
+
+        let server = SidecarServer::default();
+        let instance_id = InstanceId::new("session", "2b5e1aa6-ac16-4d84-8f3b-7dbf2aa3a9d4");
+        let queue_id = QueueId::new_unique();
+        let session = server.get_session(&instance_id.session_id);
+        session.modify_debugger_config(|cfg| {
+            let endpoint = Endpoint::from_slice(&agent.url(""));
+            cfg.set_endpoint(endpoint.clone(), endpoint).unwrap();
+        });
+        session
+            .get_runtime(&instance_id.runtime_id)
+            .lock_applications()
+            .insert(queue_id, ActiveApplication::default());
+
+        let ranges = chunk_ranges(&payloads, 200);
+        assert!(ranges.len() > 1);
+        let last = ranges.len() - 1;
+        for (i, range) in ranges.into_iter().enumerate() {
+            server
+                .clone()
+                .send_debugger_data_shm_chunk(
+                    tarpc::context::current(),
+                    instance_id.clone(),
+                    queue_id,
+                    serialize_to_shm(&payloads[range]).unwrap(),
+                    DebuggerType::Logs,
+                    i != last,
+                )
+                .await;
+        }
+
+        // The payloads are forwarded at once, reassembled
+        assert!(poll_for_mock_hit(&mut mock, 25, 100, 1, false).await);
+        server.remote_configs.shutdown();
+    }
+}
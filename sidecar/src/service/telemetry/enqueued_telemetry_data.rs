@@ -82,6 +82,14 @@ impl EnqueuedTelemetryData {
                 SidecarAction::Telemetry(TelemetryActions::AddIntegration(i)) => {
                     self.integrations.insert(i)
                 }
+                SidecarAction::Telemetry(TelemetryActions::Reset) => {
+                    self.dependencies.clear();
+                    self.configurations.clear();
+                    self.integrations.clear();
+                    self.computed_dependencies.clear();
+                    self.actions
+                        .retain(|a| !matches!(a, TelemetryActions::AddLog(_)));
+                }
                 SidecarAction::Telemetry(other) => self.actions.push(other),
                 SidecarAction::PhpComposerTelemetryFile(composer_path) => self
                     .computed_dependencies
@@ -3,6 +3,7 @@
 
 use super::TraceSendData;
 use crate::agent_remote_config::AgentRemoteConfigWriter;
+use crate::service::BackpressureMode;
 use datadog_ipc::platform::NamedShmHandle;
 use datadog_trace_utils::trace_utils;
 use datadog_trace_utils::trace_utils::SendData;
@@ -19,7 +20,7 @@ use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::select;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Notify};
 use tokio::task::{JoinError, JoinHandle};
 use tracing::{debug, error, info};
 
@@ -94,7 +95,13 @@ pub(crate) struct TraceFlusher {
     pub(crate) interval_ms: AtomicU64,
     pub(crate) min_force_flush_size_bytes: AtomicU32,
     pub(crate) min_force_drop_size_bytes: AtomicU32, // put a limit on memory usage
+    /// The maximum request size accepted by the agent, by traces endpoint, if known
+    max_request_bytes: Mutex<HashMap<Endpoint, usize>>,
+    /// Notified whenever the queued traces are taken for flushing
+    queue_drained: Notify,
     remote_config: Mutex<AgentRemoteConfigs>,
+    /// The HTTP status of the last response received from each endpoint
+    last_status: Mutex<HashMap<Endpoint, u16>>,
     pub metrics: Mutex<TraceFlusherMetrics>,
 }
 impl Default for TraceFlusher {
@@ -104,7 +111,10 @@ impl Default for TraceFlusher {
             interval_ms: AtomicU64::new(DEFAULT_FLUSH_INTERVAL_MS),
             min_force_flush_size_bytes: AtomicU32::new(DEFAULT_MIN_FORCE_FLUSH_SIZE_BYTES),
             min_force_drop_size_bytes: AtomicU32::new(DEFAULT_MIN_FORCE_DROP_SIZE_BYTES),
+            max_request_bytes: Mutex::new(Default::default()),
+            queue_drained: Notify::new(),
             remote_config: Mutex::new(Default::default()),
+            last_status: Mutex::new(Default::default()),
             metrics: Mutex::new(Default::default()),
         }
     }
@@ -116,7 +126,11 @@ impl TraceFlusher {
     /// # Arguments
     ///
     /// * `data` - A `SendData` instance that needs to be added to the traces.
-    pub(crate) fn enqueue(self: &Arc<Self>, data: SendData) {
+    ///
+    /// # Returns
+    ///
+    /// * `false` if the data was dropped because the queue is full.
+    pub(crate) fn enqueue(self: &Arc<Self>, data: SendData) -> bool {
         let mut flush_data = self.inner.lock().unwrap();
         let flush_data = flush_data.deref_mut();
 
@@ -125,7 +139,7 @@ impl TraceFlusher {
         if flush_data.traces.send_data_size
             > self.min_force_drop_size_bytes.load(Ordering::Relaxed) as usize
         {
-            return;
+            return false;
         }
 
         flush_data.traces.send_data.push(data);
@@ -139,6 +153,52 @@ impl TraceFlusher {
         {
             flush_data.traces.flush();
         }
+        true
+    }
+
+    /// Enqueue a `SendData` like [`TraceFlusher::enqueue`], but if the queue is full and the
+    /// backpressure mode is `Block`, first waits up to its timeout for the queue to be flushed.
+    /// A payload larger than the whole queue is dropped without waiting.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - A `SendData` instance that needs to be added to the traces.
+    /// * `mode` - The backpressure mode of the session sending the traces.
+    ///
+    /// # Returns
+    ///
+    /// * `false` if the data was dropped because the queue is full.
+    pub(crate) async fn enqueue_with_backpressure(
+        self: &Arc<Self>,
+        data: SendData,
+        mode: BackpressureMode,
+    ) -> bool {
+        if let BackpressureMode::Block(timeout) = mode {
+            // No flush can make room for a payload exceeding the queue limit on its own
+            if data.len() > self.min_force_drop_size_bytes.load(Ordering::Relaxed) as usize {
+                debug!("Dropping a trace payload larger than the whole trace queue");
+                return false;
+            }
+            let deadline = tokio::time::Instant::now() + timeout;
+            loop {
+                // Created before checking the queue to not miss a drain happening meanwhile
+                let drained = self.queue_drained.notified();
+                {
+                    let mut flush_data = self.inner.lock().unwrap();
+                    if flush_data.traces.send_data_size + data.len()
+                        <= self.min_force_drop_size_bytes.load(Ordering::Relaxed) as usize
+                    {
+                        break;
+                    }
+                    flush_data.traces.flush();
+                }
+                if tokio::time::timeout_at(deadline, drained).await.is_err() {
+                    debug!("Timed out waiting for room in the trace queue");
+                    break;
+                }
+            }
+        }
+        self.enqueue(data)
     }
 
     /// Join the flusher task and flush the remaining traces.
@@ -235,17 +295,82 @@ impl TraceFlusher {
             },
         )
         .send_data;
-        trace_utils::coalesce_send_data(trace_buffer)
+        self.queue_drained.notify_waiters();
+        let mut by_max_request_bytes: HashMap<Option<usize>, Vec<SendData>> = HashMap::new();
+        {
+            let max_request_bytes = self.max_request_bytes.lock().unwrap();
+            for send_data in trace_buffer {
+                by_max_request_bytes
+                    .entry(max_request_bytes.get(send_data.get_target()).copied())
+                    .or_default()
+                    .push(send_data);
+            }
+        }
+        by_max_request_bytes
             .into_iter()
+            .flat_map(|(max_request_bytes, trace_buffer)| {
+                let Some(max_request_bytes) = max_request_bytes else {
+                    return trace_utils::coalesce_send_data(trace_buffer);
+                };
+                trace_utils::coalesce_send_data_with_max_size(trace_buffer, max_request_bytes)
+                    .into_iter()
+                    .filter(|send_data| {
+                        if send_data.len() > max_request_bytes {
+                            error!(
+                                "Dropping {} bytes of traces for {}, exceeding the \
+                                max_request_bytes of {} advertised by the agent",
+                                send_data.len(),
+                                send_data.get_target().url,
+                                max_request_bytes
+                            );
+                            return false;
+                        }
+                        true
+                    })
+                    .collect()
+            })
             .collect()
     }
 
+    /// Caps the size of the requests sent to the given traces endpoint, splitting the queued traces
+    /// into several requests if needed. Called with the `max_request_bytes` advertised by the
+    /// agent.
+    pub(crate) fn set_max_request_bytes(&self, endpoint: Endpoint, max_request_bytes: i64) {
+        if max_request_bytes > 0 {
+            debug!(
+                "Capping trace requests to {} to {max_request_bytes} bytes",
+                endpoint.url
+            );
+            self.max_request_bytes
+                .lock()
+                .unwrap()
+                .insert(endpoint, max_request_bytes as usize);
+        }
+    }
+
+    /// Sets the queue sizes above which the traces are flushed and new traces are dropped.
+    pub(crate) fn set_force_sizes(&self, force_flush_size: usize, force_drop_size: usize) {
+        self.min_force_flush_size_bytes
+            .store(force_flush_size as u32, Ordering::Relaxed);
+        self.min_force_drop_size_bytes
+            .store(force_drop_size as u32, Ordering::Relaxed);
+    }
+
+    /// Returns the HTTP status of the last response received from the given endpoint, if any.
+    pub(crate) fn last_status(&self, endpoint: &Endpoint) -> Option<u16> {
+        self.last_status.lock().unwrap().get(endpoint).copied()
+    }
+
     async fn send_and_handle_trace(&self, send_data: SendData) {
         let endpoint = send_data.get_target().clone();
         let response = send_data.send().await;
         self.metrics.lock().unwrap().update(&response);
         match response.last_result {
             Ok(response) => {
+                self.last_status
+                    .lock()
+                    .unwrap()
+                    .insert(endpoint.clone(), response.status().as_u16());
                 if endpoint.api_key.is_none() {
                     // not when intake
                     match response.into_body().collect().await {
@@ -428,4 +553,163 @@ mod tests {
 
         assert!(poll_for_mock_hit(&mut mock, 5, 250, 0, true).await);
     }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_set_force_sizes() {
+        // Set the interval high enough that only a force flush sends the traces
+        let trace_flusher = Arc::new(TraceFlusher {
+            interval_ms: AtomicU64::new(10_000),
+            ..TraceFlusher::default()
+        });
+        let server = MockServer::start();
+        let mut mock = server
+            .mock_async(|_when, then| {
+                then.status(202)
+                    .header("content-type", "application/json")
+                    .body(r#"{"status":"ok"}"#);
+            })
+            .await;
+        let target_endpoint = Endpoint {
+            url: server.url("").to_owned().parse().unwrap(),
+            api_key: Some("test-key".into()),
+            ..Default::default()
+        };
+
+        // Below the default force flush size
+        assert!(trace_flusher.enqueue(create_send_data(1_000, &target_endpoint)));
+        assert!(poll_for_mock_hit(&mut mock, 10, 100, 0, false).await);
+
+        trace_flusher.set_force_sizes(1_500, 100_000);
+        assert!(trace_flusher.enqueue(create_send_data(1_000, &target_endpoint)));
+        assert!(poll_for_mock_hit(&mut mock, 25, 100, 1, true).await);
+
+        trace_flusher.set_force_sizes(100, 500);
+        assert!(!trace_flusher.enqueue(create_send_data(1_000, &target_endpoint)));
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_split_by_max_request_bytes() {
+        // Set the interval high enough that only the explicit flush sends the traces
+        let trace_flusher = Arc::new(TraceFlusher {
+            interval_ms: AtomicU64::new(10_000),
+            ..TraceFlusher::default()
+        });
+        let capped_server = MockServer::start();
+        let uncapped_server = MockServer::start();
+        let mut mocks = vec![];
+        for server in [&capped_server, &uncapped_server] {
+            mocks.push(
+                server
+                    .mock_async(|_when, then| {
+                        then.status(202)
+                            .header("content-type", "application/json")
+                            .body(r#"{"status":"ok"}"#);
+                    })
+                    .await,
+            );
+        }
+        let endpoint = |server: &MockServer| Endpoint {
+            url: server.url("").to_owned().parse().unwrap(),
+            api_key: Some("test-key".into()),
+            ..Default::default()
+        };
+        let capped_endpoint = endpoint(&capped_server);
+        let uncapped_endpoint = endpoint(&uncapped_server);
+
+        // Without a cap all three would be coalesced into a single request
+        trace_flusher.set_max_request_bytes(capped_endpoint.clone(), 1_500);
+        for _ in 0..3 {
+            trace_flusher.enqueue(create_send_data(1_000, &capped_endpoint));
+            trace_flusher.enqueue(create_send_data(1_000, &uncapped_endpoint));
+        }
+        trace_flusher.flush().await;
+
+        let mut uncapped_mock = mocks.pop().unwrap();
+        let mut capped_mock = mocks.pop().unwrap();
+        assert!(poll_for_mock_hit(&mut capped_mock, 25, 100, 3, true).await);
+        assert!(poll_for_mock_hit(&mut uncapped_mock, 25, 100, 1, true).await);
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_backpressure_mode() {
+        let server = MockServer::start();
+        server
+            .mock_async(|_when, then| {
+                then.status(202)
+                    .header("content-type", "application/json")
+                    .body(r#"{"status":"ok"}"#)
+                    .delay(Duration::from_millis(500));
+            })
+            .await;
+        let target_endpoint = Endpoint {
+            url: server.url("").to_owned().parse().unwrap(),
+            api_key: Some("test-key".into()),
+            ..Default::default()
+        };
+
+        for mode in [
+            BackpressureMode::Drop,
+            BackpressureMode::Block(Duration::from_secs(5)),
+        ] {
+            let trace_flusher = Arc::new(TraceFlusher {
+                interval_ms: AtomicU64::new(10_000),
+                min_force_flush_size_bytes: AtomicU32::new(0),
+                min_force_drop_size_bytes: AtomicU32::new(1_000),
+                ..TraceFlusher::default()
+            });
+
+            // The first trace is being sent to the slow agent while the second one fills the queue
+            trace_flusher.enqueue(create_send_data(1_000, &target_endpoint));
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            trace_flusher.enqueue(create_send_data(1_000, &target_endpoint));
+
+            let start = Instant::now();
+            trace_flusher
+                .enqueue_with_backpressure(create_send_data(1_000, &target_endpoint), mode)
+                .await;
+            match mode {
+                BackpressureMode::Drop => {
+                    assert!(start.elapsed() < Duration::from_millis(200));
+                }
+                BackpressureMode::Block(_) => {
+                    assert!(start.elapsed() >= Duration::from_millis(200));
+                    assert_eq!(
+                        trace_flusher.inner.lock().unwrap().traces.send_data_size,
+                        1_000
+                    );
+                }
+            }
+        }
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_backpressure_oversized_payload() {
+        let target_endpoint = Endpoint {
+            url: "http://127.0.0.1:1".parse().unwrap(),
+            ..Default::default()
+        };
+        let trace_flusher = Arc::new(TraceFlusher {
+            interval_ms: AtomicU64::new(10_000),
+            min_force_drop_size_bytes: AtomicU32::new(1_000),
+            ..TraceFlusher::default()
+        });
+
+        let start = Instant::now();
+        assert!(
+            !trace_flusher
+                .enqueue_with_backpressure(
+                    create_send_data(2_000, &target_endpoint),
+                    BackpressureMode::Block(Duration::from_secs(5)),
+                )
+                .await
+        );
+        assert!(start.elapsed() < Duration::from_secs(1));
+        // The queue is left untouched
+        assert_eq!(trace_flusher.inner.lock().unwrap().traces.send_data_size, 0);
+        assert!(trace_flusher.enqueue(create_send_data(1_000, &target_endpoint)));
+    }
 }
@@ -20,7 +20,7 @@ use priority_queue::PriorityQueue;
 use sha2::{Digest, Sha224};
 use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::default::Default;
 use std::ffi::{CStr, CString};
 use std::hash::{Hash, Hasher};
@@ -36,7 +36,16 @@ use tracing::{debug, error, trace, warn};
 use zwohash::ZwoHasher;
 
 pub struct RemoteConfigWriter(OneWayShmWriter<NamedShmHandle>);
-pub struct RemoteConfigReader(OneWayShmReader<NamedShmHandle, CString>);
+pub struct RemoteConfigReader {
+    reader: OneWayShmReader<NamedShmHandle, CString>,
+    /// Unknown if the reader was created from a path.
+    invariants: Option<ConfigInvariants>,
+    /// Unknown if the reader was created from a path.
+    target: Option<Arc<Target>>,
+    /// The configuration files listed in any contents returned by [`RemoteConfigReader::read`],
+    /// until pruned.
+    known_configs: HashSet<String>,
+}
 
 /// Function to dump the invariants and target, and the corresponding path.
 /// This is useful for debugging purposes.
@@ -76,22 +85,102 @@ pub fn path_for_remote_config(id: &ConfigInvariants, target: &Arc<Target>) -> CS
 impl RemoteConfigReader {
     pub fn new(id: &ConfigInvariants, target: &Arc<Target>) -> RemoteConfigReader {
         let path = path_for_remote_config(id, target);
-        RemoteConfigReader(OneWayShmReader::new(open_named_shm(&path).ok(), path))
+        RemoteConfigReader {
+            reader: OneWayShmReader::new(open_named_shm(&path).ok(), path),
+            invariants: Some(id.clone()),
+            target: Some(target.clone()),
+            known_configs: HashSet::new(),
+        }
     }
 
     pub fn from_path(path: &CStr) -> Self {
-        RemoteConfigReader(OneWayShmReader::new(
-            open_named_shm(path).ok(),
-            CString::new(path.to_bytes()).unwrap(),
-        ))
+        RemoteConfigReader {
+            reader: OneWayShmReader::new(
+                open_named_shm(path).ok(),
+                CString::new(path.to_bytes()).unwrap(),
+            ),
+            invariants: None,
+            target: None,
+            known_configs: HashSet::new(),
+        }
     }
 
     pub fn get_path(&self) -> &CStr {
-        &self.0.extra
+        &self.reader.extra
+    }
+
+    /// The invariants this reader was created for, `None` if it was created from a path.
+    pub fn invariants(&self) -> Option<&ConfigInvariants> {
+        self.invariants.as_ref()
+    }
+
+    /// The target this reader was created for, `None` if it was created from a path.
+    pub fn target(&self) -> Option<&Arc<Target>> {
+        self.target.as_ref()
+    }
+
+    /// Summarizes the path, invariants and target of this reader as JSON, for debugging purposes.
+    pub fn describe(&self) -> String {
+        serde_json::json!({
+            "path": self.get_path().to_string_lossy(),
+            "invariants": self.invariants.as_ref().map(|id| serde_json::json!({
+                "language": id.language,
+                "tracer_version": id.tracer_version,
+                "endpoint": id.endpoint.url.to_string(),
+                "products": id.products.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+                "capabilities": id.capabilities,
+            })),
+            "target": self.target.as_deref(),
+        })
+        .to_string()
     }
 
     pub fn read(&mut self) -> (bool, &[u8]) {
-        self.0.read()
+        let (changed, data) = self.reader.read();
+        if changed {
+            if let Ok((_, configs)) = parse_configs_snapshot(data) {
+                self.known_configs.extend(configs);
+            }
+        }
+        (changed, data)
+    }
+
+    /// Forgets the known configuration files which are no longer present in the contents last
+    /// returned by [`RemoteConfigReader::read`], i.e. the ones removed server-side since.
+    /// Returns the number of configuration files pruned.
+    pub fn prune_stale(&mut self) -> usize {
+        let Ok((_, configs)) = parse_configs_snapshot(self.reader.last_read()) else {
+            return 0;
+        };
+        let previous_len = self.known_configs.len();
+        self.known_configs.retain(|config| configs.contains(config));
+        previous_len - self.known_configs.len()
+    }
+
+    /// The number of configuration files read and not pruned yet.
+    pub fn len(&self) -> usize {
+        self.known_configs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.known_configs.is_empty()
+    }
+
+    /// The ids of the configurations listed in the contents last returned by
+    /// [`RemoteConfigReader::read`], i.e. the ones the tracer applied, e.g. to report them in the
+    /// remote config client state. Each id is reported once, in the order they are listed.
+    pub fn applied_config_ids(&self) -> Vec<String> {
+        let Ok((_, configs)) = parse_configs_snapshot(self.reader.last_read()) else {
+            return vec![];
+        };
+        let mut ids = vec![];
+        for config in &configs {
+            match config_id(config) {
+                Some(id) if !ids.contains(&id) => ids.push(id),
+                _ => {}
+            }
+        }
+        ids
     }
 }
 
@@ -174,7 +263,7 @@ fn store_shm(
     // as much signal as possible to be collision free
     let hashed_path = BASE64_URL_SAFE_NO_PAD.encode(Sha224::digest(path.to_string()));
     #[cfg(target_os = "macos")]
-    let sliced_path = &hashed_path[..30 - name.len()];
+    let sliced_path = &hashed_path[..30usize.saturating_sub(name.len())];
     #[cfg(not(target_os = "macos"))]
     let sliced_path = &hashed_path;
     let name = format!("/{}-{}", name, sliced_path);
@@ -376,6 +465,45 @@ fn read_config(path: &str) -> anyhow::Result<(RemoteConfigValue, u32)> {
     }
 }
 
+/// Extracts the config id out of an entry written by [`ConfigFileStorage::fetched`].
+fn config_id(entry: &str) -> Option<String> {
+    let (_, rc_path) = entry.rsplit_once(':')?;
+    let rc_path = String::from_utf8(BASE64_URL_SAFE_NO_PAD.decode(rc_path).ok()?).ok()?;
+    Some(
+        RemoteConfigPath::try_parse(&rc_path)
+            .ok()?
+            .config_id
+            .to_string(),
+    )
+}
+
+/// Parses the runtime id and the active configuration files out of the contents written by
+/// [`ConfigFileStorage::fetched`].
+fn parse_configs_snapshot(data: &[u8]) -> Result<(&str, Vec<String>), std::str::Utf8Error> {
+    let mut configs = vec![];
+    let mut runtime_id: &[u8] = b"";
+    if !data.is_empty() {
+        let mut i = 0;
+        while i < data.len() {
+            if data[i] == b'\n' {
+                break;
+            }
+            i += 1;
+        }
+        runtime_id = &data[0..i];
+        i += 1;
+        let mut start = i;
+        while i < data.len() {
+            if data[i] == b'\n' {
+                configs.push(std::str::from_utf8(&data[start..i])?.to_string());
+                start = i + 1;
+            }
+            i += 1;
+        }
+    }
+    Ok((std::str::from_utf8(runtime_id)?, configs))
+}
+
 /// Manages configs.
 /// Returns changes to configurations.
 /// Switching targets is supported; Remove and Add operations will be yielded upon the next
@@ -429,43 +557,13 @@ impl RemoteConfigManager {
 
             let (changed, data) = reader.read();
             if changed {
-                'fetch_new: {
-                    let mut configs = vec![];
-                    let mut runtime_id: &[u8] = b"";
-                    if !data.is_empty() {
-                        let mut i = 0;
-                        while i < data.len() {
-                            if data[i] == b'\n' {
-                                break;
-                            }
-                            i += 1;
-                        }
-                        runtime_id = &data[0..i];
-                        i += 1;
-                        let mut start = i;
-                        while i < data.len() {
-                            if data[i] == b'\n' {
-                                match std::str::from_utf8(&data[start..i]) {
-                                    Ok(s) => configs.push(s.to_string()),
-                                    Err(e) => {
-                                        warn!("Failed reading received configurations {e:?}");
-                                        break 'fetch_new;
-                                    }
-                                }
-                                start = i + 1;
-                            }
-                            i += 1;
-                        }
+                match parse_configs_snapshot(data) {
+                    Ok((runtime_id, configs)) => {
+                        self.current_runtime_id = runtime_id.to_string();
+                        self.last_read_configs = configs;
+                        self.check_configs = self.active_configs.keys().cloned().collect();
                     }
-                    match std::str::from_utf8(runtime_id) {
-                        Ok(s) => self.current_runtime_id = s.to_string(),
-                        Err(e) => {
-                            warn!("Failed reading received configurations {e:?}");
-                            break 'fetch_new;
-                        }
-                    }
-                    self.last_read_configs = configs;
-                    self.check_configs = self.active_configs.keys().cloned().collect();
+                    Err(e) => warn!("Failed reading received configurations {e:?}"),
                 }
 
                 while let Some((_, Reverse(instant))) = self.unexpired_targets.peek() {
@@ -516,6 +614,48 @@ impl RemoteConfigManager {
         RemoteConfigUpdate::None
     }
 
+    /// Updates the remembered configuration files of inactive targets to the latest snapshot
+    /// written by the sidecar, forgetting the ones no longer present. Inactive targets without any
+    /// configuration file left are dropped altogether.
+    /// Returns the number of configuration files pruned.
+    pub fn prune_stale(&mut self) -> usize {
+        let mut pruned = 0;
+        let mut dropped_targets = vec![];
+        self.encountered_targets
+            .retain(|target, (reader, last_fetch)| {
+                let (changed, data) = reader.read();
+                if changed {
+                    match parse_configs_snapshot(data) {
+                        Ok((_, configs)) => {
+                            // The snapshot was consumed from the reader, so it replaces the
+                            // remembered configs, including the ones first appearing in it
+                            pruned += last_fetch
+                                .iter()
+                                .filter(|config| !configs.contains(config))
+                                .count();
+                            *last_fetch = configs;
+                        }
+                        Err(e) => warn!("Failed reading received configurations {e:?}"),
+                    }
+                }
+                if last_fetch.is_empty() {
+                    dropped_targets.push(target.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+        for target in dropped_targets {
+            self.unexpired_targets.remove(&target);
+        }
+        pruned
+    }
+
+    /// The number of inactive targets whose readers and configuration files are still remembered.
+    pub fn encountered_targets_len(&self) -> usize {
+        self.encountered_targets.len()
+    }
+
     fn set_target(&mut self, target: Option<Arc<Target>>) {
         let mut current_configs = std::mem::take(&mut self.last_read_configs);
         if let Some(old_target) = std::mem::replace(&mut self.active_target, target) {
@@ -635,6 +775,35 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_reader_describe() {
+        let invariants = ConfigInvariants {
+            language: "php".to_string(),
+            tracer_version: "1.2.3".to_string(),
+            endpoint: Default::default(),
+            products: vec![RemoteConfigProduct::ApmTracing],
+            capabilities: vec![],
+        };
+        let reader = RemoteConfigReader::new(&invariants, &DUMMY_TARGET);
+        assert_eq!(reader.invariants(), Some(&invariants));
+        assert_eq!(reader.target(), Some(&*DUMMY_TARGET));
+
+        let description: serde_json::Value = serde_json::from_str(&reader.describe()).unwrap();
+        assert_eq!(description["target"]["service"], "service");
+        assert_eq!(description["target"]["env"], "env");
+        assert_eq!(description["invariants"]["products"][0], "APM_TRACING");
+        assert_eq!(
+            description["path"],
+            reader.get_path().to_string_lossy().as_ref()
+        );
+
+        let reader = RemoteConfigReader::from_path(reader.get_path());
+        assert!(reader.invariants().is_none());
+        let description: serde_json::Value = serde_json::from_str(&reader.describe()).unwrap();
+        assert!(description["target"].is_null());
+    }
+
     #[tokio::test]
     #[cfg_attr(miri, ignore)]
     async fn test_shm_updates() {
@@ -815,4 +984,227 @@ mod tests {
 
         assert!(matches!(manager.fetch_update(), RemoteConfigUpdate::None));
     }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_applied_config_ids() {
+        let server = RemoteConfigServer::spawn();
+
+        let (on_dead, on_dead_completer) = ManualFuture::new();
+        let shm = ShmRemoteConfigs::new(
+            server.dummy_invariants(),
+            Box::new(|| {
+                tokio::spawn(on_dead_completer.complete(()));
+            }),
+            Duration::from_millis(10),
+        );
+
+        {
+            let mut files = server.files.lock().unwrap();
+            for path in [&*PATH_FIRST, &*PATH_SECOND] {
+                files.insert(
+                    path.clone(),
+                    (
+                        vec![DUMMY_TARGET.clone()],
+                        1,
+                        serde_json::to_string(&dummy_dynamic_config(true)).unwrap(),
+                    ),
+                );
+            }
+        }
+
+        let mut reader = RemoteConfigReader::new(&server.dummy_invariants(), &DUMMY_TARGET);
+        assert!(reader.applied_config_ids().is_empty());
+
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        let shm_guard = shm.add_runtime(
+            "3b43524b-a70c-45dc-921d-34504e50c5eb".to_string(),
+            NotifyDummy(Arc::new(sender)),
+            DUMMY_TARGET.env.to_string(),
+            DUMMY_TARGET.service.to_string(),
+            DUMMY_TARGET.app_version.to_string(),
+            DUMMY_TARGET.tags.clone(),
+        );
+
+        receiver.recv().await;
+
+        // Only the contents which were read are reported
+        assert!(reader.applied_config_ids().is_empty());
+        assert!(reader.read().0);
+        let mut ids = reader.applied_config_ids();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![PATH_FIRST.config_id.clone(), PATH_SECOND.config_id.clone()]
+        );
+
+        drop(shm_guard);
+        shm.shutdown();
+
+        on_dead.await;
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_prune_stale() {
+        let server = RemoteConfigServer::spawn();
+
+        let (on_dead, on_dead_completer) = ManualFuture::new();
+        let shm = ShmRemoteConfigs::new(
+            server.dummy_invariants(),
+            Box::new(|| {
+                tokio::spawn(on_dead_completer.complete(()));
+            }),
+            Duration::from_millis(10),
+        );
+
+        let mut manager = RemoteConfigManager::new(server.dummy_invariants());
+
+        {
+            let mut files = server.files.lock().unwrap();
+            files.insert(
+                PATH_FIRST.clone(),
+                (
+                    vec![DUMMY_TARGET.clone()],
+                    1,
+                    serde_json::to_string(&dummy_dynamic_config(true)).unwrap(),
+                ),
+            );
+        }
+
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        let shm_guard = shm.add_runtime(
+            "3b43524b-a70c-45dc-921d-34504e50c5eb".to_string(),
+            NotifyDummy(Arc::new(sender)),
+            DUMMY_TARGET.env.to_string(),
+            DUMMY_TARGET.service.to_string(),
+            DUMMY_TARGET.app_version.to_string(),
+            DUMMY_TARGET.tags.clone(),
+        );
+
+        receiver.recv().await;
+
+        manager.track_target(&DUMMY_TARGET);
+        assert!(matches!(
+            manager.fetch_update(),
+            RemoteConfigUpdate::Add { .. }
+        ));
+        assert!(matches!(manager.fetch_update(), RemoteConfigUpdate::None));
+
+        // Move on to another target, the configs of the previous one are remembered
+        let other_target = Arc::new(Target {
+            service: "other".to_string(),
+            ..(**DUMMY_TARGET).clone()
+        });
+        manager.track_target(&other_target);
+        assert_eq!(manager.encountered_targets_len(), 1);
+        assert_eq!(manager.prune_stale(), 0);
+
+        // A config added while the target is inactive is remembered too
+        server.files.lock().unwrap().insert(
+            PATH_SECOND.clone(),
+            (
+                vec![DUMMY_TARGET.clone()],
+                1,
+                serde_json::to_string(&dummy_dynamic_config(true)).unwrap(),
+            ),
+        );
+        receiver.recv().await;
+        assert_eq!(manager.prune_stale(), 0);
+
+        server.files.lock().unwrap().remove(&*PATH_FIRST);
+        receiver.recv().await;
+
+        assert_eq!(manager.prune_stale(), 1);
+        assert_eq!(manager.encountered_targets_len(), 1);
+
+        // Switching back reports the changes which happened in the meantime
+        manager.track_target(&DUMMY_TARGET);
+        match manager.fetch_update() {
+            RemoteConfigUpdate::Remove(path) => assert_eq!(path.config_id, PATH_FIRST.config_id),
+            update => panic!("Expected a Remove, got {update:?}"),
+        }
+        match manager.fetch_update() {
+            RemoteConfigUpdate::Add { value, .. } => {
+                assert_eq!(value.config_id, PATH_SECOND.config_id)
+            }
+            update => panic!("Expected an Add, got {update:?}"),
+        }
+        assert!(matches!(manager.fetch_update(), RemoteConfigUpdate::None));
+
+        manager.track_target(&other_target);
+        server.files.lock().unwrap().remove(&*PATH_SECOND);
+        receiver.recv().await;
+
+        assert_eq!(manager.prune_stale(), 1);
+        assert_eq!(manager.encountered_targets_len(), 0);
+
+        drop(shm_guard);
+        shm.shutdown();
+
+        on_dead.await;
+    }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_reader_prune_stale() {
+        let server = RemoteConfigServer::spawn();
+
+        let (on_dead, on_dead_completer) = ManualFuture::new();
+        let shm = ShmRemoteConfigs::new(
+            server.dummy_invariants(),
+            Box::new(|| {
+                tokio::spawn(on_dead_completer.complete(()));
+            }),
+            Duration::from_millis(10),
+        );
+
+        {
+            let mut files = server.files.lock().unwrap();
+            for path in [&*PATH_FIRST, &*PATH_SECOND] {
+                files.insert(
+                    path.clone(),
+                    (
+                        vec![DUMMY_TARGET.clone()],
+                        1,
+                        serde_json::to_string(&dummy_dynamic_config(true)).unwrap(),
+                    ),
+                );
+            }
+        }
+
+        let mut reader = RemoteConfigReader::new(&server.dummy_invariants(), &DUMMY_TARGET);
+
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(1);
+        let shm_guard = shm.add_runtime(
+            "3b43524b-a70c-45dc-921d-34504e50c5eb".to_string(),
+            NotifyDummy(Arc::new(sender)),
+            DUMMY_TARGET.env.to_string(),
+            DUMMY_TARGET.service.to_string(),
+            DUMMY_TARGET.app_version.to_string(),
+            DUMMY_TARGET.tags.clone(),
+        );
+
+        receiver.recv().await;
+
+        assert!(reader.read().0);
+        assert_eq!(reader.len(), 2);
+        assert_eq!(reader.prune_stale(), 0);
+
+        server.files.lock().unwrap().remove(&*PATH_FIRST);
+        receiver.recv().await;
+
+        // Pruning only considers the contents which were read
+        assert_eq!(reader.prune_stale(), 0);
+        assert!(reader.read().0);
+        assert_eq!(reader.len(), 2);
+        assert_eq!(reader.prune_stale(), 1);
+        assert_eq!(reader.len(), 1);
+        assert_eq!(reader.prune_stale(), 0);
+
+        drop(shm_guard);
+        shm.shutdown();
+
+        on_dead.await;
+    }
 }
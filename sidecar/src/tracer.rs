@@ -25,12 +25,22 @@ impl Config {
             parts.path_and_query = Some(PathAndQuery::from_static("/v0.4/traces"));
             hyper::Uri::from_parts(parts)?
         };
-        self.endpoint = Some(Endpoint {
-            url: uri,
-            ..endpoint
-        });
+        self.endpoint = Some(endpoint.with_url(uri));
         Ok(())
     }
+
+    /// Returns the traces endpoint of the given agent, keeping the other settings (timeout, test
+    /// session token) of the configured endpoint.
+    pub fn endpoint_for(&self, agent: Endpoint) -> anyhow::Result<Endpoint> {
+        let mut config = Config::default();
+        config.set_endpoint(Endpoint {
+            url: agent.url,
+            api_key: agent.api_key,
+            resolved_addr: agent.resolved_addr,
+            ..self.endpoint.clone().unwrap_or_default()
+        })?;
+        Ok(config.endpoint.unwrap_or_default())
+    }
 }
 
 pub fn shm_limiter_path() -> CString {
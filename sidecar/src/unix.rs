@@ -7,11 +7,13 @@ use std::ffi::CString;
 use std::os::unix::net::UnixListener as StdUnixListener;
 
 use crate::config::FromEnv;
-use crate::enter_listener_loop;
+use crate::{enter_listener_loop, namespaced_identifier};
+use datadog_ipc::platform::shm_namespace;
 use nix::fcntl::{fcntl, OFlag, F_GETFL, F_SETFL};
 use nix::sys::socket::{shutdown, Shutdown};
 use std::io;
 use std::os::unix::prelude::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd};
+use std::sync::OnceLock;
 use std::time::Instant;
 use tokio::net::{UnixListener, UnixStream};
 use tracing::{error, info};
@@ -94,8 +96,14 @@ pub fn setup_daemon_process(
     Ok(())
 }
 
-pub fn primary_sidecar_identifier() -> u32 {
-    unsafe { libc::geteuid() }
+/// Identifies the sidecar shared by the processes of a user within the configured
+/// [`shm_namespace`].
+pub fn primary_sidecar_identifier() -> &'static str {
+    static IDENTIFIER: OnceLock<String> = OnceLock::new();
+    IDENTIFIER.get_or_init(|| {
+        let euid = unsafe { libc::geteuid() };
+        namespaced_identifier(shm_namespace(), &euid.to_string())
+    })
 }
 
 fn maybe_start_appsec() -> bool {
@@ -1,10 +1,10 @@
 // Copyright 2021-Present Datadog, Inc. https://www.datadoghq.com/
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::enter_listener_loop;
 use crate::setup::pid_shm_path;
+use crate::{enter_listener_loop, namespaced_identifier};
 use datadog_ipc::platform::{
-    named_pipe_name_from_raw_handle, FileBackedHandle, MappedMem, NamedShmHandle,
+    named_pipe_name_from_raw_handle, shm_namespace, FileBackedHandle, MappedMem, NamedShmHandle,
 };
 use futures::FutureExt;
 use lazy_static::lazy_static;
@@ -129,7 +129,8 @@ pub fn setup_daemon_process(listener: OwnedHandle, spawn_cfg: &mut SpawnWorker)
 }
 
 lazy_static! {
-    static ref SIDECAR_IDENTIFIER: String = fetch_sidecar_identifier();
+    static ref SIDECAR_IDENTIFIER: String =
+        namespaced_identifier(shm_namespace(), &fetch_sidecar_identifier());
 }
 
 fn fetch_sidecar_identifier() -> String {
@@ -46,6 +46,13 @@ impl BytesString {
         })
     }
 
+    /// Creates a `BytesString` from a static string, without copying, e.g. for interned names.
+    pub const fn from_static(value: &'static str) -> Self {
+        Self {
+            bytes: Bytes::from_static_str(value),
+        }
+    }
+
     /// Creates a `BytesString` from a `tinybytes::Bytes` instance.
     ///
     /// This function validates that the provided `Bytes` instance contains valid UTF-8 data. If the
@@ -144,8 +151,7 @@ impl From<String> for BytesString {
 
 impl From<&'static str> for BytesString {
     fn from(value: &'static str) -> Self {
-        // SAFETY: This is safe as a str is always a valid UTF-8 slice.
-        unsafe { Self::from_bytes_unchecked(Bytes::from_static(value.as_bytes())) }
+        Self::from_static(value)
     }
 }
 
@@ -238,6 +244,16 @@ mod tests {
         assert_eq!(bytes_string.as_str(), "hello")
     }
 
+    #[test]
+    fn test_from_static() {
+        const BYTES_STRING: BytesString = BytesString::from_static("hello");
+        assert_eq!(BYTES_STRING.as_str(), "hello");
+
+        static HELLO: &str = "hello";
+        let bytes_string = BytesString::from_static(HELLO);
+        assert_eq!(bytes_string.as_str().as_ptr(), HELLO.as_ptr());
+    }
+
     fn calculate_hash<T: Hash>(t: &T) -> u64 {
         let mut s = DefaultHasher::new();
         t.hash(&mut s);
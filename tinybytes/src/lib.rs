@@ -33,11 +33,17 @@ impl Bytes {
 
     /// Creates `Bytes` from a static slice.
     #[inline]
-    pub fn from_static(value: &'static [u8]) -> Self {
+    pub const fn from_static(value: &'static [u8]) -> Self {
         let slice: &[u8] = value;
         Self { slice, bytes: None }
     }
 
+    /// Creates `Bytes` from a static string, without copying, e.g. for interned names.
+    #[inline]
+    pub const fn from_static_str(value: &'static str) -> Self {
+        Self::from_static(value.as_bytes())
+    }
+
     /// Creates `Bytes` from a slice, by copying.
     pub fn copy_from_slice(data: &[u8]) -> Self {
         Self::from_underlying(data.to_vec())
@@ -55,6 +61,25 @@ impl Bytes {
         self.slice.is_empty()
     }
 
+    /// Returns the underlying `'static` slice if the `Bytes` is backed by static memory, e.g.
+    /// created with `from_static`, and `None` if it references reference-counted bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tinybytes::Bytes;
+    ///
+    /// assert_eq!(Bytes::from_static(b"hello").as_static(), Some(&b"hello"[..]));
+    /// assert_eq!(Bytes::copy_from_slice(b"hello").as_static(), None);
+    /// ```
+    #[inline]
+    pub fn as_static(&self) -> Option<&'static [u8]> {
+        match self.bytes {
+            None => Some(self.slice),
+            Some(_) => None,
+        }
+    }
+
     /// Returns a slice of self for the provided range.
     ///
     /// This will return a new `Bytes` handle set to the slice, and will not copy the underlying
@@ -115,6 +140,47 @@ impl Bytes {
         }
     }
 
+    /// Returns a slice of self for the provided range, or `None` if the range does not conform to
+    /// `start <= end` and `end <= self.len()`.
+    ///
+    /// This is the non-panicking version of [`Bytes::slice`], useful to validate offsets coming
+    /// from an untrusted source, e.g. a decoder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tinybytes::Bytes;
+    ///
+    /// let bytes = Bytes::copy_from_slice(b"hello world");
+    /// assert_eq!(bytes.try_slice(6..11).unwrap().as_ref(), b"world");
+    /// assert!(bytes.try_slice(6..12).is_none());
+    /// ```
+    pub fn try_slice(&self, range: impl RangeBounds<usize>) -> Option<Self> {
+        use std::ops::Bound;
+
+        let len = self.len();
+
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n.checked_add(1)?,
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n.checked_add(1)?,
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => len,
+        };
+
+        if start > end || end > len {
+            None
+        } else if end == start {
+            Some(Bytes::empty())
+        } else {
+            Some(self.safe_slice_ref(start, end))
+        }
+    }
+
     /// Returns a slice of self that is equivalent to the given `subset`, if it is a subset.
     ///
     /// When processing a `Bytes` buffer with other tools, one often gets a
@@ -158,6 +224,56 @@ impl Bytes {
         }
     }
 
+    /// Returns an iterator over `size` bytes long slices of self, the last one being shorter if the
+    /// length of self is not a multiple of `size`. The slices share the underlying bytes of self.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tinybytes::Bytes;
+    ///
+    /// let bytes = Bytes::copy_from_slice(b"hello world");
+    /// let mut chunks = bytes.chunks(5);
+    /// assert_eq!(chunks.next().unwrap().as_ref(), b"hello");
+    /// assert_eq!(chunks.next().unwrap().as_ref(), b" worl");
+    /// assert_eq!(chunks.next().unwrap().as_ref(), b"d");
+    /// assert!(chunks.next().is_none());
+    /// ```
+    pub fn chunks(&self, size: usize) -> impl Iterator<Item = Bytes> {
+        assert!(size != 0, "chunk size must be non-zero");
+        let bytes = self.clone();
+        (0..self.len())
+            .step_by(size)
+            .map(move |start| bytes.safe_slice_ref(start, usize::min(start + size, bytes.len())))
+    }
+
+    /// Returns a copy of self which does not reference the underlying bytes of self, e.g. to keep
+    /// a small slice of a large buffer without keeping the whole buffer alive.
+    ///
+    /// Static bytes are not copied, since there is no underlying allocation to release.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tinybytes::Bytes;
+    ///
+    /// let bytes = Bytes::copy_from_slice(b"hello world");
+    /// let detached = bytes.slice(0..5).detach();
+    /// drop(bytes);
+    /// assert_eq!(detached.as_ref(), b"hello");
+    /// ```
+    pub fn detach(&self) -> Self {
+        match self.bytes {
+            None => self.clone(),
+            Some(_) if self.is_empty() => Bytes::empty(),
+            Some(_) => Self::copy_from_slice(self.slice),
+        }
+    }
+
     /// Returns a mutable reference to the slice of self.
     /// Allows for fast unchecked shrinking of the slice.
     ///
@@ -40,6 +40,25 @@ fn test_bytes_slice_range(range: impl RangeBounds<usize>, expected: &str) {
     );
 }
 
+#[allow(clippy::reversed_empty_ranges)]
+#[test_case(0..0, Some(""); "0 to 0 is empty")]
+#[test_case(.., Some("hello"); "full range is hello")]
+#[test_case(1..=3, Some("ell"); "1 to 3 inclusive is ell")]
+#[test_case(3.., Some("lo"); "3 to end is lo")]
+#[test_case(0..6, None; "0 to 6 is out of bounds")]
+#[test_case(0..=5, None; "0 to 5 inclusive is out of bounds")]
+#[test_case(6.., None; "6 to end is out of bounds")]
+#[test_case(4..3, None; "4 to 3 is inverted")]
+#[test_case(3..=usize::MAX, None; "3 to usize::MAX inclusive overflows")]
+fn test_bytes_try_slice_range(range: impl RangeBounds<usize>, expected: Option<&str>) {
+    assert_eq!(
+        hello()
+            .try_slice(range)
+            .map(|bytes| str::from_utf8(bytes.as_ref()).unwrap().to_string()),
+        expected.map(str::to_string)
+    );
+}
+
 #[test_case(hello(), b"", ""; "any empty slice is empty")]
 #[test_case(hello(), &hello_slice(..), "hello"; "full range is hello")]
 #[test_case(hello(), &hello_slice(2..4), "ll"; "2 to 4 is ll")]
@@ -127,6 +146,28 @@ fn test_bytes_slice_ref_is_shallow() {
         .unwrap();
 }
 
+#[test]
+fn test_bytes_as_static() {
+    static HELLO: &[u8] = b"hello";
+    let bytes = Bytes::from_static(HELLO);
+    assert_eq!(bytes.as_static().map(<[u8]>::as_ptr), Some(HELLO.as_ptr()));
+    assert_eq!(bytes.slice(1..3).as_static(), Some(&b"el"[..]));
+
+    let bytes = Bytes::copy_from_slice(HELLO);
+    assert_eq!(bytes.as_static(), None);
+    assert_eq!(bytes.slice(1..3).as_static(), None);
+}
+
+#[test]
+fn test_bytes_from_static_str() {
+    const BYTES: Bytes = Bytes::from_static_str("hello");
+    assert_eq!(BYTES.as_ref(), b"hello");
+
+    static HELLO: &str = "hello";
+    let bytes = Bytes::from_static_str(HELLO);
+    assert_eq!(bytes.as_static().map(<[u8]>::as_ptr), Some(HELLO.as_ptr()));
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn test_bytes_drop_frees_underlying() {
@@ -143,6 +184,61 @@ fn test_bytes_drop_frees_underlying() {
     assert_eq!(get_counter(&counter), 1);
 }
 
+#[test]
+fn test_bytes_chunks() {
+    let bytes = Bytes::copy_from_slice(b"abcdefghijkl");
+
+    let chunks: Vec<Bytes> = bytes.chunks(4).collect();
+    assert_eq!(chunks, [&b"abcd"[..], b"efgh", b"ijkl"]);
+
+    let chunks: Vec<Bytes> = bytes.chunks(5).collect();
+    assert_eq!(chunks, [&b"abcde"[..], b"fghij", b"kl"]);
+    for (i, chunk) in chunks.iter().enumerate() {
+        // The chunks share the storage of the parent
+        assert_eq!(chunk.as_ptr(), bytes.as_ptr().wrapping_add(i * 5));
+    }
+
+    assert_eq!(bytes.chunks(20).collect::<Vec<_>>(), [bytes.clone()]);
+    assert_eq!(Bytes::empty().chunks(4).count(), 0);
+}
+
+#[test]
+#[should_panic(expected = "chunk size must be non-zero")]
+fn test_bytes_chunks_zero_size() {
+    let _ = Bytes::copy_from_slice(b"abc").chunks(0);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_bytes_chunks_keep_underlying() {
+    let underlying = CountingU8::new(vec![1, 2, 3, 4, 5].into());
+    let counter = underlying.counter();
+    let bytes = Bytes::from(underlying);
+    let chunks: Vec<Bytes> = bytes.chunks(2).collect();
+    drop(bytes);
+    assert_eq!(get_counter(&counter), 0);
+    assert_eq!(chunks, [&[1u8, 2][..], &[3, 4], &[5]]);
+    drop(chunks);
+    assert_eq!(get_counter(&counter), 1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_bytes_detach_frees_underlying() {
+    let underlying = CountingU8::new(vec![7; 1024 * 1024].into());
+    let counter = underlying.counter();
+    let large = Bytes::from(underlying);
+    let detached = large.slice(10..15).detach();
+    assert_ne!(detached.as_ptr(), large.as_ptr().wrapping_add(10));
+    drop(large);
+    assert_eq!(get_counter(&counter), 1);
+    assert_eq!(detached.as_ref(), &[7; 5]);
+
+    static HELLO: &[u8] = b"hello";
+    let bytes = Bytes::from_static(HELLO).detach();
+    assert_eq!(bytes.as_static().map(<[u8]>::as_ptr), Some(HELLO.as_ptr()));
+}
+
 struct CountingU8 {
     inner: Box<[u8]>,
     count: Arc<AtomicUsize>,
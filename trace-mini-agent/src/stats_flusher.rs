@@ -67,7 +67,10 @@ impl StatsFlusher for ServerlessStatsFlusher {
 
         debug!("Stats payload to be sent: {stats_payload:?}");
 
-        let serialized_stats_payload = match stats_utils::serialize_stats_payload(stats_payload) {
+        let serialized_stats_payload = match stats_utils::serialize_stats_payload_with_threshold(
+            stats_payload,
+            stats_utils::DEFAULT_COMPRESS_MIN_BYTES,
+        ) {
             Ok(res) => res,
             Err(err) => {
                 error!("Failed to serialize stats payload, dropping stats: {err}");
@@ -75,7 +78,7 @@ impl StatsFlusher for ServerlessStatsFlusher {
             }
         };
 
-        match stats_utils::send_stats_payload(
+        match stats_utils::send_serialized_stats_payload(
             serialized_stats_payload,
             &config.trace_stats_intake,
             config.trace_stats_intake.api_key.as_ref().unwrap(),
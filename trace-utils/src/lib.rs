@@ -3,6 +3,7 @@
 
 pub mod config_utils;
 pub mod msgpack_decoder;
+pub mod msgpack_encoder;
 pub mod send_data;
 pub mod stats_utils;
 #[cfg(any(test, feature = "test-utils"))]
@@ -0,0 +1,244 @@
+// Copyright 2024-Present Datadog, Inc. https://www.datadoghq.com/
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::span_v04::Span;
+use rmp_serde::encode::Error;
+use serde::Serialize;
+use std::io;
+
+/// A `Write` implementation which discards all bytes and only keeps track of their count.
+struct CountLength(usize);
+
+impl io::Write for CountLength {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn encode<W: io::Write>(writer: W, traces: &[Vec<Span>]) -> Result<W, Error> {
+    let mut serializer = rmp_serde::Serializer::new(writer).with_struct_map();
+    traces.serialize(&mut serializer)?;
+    Ok(serializer.into_inner())
+}
+
+/// Encodes a collection of traces into a slice of bytes, advancing the slice past the written
+/// bytes.
+///
+/// # Arguments
+///
+/// * `slice` - A mutable reference to the byte slice the traces are written to.
+/// * `traces` - A list of traces, each being a list of v04 spans.
+///
+/// # Errors
+///
+/// Returns an error if the slice is too small to hold the encoded traces. Use
+/// [`serialized_size`] to compute the required length beforehand.
+pub fn write_to_slice(slice: &mut &mut [u8], traces: &[Vec<Span>]) -> Result<(), Error> {
+    encode(slice, traces)?;
+    Ok(())
+}
+
+/// Encodes a collection of traces into a newly allocated vector of bytes.
+pub fn to_vec(traces: &[Vec<Span>]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(serialized_size(traces));
+    encode(&mut buf, traces).expect("infallible: writing to a Vec cannot fail");
+    buf
+}
+
+/// Computes the exact number of bytes the traces occupy once encoded as msgpack, without
+/// writing them anywhere.
+///
+/// This allows sizing a buffer (e.g. shared memory) precisely before calling
+/// [`write_to_slice`].
+pub fn serialized_size(traces: &[Vec<Span>]) -> usize {
+    encode(CountLength(0), traces).map_or(0, |counter| counter.0)
+}
+
+/// Size of the msgpack header of an array of `len` elements.
+fn array_header_size(len: usize) -> usize {
+    match len {
+        0..=15 => 1,
+        16..=0xffff => 3,
+        _ => 5,
+    }
+}
+
+/// Partitions traces into batches each encoding to at most `max_bytes`, e.g. to stay below the
+/// `max_request_bytes` accepted by the agent. Traces are kept whole and in order.
+///
+/// # Errors
+///
+/// Returns an error if a single trace alone encodes to more than `max_bytes`.
+pub fn split_by_size(
+    traces: Vec<Vec<Span>>,
+    max_bytes: usize,
+) -> anyhow::Result<Vec<Vec<Vec<Span>>>> {
+    let mut batches = vec![];
+    let mut batch = vec![];
+    let mut batch_size = 0;
+    for trace in traces {
+        // The encoded traces of a batch, without the array header
+        let trace_size = serialized_size(std::slice::from_ref(&trace)) - array_header_size(1);
+        if array_header_size(1) + trace_size > max_bytes {
+            anyhow::bail!(
+                "a trace of {} bytes exceeds the budget of {max_bytes} bytes",
+                array_header_size(1) + trace_size
+            );
+        }
+        if !batch.is_empty()
+            && array_header_size(batch.len() + 1) + batch_size + trace_size > max_bytes
+        {
+            batches.push(std::mem::take(&mut batch));
+            batch_size = 0;
+        }
+        batch.push(trace);
+        batch_size += trace_size;
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span_v04::SpanLink;
+    use std::collections::HashMap;
+    use tinybytes::BytesString;
+
+    fn bytes_string(str: &'static str) -> BytesString {
+        BytesString::from_slice(str.as_bytes()).unwrap()
+    }
+
+    fn assert_size_matches(traces: Vec<Vec<Span>>) {
+        let size = serialized_size(&traces);
+        let mut buf = vec![0u8; size + 16];
+        let mut slice = buf.as_mut_slice();
+        write_to_slice(&mut slice, &traces).expect("Encoding failed");
+        let written = size + 16 - slice.len();
+
+        assert_eq!(size, written);
+        assert_eq!(size, to_vec(&traces).len());
+    }
+
+    #[test]
+    fn test_serialized_size_empty() {
+        assert_size_matches(vec![]);
+        assert_size_matches(vec![vec![]]);
+    }
+
+    #[test]
+    fn test_serialized_size_simple_span() {
+        assert_size_matches(vec![vec![Span {
+            name: bytes_string("span_name"),
+            service: bytes_string("service"),
+            trace_id: 1,
+            span_id: 2,
+            ..Default::default()
+        }]]);
+    }
+
+    #[test]
+    fn test_serialized_size_full_span() {
+        let span = Span {
+            service: bytes_string("service"),
+            name: bytes_string("name"),
+            resource: bytes_string("resource"),
+            r#type: bytes_string("web"),
+            trace_id: u64::MAX,
+            span_id: 1 << 40,
+            parent_id: 300,
+            start: -1,
+            duration: i64::MAX,
+            error: 1,
+            meta: HashMap::from([
+                (bytes_string("key"), bytes_string("value")),
+                (bytes_string("http.method"), bytes_string("GET")),
+            ]),
+            metrics: HashMap::from([(bytes_string("_sampling_priority_v1"), 1.0)]),
+            meta_struct: HashMap::from([(bytes_string("appsec"), vec![1, 2, 200])]),
+            span_links: vec![SpanLink {
+                trace_id: 1,
+                trace_id_high: 2,
+                span_id: 3,
+                attributes: HashMap::from([(bytes_string("link"), bytes_string("attr"))]),
+                tracestate: bytes_string("dd=s:1"),
+                flags: 1,
+            }],
+        };
+        assert_size_matches(vec![vec![span.clone()]]);
+        assert_size_matches(vec![vec![span.clone(), span.clone()], vec![span]]);
+    }
+
+    #[test]
+    fn test_write_to_slice_too_small() {
+        let traces = vec![vec![Span {
+            name: bytes_string("span_name"),
+            ..Default::default()
+        }]];
+        let mut buf = vec![0u8; serialized_size(&traces) - 1];
+        let mut slice = buf.as_mut_slice();
+        assert!(write_to_slice(&mut slice, &traces).is_err());
+    }
+
+    fn trace(span_id: u64) -> Vec<Span> {
+        vec![Span {
+            name: bytes_string("span_name"),
+            service: bytes_string("service"),
+            trace_id: span_id,
+            span_id,
+            ..Default::default()
+        }]
+    }
+
+    #[test]
+    fn test_split_by_size_single_batch() {
+        let traces = vec![trace(1), trace(2), trace(3)];
+        let size = serialized_size(&traces);
+
+        let batches = split_by_size(traces.clone(), size).unwrap();
+        assert_eq!(batches, vec![traces]);
+        assert!(split_by_size(vec![], size).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_by_size_two_batches() {
+        let traces = vec![trace(1), trace(2), trace(3), trace(4)];
+        let max_bytes = serialized_size(&traces[..3]);
+
+        let batches = split_by_size(traces.clone(), max_bytes).unwrap();
+        assert_eq!(batches, vec![traces[..3].to_vec(), traces[3..].to_vec()]);
+        for batch in &batches {
+            assert!(serialized_size(batch) <= max_bytes);
+        }
+    }
+
+    #[test]
+    fn test_split_by_size_many_traces() {
+        // Batches of more than 15 traces have a larger array header
+        let traces: Vec<_> = (0..40).map(trace).collect();
+        let max_bytes = serialized_size(&traces[..20]);
+
+        let batches = split_by_size(traces.clone(), max_bytes).unwrap();
+        assert_eq!(batches.concat(), traces);
+        for batch in &batches {
+            assert!(serialized_size(batch) <= max_bytes);
+        }
+    }
+
+    #[test]
+    fn test_split_by_size_trace_too_large() {
+        let traces = vec![trace(1), vec![trace(2)[0].clone(); 10]];
+        let max_bytes = serialized_size(&traces[..1]);
+
+        assert!(split_by_size(traces, max_bytes).is_err());
+    }
+}
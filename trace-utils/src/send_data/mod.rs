@@ -239,12 +239,14 @@ impl SendData {
             Duration::from_millis(self.target.timeout_ms),
             if let Some(proxy) = http_proxy {
                 let proxy = Proxy::new(Intercept::Https, proxy.parse().unwrap());
+                // The proxy resolves the host itself, so the resolved address of the target, if
+                // any, is not used: pinning it here would redirect the connection to the proxy.
                 let proxy_connector =
                     ProxyConnector::from_proxy(connector::Connector::default(), proxy).unwrap();
                 Client::builder().build(proxy_connector).request(req)
             } else {
                 Client::builder()
-                    .build(connector::Connector::default())
+                    .build(connector::Connector::for_endpoint(&self.target))
                     .request(req)
             },
         )
@@ -11,6 +11,15 @@ use datadog_trace_protobuf::pb;
 use ddcommon::connector::Connector;
 use ddcommon::Endpoint;
 
+/// Payloads smaller than this many bytes are not worth compressing by default
+pub const DEFAULT_COMPRESS_MIN_BYTES: usize = 512;
+
+/// A serialized stats payload, gzipped unless it was smaller than the compression threshold.
+pub struct SerializedStatsPayload {
+    pub data: Vec<u8>,
+    pub gzipped: bool,
+}
+
 pub async fn get_stats_from_request_body(body: Body) -> anyhow::Result<pb::ClientStatsPayload> {
     let buffer = body.collect().await?.aggregate();
 
@@ -39,11 +48,28 @@ pub fn construct_stats_payload(stats: Vec<pb::ClientStatsPayload>) -> pb::StatsP
 }
 
 pub fn serialize_stats_payload(payload: pb::StatsPayload) -> anyhow::Result<Vec<u8>> {
+    Ok(serialize_stats_payload_with_threshold(payload, 0)?.data)
+}
+
+/// Serializes the payload, gzipping it only if it is at least `compress_min_bytes` long.
+pub fn serialize_stats_payload_with_threshold(
+    payload: pb::StatsPayload,
+    compress_min_bytes: usize,
+) -> anyhow::Result<SerializedStatsPayload> {
     let msgpack = rmp_serde::to_vec_named(&payload)?;
+    if msgpack.len() < compress_min_bytes {
+        return Ok(SerializedStatsPayload {
+            data: msgpack,
+            gzipped: false,
+        });
+    }
     let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
     encoder.write_all(&msgpack)?;
     match encoder.finish() {
-        Ok(res) => Ok(res),
+        Ok(data) => Ok(SerializedStatsPayload {
+            data,
+            gzipped: true,
+        }),
         Err(e) => anyhow::bail!("Error serializing stats payload: {e}"),
     }
 }
@@ -53,15 +79,36 @@ pub async fn send_stats_payload(
     target: &Endpoint,
     api_key: &str,
 ) -> anyhow::Result<()> {
-    let req = Request::builder()
+    send_serialized_stats_payload(
+        SerializedStatsPayload {
+            data,
+            gzipped: true,
+        },
+        target,
+        api_key,
+    )
+    .await
+}
+
+/// Sends a payload from [`serialize_stats_payload_with_threshold`], with a `Content-Encoding`
+/// header only if it was gzipped.
+pub async fn send_serialized_stats_payload(
+    payload: SerializedStatsPayload,
+    target: &Endpoint,
+    api_key: &str,
+) -> anyhow::Result<()> {
+    let mut req = Request::builder()
         .method(Method::POST)
         .uri(target.url.clone())
-        .header("Content-Type", "application/msgpack")
-        .header("Content-Encoding", "gzip")
+        .header("Content-Type", "application/msgpack");
+    if payload.gzipped {
+        req = req.header("Content-Encoding", "gzip");
+    }
+    let req = req
         .header("DD-API-KEY", api_key)
-        .body(Body::from(data.clone()))?;
+        .body(Body::from(payload.data))?;
 
-    let client: Client<_, hyper::Body> = Client::builder().build(Connector::default());
+    let client: Client<_, hyper::Body> = Client::builder().build(Connector::for_endpoint(target));
     match client.request(req).await {
         Ok(response) => {
             if response.status() != StatusCode::ACCEPTED {
@@ -81,6 +128,8 @@ mod tests {
     use datadog_trace_protobuf::pb::{
         ClientGroupedStats, ClientStatsBucket, ClientStatsPayload, Trilean::NotSet,
     };
+    use ddcommon::Endpoint;
+    use httpmock::MockServer;
     use hyper::Request;
     use serde_json::Value;
 
@@ -293,4 +342,49 @@ mod tests {
             res.unwrap_err()
         );
     }
+
+    #[tokio::test]
+    #[cfg_attr(miri, ignore)]
+    async fn test_send_stats_payload_compress_min_bytes() {
+        let server = MockServer::start_async().await;
+        let raw_mock = server
+            .mock_async(|when, then| {
+                when.path("/raw").header_missing("Content-Encoding");
+                then.status(202);
+            })
+            .await;
+        let gzipped_mock = server
+            .mock_async(|when, then| {
+                when.path("/gzipped").header("Content-Encoding", "gzip");
+                then.status(202);
+            })
+            .await;
+
+        let small_payload = stats_utils::construct_stats_payload(vec![]);
+        let large_payload = stats_utils::construct_stats_payload(vec![ClientStatsPayload {
+            hostname: "h".repeat(stats_utils::DEFAULT_COMPRESS_MIN_BYTES),
+            ..Default::default()
+        }]);
+        for (payload, path, gzipped) in [
+            (small_payload, "/raw", false),
+            (large_payload, "/gzipped", true),
+        ] {
+            let serialized = stats_utils::serialize_stats_payload_with_threshold(
+                payload,
+                stats_utils::DEFAULT_COMPRESS_MIN_BYTES,
+            )
+            .unwrap();
+            assert_eq!(serialized.gzipped, gzipped);
+            stats_utils::send_serialized_stats_payload(
+                serialized,
+                &Endpoint::from_slice(&server.url(path)),
+                "api-key",
+            )
+            .await
+            .unwrap();
+        }
+
+        raw_mock.assert_async().await;
+        gzipped_mock.assert_async().await;
+    }
 }
@@ -306,7 +306,14 @@ pub(crate) fn cmp_send_data_payloads(a: &pb::TracerPayload, b: &pb::TracerPayloa
         .then(a.app_version.cmp(&b.app_version))
 }
 
-pub fn coalesce_send_data(mut data: Vec<SendData>) -> Vec<SendData> {
+pub fn coalesce_send_data(data: Vec<SendData>) -> Vec<SendData> {
+    coalesce_send_data_with_max_size(data, MAX_PAYLOAD_SIZE / 2)
+}
+
+/// Same as `coalesce_send_data`, but payloads are only merged while their combined size stays below
+/// `max_size` bytes, e.g. the maximum request size accepted by the agent. A payload individually
+/// exceeding `max_size` is returned as is.
+pub fn coalesce_send_data_with_max_size(mut data: Vec<SendData>, max_size: usize) -> Vec<SendData> {
     // TODO trace payloads with identical data except for chunk could be merged?
 
     data.sort_unstable_by(|a, b| {
@@ -323,8 +330,7 @@ pub fn coalesce_send_data(mut data: Vec<SendData>) -> Vec<SendData> {
             // Size is only an approximation. In practice it won't vary much, but be safe here.
             // We also don't care about the exact maximum size, like two 25 MB or one 50 MB request
             // has similar results. The primary goal here is avoiding many small requests.
-            // TODO: maybe make the MAX_PAYLOAD_SIZE configurable?
-            if a.size + b.size < MAX_PAYLOAD_SIZE / 2 {
+            if a.size + b.size < max_size {
                 // Note: dedup_by drops a, and retains b.
                 b.tracer_payloads.append(&mut a.tracer_payloads);
                 b.size += a.size;